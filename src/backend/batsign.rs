@@ -4,9 +4,13 @@
 //!
 //! The Batsign service allows sending email notifications by making HTTP POST
 //! requests to a specific URL format.
-use reqwest::blocking::Client;
-use rppal::gpio::Level;
+use reqwest::blocking::{Client, ClientBuilder};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::notify::Context;
+use crate::settings::{self, BatsignRequestStyle, LineEnding};
 
 /// Batsign backend for sending notifications via the free Batsign service.
 pub struct BatsignBackend {
@@ -18,15 +22,188 @@ pub struct BatsignBackend {
 
     /// Batsign URL to which the notification will be sent.
     url: String,
+
+    /// How the message is packaged into the HTTP request.
+    request_style: BatsignRequestStyle,
+
+    /// Line ending used in the message.
+    line_ending: LineEnding,
+
+    /// Options shared by the HTTP-based backends.
+    options: super::HttpOptions,
+
+    /// Number of consecutive failed requests, for the retry timeout schedule.
+    failed_attempts: usize,
+
+    /// HTTP status of the response to the last request, if there was one.
+    last_status: Option<u16>,
+
+    /// Optional signer adding an HMAC signature header to each request.
+    signer: Option<super::HmacSigner>,
+
+    /// Optional pin of the addresses the host resolves to.
+    dns_pin: Option<DnsPin>,
 }
 
 impl BatsignBackend {
     /// Creates a new instance of the BatsignBackend with the provided HTTP client and Batsign URL.
-    pub fn new(id: usize, client: Arc<Client>, url: &str) -> Self {
+    pub fn new(
+        id: usize,
+        client: Arc<Client>,
+        url: &str,
+        request_style: BatsignRequestStyle,
+        options: super::HttpOptions,
+    ) -> Self {
         Self {
             id,
             client,
             url: url.to_owned(),
+            request_style,
+            line_ending: LineEnding::Lf,
+            options,
+            failed_attempts: 0,
+            last_status: None,
+            signer: None,
+            dns_pin: None,
+        }
+    }
+
+    /// Uses the passed line ending in messages, rather than LF.
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Signs each request with the passed signer.
+    pub fn with_signer(mut self, signer: super::HmacSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Sends through a client with the addresses of the passed pin, resolving
+    /// them right away.
+    pub fn with_dns_pin(mut self, dns_pin: DnsPin) -> Self {
+        self.dns_pin = Some(dns_pin);
+        self.refresh_dns_pin();
+        self
+    }
+
+    /// Resolves the pinned host anew if it is due, and switches to a client
+    /// with the new addresses if they changed.
+    fn refresh_dns_pin(&mut self) {
+        if let Some(dns_pin) = &mut self.dns_pin
+            && let Some(client) = dns_pin.refresh(Instant::now())
+        {
+            self.client = Arc::new(client);
+        }
+    }
+}
+
+/// Addresses a Batsign host resolves to, pinned in the HTTP client so that
+/// sending doesn't depend on DNS resolving at the time.
+///
+/// The host is resolved anew once the addresses are older than the refresh
+/// interval. If that fails, the old addresses are kept for another interval,
+/// which is the point; until the host has resolved once, it is tried on
+/// every send.
+pub struct DnsPin {
+    /// Host name of the URL.
+    domain: String,
+
+    /// Port of the URL.
+    port: u16,
+
+    /// Addresses the host last resolved to, empty until it first has.
+    addrs: Vec<SocketAddr>,
+
+    /// When the host was last resolved, or `None` if it never has been.
+    resolved_at: Option<Instant>,
+
+    /// Age after which the addresses are resolved anew.
+    refresh_interval: Duration,
+
+    /// Returns a builder of the HTTP client, to which the addresses are added.
+    client_builder: Box<dyn Fn() -> ClientBuilder + Send>,
+}
+
+impl DnsPin {
+    /// Creates a pin for the host of the passed URL, or returns `None` if the
+    /// URL can't be parsed or its host is an IP address.
+    pub fn new(
+        url: &str,
+        refresh_interval: Duration,
+        client_builder: Box<dyn Fn() -> ClientBuilder + Send>,
+    ) -> Option<Self> {
+        let url = reqwest::Url::parse(url).ok()?;
+
+        Some(Self {
+            domain: url.domain()?.to_string(),
+            port: url.port_or_known_default()?,
+            addrs: Vec::new(),
+            resolved_at: None,
+            refresh_interval,
+            client_builder,
+        })
+    }
+
+    /// Resolves the host if it is due, returning a new client if the
+    /// addresses changed.
+    fn refresh(&mut self, now: Instant) -> Option<Client> {
+        if self
+            .resolved_at
+            .is_some_and(|at| now.duration_since(at) < self.refresh_interval)
+        {
+            return None;
+        }
+
+        let addrs: Vec<SocketAddr> = match (self.domain.as_str(), self.port).to_socket_addrs() {
+            Ok(addrs) => addrs.collect(),
+            Err(e) => {
+                eprintln!("[!] Failed to resolve Batsign host {}: {e}", self.domain);
+                Vec::new()
+            }
+        };
+
+        if addrs.is_empty() {
+            if !self.addrs.is_empty() {
+                // Keep the old addresses until the next refresh.
+                eprintln!(
+                    "[!] Keeping the pinned address(es) of {} for now.",
+                    self.domain
+                );
+                self.resolved_at = Some(now);
+            }
+
+            return None;
+        }
+
+        self.resolved_at = Some(now);
+
+        if addrs == self.addrs {
+            return None;
+        }
+
+        match (self.client_builder)()
+            .resolve_to_addrs(&self.domain, &addrs)
+            .build()
+        {
+            Ok(client) => {
+                println!(
+                    "Pinned Batsign host {} to {}.",
+                    self.domain,
+                    addrs
+                        .iter()
+                        .map(|addr| addr.ip().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                self.addrs = addrs;
+                Some(client)
+            }
+            Err(e) => {
+                eprintln!("[!] Failed to build HTTP client for {}: {e}", self.domain);
+                None
+            }
         }
     }
 }
@@ -43,18 +220,164 @@ impl super::Backend for BatsignBackend {
     }
 
     /// Builds the message to be sent via Batsign.
-    fn build_message(&self, _level: Level, template: &str) -> String {
-        template.to_owned()
+    fn build_message(&self, _ctx: &Context, template: &str) -> String {
+        apply_line_ending(template, self.line_ending)
+    }
+
+    /// Builds a notice to be sent via Batsign, with the subject on a `Subject:` line.
+    fn build_oneshot(&self, _ctx: &Context, subject: &str, body: &str) -> Option<String> {
+        Some(apply_line_ending(
+            &format!("Subject: {subject}\n{body}"),
+            self.line_ending,
+        ))
     }
 
     /// Sends a notification via the Batsign backend by making a POST request
-    /// to the specified URL with the message as the body.
-    fn send_message(&mut self, message: &str) -> Result<(), String> {
-        match self.client.post(&self.url).body(message.to_owned()).send() {
-            Ok(resp) if resp.status().is_success() => Ok(()),
-            Ok(resp) => Err(format!("HTTP {}", resp.status())),
-            Err(e) => Err(e.to_string()),
+    /// to the specified URL, packaging the message as per the request style.
+    fn send_message(&mut self, _ctx: &Context, message: &str) -> Result<(), String> {
+        self.refresh_dns_pin();
+
+        let (content_type, payload) = match self.request_style {
+            BatsignRequestStyle::RawSubject => (None, message.to_owned()),
+            BatsignRequestStyle::FormEncoded => {
+                let (subject, body) = split_subject(message);
+                let form = form_urlencoded::Serializer::new(String::new())
+                    .append_pair("subject", subject)
+                    .append_pair("body", body)
+                    .finish();
+
+                (Some("application/x-www-form-urlencoded"), form)
+            }
+            BatsignRequestStyle::Json => {
+                let (subject, body) = split_subject(message);
+                let json = serde_json::json!({ "subject": subject, "body": body });
+                (Some("application/json"), json.to_string())
+            }
+        };
+
+        let mut request = self.client.post(&self.url);
+
+        if let Some(content_type) = content_type {
+            request = request.header("Content-Type", content_type);
         }
+
+        if let Some(signer) = &self.signer {
+            request = signer.sign(request, payload.as_bytes());
+        }
+
+        super::send_timed(
+            request.body(payload),
+            &self.url,
+            &self.options,
+            &mut self.failed_attempts,
+            &mut self.last_status,
+        )
+    }
+
+    /// Returns the scheme and host of the URL, leaving out the rest.
+    fn target(&self) -> String {
+        super::redact_url(&self.url)
+    }
+
+    /// Returns the HTTP status of the response to the last request.
+    fn last_status(&self) -> Option<u16> {
+        self.last_status
+    }
+}
+
+/// Fetches a remote list of Batsign URLs over HTTP, returning the response body.
+///
+/// The request is bounded by the passed `timeout`.
+pub fn fetch_url_list(source: &str, timeout: Duration) -> Result<String, String> {
+    let client = Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    match client.get(source).send() {
+        Ok(resp) if resp.status().is_success() => resp.text().map_err(|e| e.to_string()),
+        Ok(resp) => Err(format!("HTTP {}", resp.status())),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses a list of Batsign URLs, one or more per line as in the
+/// environment variable, skipping empty lines and lines starting with `#`.
+pub fn parse_url_list(body: &str) -> Vec<String> {
+    let lines: Vec<String> = body.lines().map(str::to_string).collect();
+
+    settings::trim_vec_of_strings(&lines)
+        .iter()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| settings::split_url_list(line))
+        .collect()
+}
+
+/// Returns the distinct `host:port` pairs the passed URLs point to, in order,
+/// skipping any that can't be parsed.
+pub fn hosts_of(urls: &[String]) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+
+    for url in urls {
+        let Ok(url) = reqwest::Url::parse(url) else {
+            continue;
+        };
+
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            continue;
+        };
+
+        let host = format!("{host}:{port}");
+
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+
+    hosts
+}
+
+/// Checks that the passed `host:port` resolves and accepts a TCP connection
+/// within `timeout`, trying each address it resolves to.
+pub fn check_reachable(host: &str, timeout: Duration) -> Result<(), String> {
+    let addrs: Vec<_> = host
+        .to_socket_addrs()
+        .map_err(|e| format!("failed to resolve: {e}"))?
+        .collect();
+
+    let mut last_error = String::from("resolved to no addresses");
+
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = format!("failed to connect to {addr}: {e}"),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Rewrites the line endings of a message to the passed style, whichever
+/// style it was written in.
+fn apply_line_ending(message: &str, line_ending: LineEnding) -> String {
+    let message = message.replace("\r\n", "\n");
+
+    match line_ending {
+        LineEnding::Lf => message,
+        LineEnding::Crlf => message.replace('\n', "\r\n"),
+    }
+}
+
+/// Splits a message into its subject and body.
+///
+/// The subject is taken from a leading `Subject:` line, if there is one;
+/// otherwise it is empty and the whole message is the body.
+fn split_subject(message: &str) -> (&str, &str) {
+    let (first, rest) = message.split_once('\n').unwrap_or((message, ""));
+
+    match first.strip_prefix("Subject:") {
+        Some(subject) => (subject.trim(), rest),
+        None => ("", message),
     }
 }
 
@@ -98,4 +421,113 @@ mod tests {
         let email = super::get_email_from_batsign_url(url);
         assert_eq!(email, None);
     }
+
+    #[test]
+    fn test_split_subject() {
+        assert_eq!(
+            super::split_subject("Subject: PellX Alarm\nBurner is down.\n"),
+            ("PellX Alarm", "Burner is down.\n")
+        );
+        assert_eq!(super::split_subject("Subject: Only"), ("Only", ""));
+        assert_eq!(
+            super::split_subject("No subject\nhere"),
+            ("", "No subject\nhere")
+        );
+    }
+
+    #[test]
+    fn test_apply_line_ending() {
+        use crate::settings::LineEnding;
+
+        let message = "Subject: PellX Alarm\nBurner is down.\r\nCheck it.\n";
+        assert_eq!(
+            super::apply_line_ending(message, LineEnding::Lf),
+            "Subject: PellX Alarm\nBurner is down.\nCheck it.\n"
+        );
+        assert_eq!(
+            super::apply_line_ending(message, LineEnding::Crlf),
+            "Subject: PellX Alarm\r\nBurner is down.\r\nCheck it.\r\n"
+        );
+        assert_eq!(
+            super::split_subject(&super::apply_line_ending(message, LineEnding::Crlf)),
+            ("PellX Alarm", "Burner is down.\r\nCheck it.\r\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_url_list() {
+        let body = "# Recipients\n\
+            https://batsign.me/at/test@example.com/token\n\
+            \n\
+            \t https://batsign.me/at/example@test.com/token  \n\
+            # https://batsign.me/at/disabled@example.com/token\n\
+            https://batsign.me/at/a/b, https://batsign.me/at/c/d\n";
+
+        let expected = vec![
+            "https://batsign.me/at/test@example.com/token".to_string(),
+            "https://batsign.me/at/example@test.com/token".to_string(),
+            "https://batsign.me/at/a/b".to_string(),
+            "https://batsign.me/at/c/d".to_string(),
+        ];
+
+        assert_eq!(super::parse_url_list(body), expected);
+        assert!(super::parse_url_list("").is_empty());
+    }
+
+    #[test]
+    fn test_hosts_of() {
+        let urls = [
+            "https://batsign.me/at/test@example.com/token",
+            "https://batsign.me/at/example@test.com/token",
+            "http://mirror.example:8080/at/test@example.com/token",
+            "not a url",
+        ]
+        .map(String::from);
+
+        assert_eq!(
+            super::hosts_of(&urls),
+            vec!["batsign.me:443", "mirror.example:8080"]
+        );
+    }
+
+    #[test]
+    fn test_check_reachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        assert_eq!(
+            super::check_reachable(&addr, super::Duration::from_secs(1)),
+            Ok(())
+        );
+
+        drop(listener);
+        assert!(super::check_reachable(&addr, super::Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_dns_pin() {
+        let interval = super::Duration::from_secs(3600);
+        let builder = || Box::new(super::Client::builder) as Box<_>;
+
+        assert!(super::DnsPin::new("https://127.0.0.1/at/test", interval, builder()).is_none());
+        assert!(super::DnsPin::new("not a url", interval, builder()).is_none());
+
+        let mut pin =
+            super::DnsPin::new("http://localhost:8080/at/test", interval, builder()).unwrap();
+        let now = super::Instant::now();
+
+        assert!(pin.refresh(now).is_some());
+        assert!(pin.addrs.iter().all(|addr| addr.port() == 8080));
+
+        // Not due yet, and then due but resolving to the same addresses.
+        assert!(pin.refresh(now + interval / 2).is_none());
+        assert!(pin.refresh(now + interval).is_none());
+        assert_eq!(pin.resolved_at, Some(now + interval));
+
+        // A host that fails to resolve keeps its old addresses.
+        let addrs = pin.addrs.clone();
+        pin.domain = String::from("pellx-monitor.invalid");
+        assert!(pin.refresh(now + interval * 2).is_none());
+        assert_eq!(pin.addrs, addrs);
+        assert_eq!(pin.resolved_at, Some(now + interval * 2));
+    }
 }
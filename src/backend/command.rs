@@ -0,0 +1,128 @@
+//! Command backend for running a local shell command on alarm and restore.
+//!
+//! This module defines the `CommandBackend` struct, which implements the `Backend`
+//! trait by spawning the configured command through `sh -c`. Details of the
+//! state change are passed to the command as environment variables:
+//!
+//! * `PELLX_PIN`: the GPIO pin number being monitored
+//! * `PELLX_PIN_NAME`: the friendly name of the pin, or `GPIO<n>` if it has none
+//! * `PELLX_STATE`: `alarm` or `restored`
+//! * `PELLX_SINCE`: number of seconds the pin has been at its current level
+use std::process::{Command, Output};
+
+use crate::gpio::Level;
+use crate::notify::Context;
+
+/// Command backend for running a local shell command on state changes.
+pub struct CommandBackend {
+    /// Unique identifier for the command backend instance, used for logging and identification purposes.
+    id: usize,
+}
+
+impl CommandBackend {
+    /// Creates a new instance of CommandBackend.
+    pub fn new(id: usize) -> Self {
+        Self { id }
+    }
+}
+
+impl super::Backend for CommandBackend {
+    /// Returns the name of the backend, which is "command" in this case.
+    fn name(&self) -> String {
+        format!("command#{}", self.id)
+    }
+
+    /// Builds the command line to run, which is the template verbatim.
+    fn build_message(&self, _ctx: &Context, template: &str) -> String {
+        template.to_owned()
+    }
+
+    /// Runs the command line through `sh -c`, passing details of the state change
+    /// as environment variables. A non-zero exit status is treated as a failure.
+    fn send_message(&mut self, ctx: &Context, message: &str) -> Result<(), String> {
+        if message.trim().is_empty() {
+            // No command configured for this level
+            return Ok(());
+        }
+
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(message)
+            .envs(environment(ctx))
+            .output()
+            .map_err(|e| e.to_string())?;
+
+        check_status(&output)
+    }
+
+    /// Returns false, as each line of the tail would be run as a command.
+    fn appends_log_tail(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the environment variables passing details of the state change to
+/// a command, as listed in the module documentation.
+pub(super) fn environment(ctx: &Context) -> [(&'static str, String); 4] {
+    let state = match ctx.level {
+        Level::High => "alarm",
+        Level::Low => "restored",
+    };
+
+    [
+        ("PELLX_PIN", ctx.pin_number.to_string()),
+        ("PELLX_PIN_NAME", ctx.pin_name.clone()),
+        ("PELLX_STATE", state.to_string()),
+        (
+            "PELLX_SINCE",
+            ctx.now
+                .saturating_duration_since(ctx.since)
+                .as_secs()
+                .to_string(),
+        ),
+    ]
+}
+
+/// Returns an error with the exit status and anything written to stderr if
+/// the command exited with a non-zero status.
+pub(super) fn check_status(output: &Output) -> Result<(), String> {
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    match stderr.trim() {
+        "" => Err(output.status.to_string()),
+        stderr => Err(format!("{}: {stderr}", output.status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::backend::Backend;
+
+    #[test]
+    fn test_command_environment_and_exit_status() {
+        let mut backend = CommandBackend::new(0);
+        let now = Instant::now();
+        let ctx = Context {
+            since: now - Duration::from_secs(12),
+            ..Context::for_test(Level::High, now)
+        };
+
+        let ok = r#"test "$PELLX_PIN" = 24 && test "$PELLX_PIN_NAME" = boiler && test "$PELLX_STATE" = alarm && test "$PELLX_SINCE" = 12"#;
+        assert_eq!(backend.send_message(&ctx, ok), Ok(()));
+
+        let err = backend
+            .send_message(&ctx, "echo oops >&2; exit 3")
+            .unwrap_err();
+        assert!(err.contains("3"));
+        assert!(err.ends_with("oops"));
+
+        assert_eq!(backend.send_message(&ctx, "  "), Ok(()));
+    }
+}
@@ -0,0 +1,118 @@
+//! Pipe backend for feeding the message to a local shell command.
+//!
+//! This module defines the `PipeBackend` struct, which implements the `Backend`
+//! trait by spawning the configured command through `sh -c` and writing the
+//! message to its stdin, e.g. to hand it off to `mail` or a custom script.
+//! Unlike the command backend, the message is rendered from a template, and the
+//! command line stays the same for alarms and restores. Details of the state
+//! change are passed as environment variables, the same as to commands.
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use super::command::{check_status, environment};
+use crate::notify::Context;
+
+/// Pipe backend for feeding messages to a local shell command.
+pub struct PipeBackend {
+    /// Unique identifier for the pipe backend instance, used for logging and identification purposes.
+    id: usize,
+
+    /// Shell command line the message is piped to.
+    command: String,
+}
+
+impl PipeBackend {
+    /// Creates a new instance of PipeBackend.
+    pub fn new(id: usize, command: &str) -> Self {
+        Self {
+            id,
+            command: command.to_owned(),
+        }
+    }
+}
+
+impl super::Backend for PipeBackend {
+    /// Returns the name of the backend, which is "pipe" in this case.
+    fn name(&self) -> String {
+        format!("pipe#{}", self.id)
+    }
+
+    /// Builds the message to pipe, which is the template verbatim.
+    fn build_message(&self, _ctx: &Context, template: &str) -> String {
+        template.to_owned()
+    }
+
+    /// Builds a notice to be piped, with the subject on a `Subject:` line.
+    fn build_oneshot(&self, _ctx: &Context, subject: &str, body: &str) -> Option<String> {
+        Some(format!("Subject: {subject}\n{body}"))
+    }
+
+    /// Runs the command line through `sh -c` and writes the message to its
+    /// stdin. A non-zero exit status is treated as a failure.
+    fn send_message(&mut self, ctx: &Context, message: &str) -> Result<(), String> {
+        if message.trim().is_empty() {
+            // No message configured for this level
+            return Ok(());
+        }
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .envs(environment(ctx))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that exits without reading all of it is judged by its
+            // exit status alone.
+            match stdin.write_all(message.as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("failed to write to stdin: {e}"));
+                }
+                _ => {}
+            }
+        }
+
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        check_status(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::backend::Backend;
+    use crate::gpio::Level;
+
+    #[test]
+    fn test_pipe_stdin_and_exit_status() {
+        let now = Instant::now();
+        let ctx = Context {
+            since: now - Duration::from_secs(5),
+            ..Context::for_test(Level::Low, now)
+        };
+
+        let mut backend = PipeBackend::new(
+            0,
+            r#"test "$(cat)" = "Subject: x
+body" && test "$PELLX_STATE" = restored"#,
+        );
+        let message = backend.build_oneshot(&ctx, "x", "body").unwrap();
+        assert_eq!(backend.send_message(&ctx, &message), Ok(()));
+        assert!(backend.send_message(&ctx, "other").is_err());
+
+        let mut backend = PipeBackend::new(0, "echo oops >&2; exit 3");
+        let err = backend.send_message(&ctx, "message").unwrap_err();
+        assert!(err.contains("3"));
+        assert!(err.ends_with("oops"));
+
+        assert_eq!(backend.send_message(&ctx, "  "), Ok(()));
+    }
+}
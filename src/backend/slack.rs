@@ -1,7 +1,8 @@
 use reqwest::blocking::Client;
-use rppal::gpio::Level;
 use std::sync::Arc;
 
+use crate::notify::Context;
+
 /// Defines the Slack backend for sending notifications to a Slack channel.
 pub struct SlackBackend {
     /// Unique identifier for the Slack backend instance, used for logging and identification purposes.
@@ -12,15 +13,27 @@ pub struct SlackBackend {
 
     /// Slack webhook URL to which the notification will be sent.
     url: String,
+
+    /// Options shared by the HTTP-based backends.
+    options: super::HttpOptions,
+
+    /// Number of consecutive failed requests, for the retry timeout schedule.
+    failed_attempts: usize,
+
+    /// HTTP status of the response to the last request, if there was one.
+    last_status: Option<u16>,
 }
 
 impl SlackBackend {
     /// Creates a new instance of SlackBackend.
-    pub fn new(id: usize, client: Arc<Client>, url: &str) -> Self {
+    pub fn new(id: usize, client: Arc<Client>, url: &str, options: super::HttpOptions) -> Self {
         Self {
             id,
             client,
             url: url.to_owned(),
+            options,
+            failed_attempts: 0,
+            last_status: None,
         }
     }
 }
@@ -33,19 +46,37 @@ impl super::Backend for SlackBackend {
     }
 
     /// Builds the message to be sent via Slack.
-    fn build_message(&self, _level: Level, template: &str) -> String {
+    fn build_message(&self, _ctx: &Context, template: &str) -> String {
         serde_json::json!({ "text": format!("{template}") }).to_string()
     }
 
+    /// Builds a notice to be sent via Slack, with the subject in bold.
+    fn build_oneshot(&self, _ctx: &Context, subject: &str, body: &str) -> Option<String> {
+        Some(serde_json::json!({ "text": format!("*{subject}*\n{body}") }).to_string())
+    }
+
     /// Sends a notification via the Slack backend by making a POST request
     /// to the specified URL with the message as a JSON payload.
-    fn send_message(&mut self, message: &str) -> Result<(), String> {
+    fn send_message(&mut self, _ctx: &Context, message: &str) -> Result<(), String> {
         let json: serde_json::Value = serde_json::from_str(message).expect("internal slack json");
 
-        match self.client.post(&self.url).json(&json).send() {
-            Ok(resp) if resp.status().is_success() => Ok(()),
-            Ok(resp) => Err(format!("HTTP {}", resp.status())),
-            Err(e) => Err(e.to_string()),
-        }
+        let request = self.client.post(&self.url).json(&json);
+        super::send_timed(
+            request,
+            &self.url,
+            &self.options,
+            &mut self.failed_attempts,
+            &mut self.last_status,
+        )
+    }
+
+    /// Returns the scheme and host of the URL, leaving out the rest.
+    fn target(&self) -> String {
+        super::redact_url(&self.url)
+    }
+
+    /// Returns the HTTP status of the response to the last request.
+    fn last_status(&self) -> Option<u16> {
+        self.last_status
     }
 }
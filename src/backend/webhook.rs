@@ -0,0 +1,221 @@
+//! Generic webhook backend for sending JSON payloads to arbitrary receivers.
+//!
+//! This module defines the `WebhookBackend` struct, which implements the `Backend`
+//! trait by POSTing a JSON payload, defined entirely by a template file, to a URL.
+//! This allows for integrating with services like the PagerDuty Events API or
+//! Opsgenie without hardcoding their schemas.
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::notify::Context;
+
+/// Generic webhook backend for sending templated JSON payloads.
+pub struct WebhookBackend {
+    /// Unique identifier for the webhook backend instance, used for logging and identification purposes.
+    id: usize,
+
+    /// HTTP client used to send requests to the webhook receiver.
+    client: Arc<Client>,
+
+    /// Webhook URL to which the payload will be sent.
+    url: String,
+
+    /// Options shared by the HTTP-based backends.
+    options: super::HttpOptions,
+
+    /// Number of consecutive failed requests, for the retry timeout schedule.
+    failed_attempts: usize,
+
+    /// HTTP status of the response to the last request, if there was one.
+    last_status: Option<u16>,
+
+    /// Size in bytes above which payloads are sent gzip-compressed, or `None`
+    /// if the receiver hasn't opted in to compressed payloads.
+    gzip_min_bytes: Option<usize>,
+}
+
+impl WebhookBackend {
+    /// Creates a new instance of WebhookBackend.
+    pub fn new(id: usize, client: Arc<Client>, url: &str, options: super::HttpOptions) -> Self {
+        Self {
+            id,
+            client,
+            url: url.to_owned(),
+            options,
+            failed_attempts: 0,
+            last_status: None,
+            gzip_min_bytes: None,
+        }
+    }
+
+    /// Sends payloads larger than the passed size in bytes gzip-compressed,
+    /// if it is set.
+    pub fn with_gzip(mut self, gzip_min_bytes: Option<usize>) -> Self {
+        self.gzip_min_bytes = gzip_min_bytes;
+        self
+    }
+}
+
+impl super::Backend for WebhookBackend {
+    /// Returns the name of the backend, which is "webhook" in this case.
+    fn name(&self) -> String {
+        format!("webhook#{}", self.id)
+    }
+
+    /// Builds the payload to be sent, which is the formatted template verbatim.
+    fn build_message(&self, _ctx: &Context, template: &str) -> String {
+        template.to_owned()
+    }
+
+    /// Builds a notice payload, since the payload templates only cover alarms and restores.
+    fn build_oneshot(&self, ctx: &Context, subject: &str, body: &str) -> Option<String> {
+        let payload = serde_json::json!({
+            "event": "notice",
+            "pin_number": ctx.pin_number,
+            "pin_name": ctx.pin_name,
+            "subject": subject,
+            "body": body,
+        });
+
+        Some(payload.to_string())
+    }
+
+    /// Sends the payload by making a POST request with an `application/json`
+    /// content type, after verifying that it is valid JSON. A payload larger
+    /// than the gzip threshold is compressed, if the receiver opted in.
+    fn send_message(&mut self, _ctx: &Context, message: &str) -> Result<(), String> {
+        validate_json_payload(message)?;
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json");
+
+        request = match self.gzip_min_bytes {
+            Some(min_bytes) if message.len() > min_bytes => {
+                let compressed = gzip(message.as_bytes()).map_err(|e| e.to_string())?;
+
+                if self.options.debug {
+                    println!(
+                        "{}: compressed payload from {} to {} bytes",
+                        super::Backend::name(self),
+                        message.len(),
+                        compressed.len()
+                    );
+                }
+
+                request.header(CONTENT_ENCODING, "gzip").body(compressed)
+            }
+            _ => request.body(message.to_owned()),
+        };
+
+        super::send_timed(
+            request,
+            &self.url,
+            &self.options,
+            &mut self.failed_attempts,
+            &mut self.last_status,
+        )
+    }
+
+    /// Returns the scheme and host of the URL, leaving out the rest.
+    fn target(&self) -> String {
+        super::redact_url(&self.url)
+    }
+
+    /// Returns the HTTP status of the response to the last request.
+    fn last_status(&self) -> Option<u16> {
+        self.last_status
+    }
+
+    /// Returns false, as the tail would follow the end of the JSON payload.
+    fn appends_log_tail(&self) -> bool {
+        false
+    }
+}
+
+/// Compresses the passed data into the gzip format.
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Verifies that a formatted payload is valid JSON.
+pub fn validate_json_payload(payload: &str) -> Result<(), String> {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .map(|_| ())
+        .map_err(|e| format!("payload is not valid JSON: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::read::GzDecoder;
+    use httpmock::prelude::*;
+    use std::io::Read;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::backend::{Backend, TEST_HTTP_OPTIONS};
+    use crate::gpio::Level;
+
+    #[test]
+    fn test_large_payload_is_gzipped_if_opted_in() {
+        let server = MockServer::start();
+        let payload = r#"{"text": "Pellets burner is in an error state."}"#;
+        let mut gzipped = server.mock(|when, then| {
+            when.method(POST)
+                .path("/hook")
+                .header("content-encoding", "gzip");
+            then.status(200);
+        });
+
+        let ctx = Context::for_test(Level::High, Instant::now());
+        let mut backend = WebhookBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url("/hook"),
+            TEST_HTTP_OPTIONS,
+        )
+        .with_gzip(Some(payload.len() - 1));
+        assert_eq!(backend.send_message(&ctx, payload), Ok(()));
+        gzipped.assert_calls(1);
+
+        // At the threshold, it is sent as it is.
+        gzipped.delete();
+        let plain = server.mock(|when, then| {
+            when.method(POST).path("/hook").body(payload);
+            then.status(200);
+        });
+        backend = backend.with_gzip(Some(payload.len()));
+        assert_eq!(backend.send_message(&ctx, payload), Ok(()));
+        plain.assert_calls(1);
+    }
+
+    #[test]
+    fn test_gzip_round_trips() {
+        let payload = (0..200)
+            .map(|i| format!(r#"{{"pin_number": 24, "event": "alarm", "seq": {i}}}"#))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let compressed = gzip(payload.as_bytes()).unwrap();
+        assert!(compressed.len() < payload.len() / 4);
+
+        let mut decompressed = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, payload);
+    }
+
+    #[test]
+    fn test_validate_json_payload() {
+        assert!(validate_json_payload(r#"{"pin": 24, "since": "10s"}"#).is_ok());
+        assert!(validate_json_payload(r#"{"pin": 24, "since": 10s}"#).is_err());
+        assert!(validate_json_payload("").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Measuring the overhead of the monitor loop, for tuning `poll_interval`.
+//!
+//! The pin is read and the readings run through the same `Monitor` and
+//! `TwoLevelNotifier` logic as the real loop, as fast as possible and without
+//! sleeping in between. Nothing is actually sent; the notifiers are the same
+//! silent stand-ins that `--explain` uses.
+
+use std::time::{Duration, Instant};
+
+use crate::duration::format_duration;
+use crate::explain;
+use crate::gpio::PinReader;
+use crate::monitor::Monitor;
+use crate::notify::Context;
+use crate::settings::Settings;
+
+/// Timings gathered over a benchmark run.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Number of iterations run.
+    pub iterations: u64,
+
+    /// Total time spent in all iterations.
+    pub total: Duration,
+
+    /// Time spent in the slowest iteration.
+    pub max_iteration: Duration,
+
+    /// Total time spent reading the pin.
+    pub total_read: Duration,
+
+    /// Time spent in the slowest read of the pin.
+    pub max_read: Duration,
+
+    /// Number of reads that failed.
+    pub read_errors: u64,
+}
+
+impl Report {
+    /// Returns the average time of an iteration.
+    pub fn average_iteration(&self) -> Duration {
+        average(self.total, self.iterations)
+    }
+
+    /// Returns the average time of a read of the pin.
+    pub fn average_read(&self) -> Duration {
+        average(self.total_read, self.iterations)
+    }
+
+    /// Prints the report, comparing the slowest iteration to the poll interval.
+    pub fn print(&self, settings: &Settings) {
+        println!("Iterations                   {}", self.iterations);
+        println!("Failed pin reads             {}", self.read_errors);
+        println!(
+            "Average iteration time       {:.1?}",
+            self.average_iteration()
+        );
+        println!("Max iteration time           {:.1?}", self.max_iteration);
+        println!("Average read latency         {:.1?}", self.average_read());
+        println!("Max read latency             {:.1?}", self.max_read);
+        println!(
+            "Poll interval                {}",
+            format_duration(settings.gpio.poll_interval, settings.duration_format)
+        );
+
+        if self.max_iteration >= settings.gpio.poll_interval {
+            println!();
+            println!(
+                "[!] The slowest iteration took longer than the poll interval; \
+                consider polling less often."
+            );
+        }
+    }
+}
+
+/// Returns the average of the passed total over `count` iterations.
+fn average(total: Duration, count: u64) -> Duration {
+    match u32::try_from(count) {
+        Ok(0) => Duration::ZERO,
+        Ok(count) => total / count,
+        Err(_) => Duration::from_secs_f64(total.as_secs_f64() / count as f64),
+    }
+}
+
+/// Reads the pin and runs the reading through the monitor logic `count`
+/// times, without sleeping, and returns how long it took.
+pub fn run(pin: &mut dyn PinReader, settings: &Settings, count: u64) -> Report {
+    let mut monitor = Monitor::new(
+        settings.gpio.qualify_high_hold,
+        settings.gpio.qualify_low_hold,
+    )
+    .with_startup_alarm(settings.gpio.notify_on_startup_alarm)
+    .with_m_of_n(settings.gpio.m_of_n());
+    let mut notifiers = explain::build_trace_notifiers(settings);
+    let mut report = Report::default();
+
+    for _ in 0..count {
+        let start = Instant::now();
+        let reading = pin.read();
+        let read = start.elapsed();
+
+        match reading {
+            Ok(reading) => {
+                if let Some(level) = monitor.poll(reading, Instant::now()) {
+                    let now = Instant::now();
+                    let since = monitor.since(level).unwrap_or(now);
+                    let ctx = Context {
+                        config_summary: String::new(),
+                        ..Context::new(settings, level, now, since)
+                    };
+
+                    for n in notifiers.iter_mut() {
+                        n.send_notification(&ctx);
+                    }
+                }
+            }
+            Err(_) => report.read_errors += 1,
+        }
+
+        let iteration = start.elapsed();
+        report.iterations += 1;
+        report.total += iteration;
+        report.max_iteration = report.max_iteration.max(iteration);
+        report.total_read += read;
+        report.max_read = report.max_read.max(read);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpio::{Level, Sample, ScriptedPin};
+
+    #[test]
+    fn test_run_counts_iterations() {
+        let samples = vec![Sample {
+            offset: Duration::ZERO,
+            level: Level::High,
+        }];
+        let mut pin = ScriptedPin::new(samples);
+        let report = run(&mut pin, &Settings::default(), 100);
+
+        assert_eq!(report.iterations, 100);
+        assert_eq!(report.read_errors, 0);
+        assert!(report.max_iteration >= report.average_iteration());
+        assert!(report.max_read >= report.average_read());
+        assert!(report.total >= report.total_read);
+    }
+}
@@ -1,35 +1,220 @@
-use clap::Parser;
+use clap::{ArgAction, Parser, ValueEnum, ValueHint};
+use std::net::SocketAddr;
+use std::time::Duration;
 
 use crate::defaults;
+use crate::settings::InputMode;
 
 // Don't have the below be a documenting /// or it will pollute the --help screen.
 // Command-line arguments, which override config file settings.
 #[derive(Parser, Clone)]
 #[command(name = defaults::PROGRAM_NAME)]
 #[command(author = defaults::AUTHOR)]
+#[command(
+    after_help = "While running, the monitor responds to these signals (e.g. kill -HUP <pid>):\n  \
+    SIGHUP           Reload the configuration and templates\n  \
+    SIGUSR1          Send the alarm message once through all notifiers as a test\n  \
+    SIGUSR2          Print the current state and a summary\n  \
+    SIGINT, SIGTERM  Shut down cleanly\n\n\
+    The first three can be remapped in the [signals] section of the configuration file,\n\
+    where one of them can also be made to toggle maintenance mode."
+)]
 //#[command(version = defaults::VERSION)]
 pub struct Cli {
     /// Specify an alternate configuration directory
-    #[arg(short = 'c', long, value_name = "path")]
+    #[arg(short = 'c', long, value_name = "path", value_hint = ValueHint::DirPath)]
     pub config_dir: Option<String>,
 
+    /// Specify an alternate configuration file (.toml, .json or .yaml)
+    #[arg(short = 'f', long, value_name = "file", value_hint = ValueHint::FilePath)]
+    pub config_file: Option<String>,
+
+    /// Don't read or write any files in a configuration directory; configure from the command line and environment only
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "config_dir", "config_file", "profile", "save", "diff_config", "config_check", "update_templates",
+            "setup", "watch_config", "history", "status"
+        ]
+    )]
+    pub no_config: bool,
+
+    /// Send Batsign notifications to this URL, in addition to those configured; may be repeated
+    #[cfg(feature = "notifications")]
+    #[arg(short = 'u', long, value_name = "url", action = ArgAction::Append)]
+    pub batsign_url: Vec<String>,
+
+    /// Use only the Batsign URLs given with --batsign-url, instead of adding them to the configured ones
+    #[cfg(feature = "notifications")]
+    #[arg(long, requires = "batsign_url")]
+    pub replace_urls: bool,
+
+    /// Apply the named [profiles.<name>] table of the configuration file on top of the rest
+    #[arg(short = 'p', long, value_name = "name", conflicts_with = "save")]
+    pub profile: Option<String>,
+
     /// Show the resolved configuration and exit
     #[arg(long)]
     pub show: bool,
 
-    /// Print additional debug information
+    /// Print additional debug information (same as -v)
     #[arg(short = 'd', long)]
     pub debug: bool,
 
+    /// Increase output verbosity; repeat for more (-v debug, -vv trace)
+    #[arg(short = 'v', long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Suppress all output except errors
+    #[arg(short = 'q', long, conflicts_with_all = ["debug", "verbose"])]
+    pub quiet: bool,
+
     /// Perform a dry run without sending any notifications
     #[arg(long)]
     pub dry_run: bool,
 
+    /// Exit cleanly after running the monitor for the given duration
+    #[arg(long, value_name = "duration", value_parser = humantime::parse_duration)]
+    pub run_for: Option<Duration>,
+
+    /// Wait this long after starting up before the first poll, overriding startup_delay
+    #[arg(long, value_name = "duration", value_parser = humantime::parse_duration)]
+    pub startup_delay: Option<Duration>,
+
+    /// Add a random extra wait of up to this long to the startup delay, overriding startup_jitter
+    #[arg(long, value_name = "duration", value_parser = humantime::parse_duration)]
+    pub startup_jitter: Option<Duration>,
+
+    /// Don't take the lockfile, allowing several instances to run at once
+    #[arg(long)]
+    pub no_lock: bool,
+
+    /// Notify right away if the pin is already HIGH at startup, without waiting out the hold
+    #[arg(long)]
+    pub since_boot: bool,
+
+    /// Don't send restored notifications, only alarms
+    #[arg(long)]
+    pub no_restored: bool,
+
+    /// Qualify alarms and restores on the first reading, bypassing the holds for this session
+    #[arg(long, conflicts_with = "save")]
+    pub immediate: bool,
+
+    /// Allow durations outside their sensible ranges, such as holds over an hour
+    #[arg(long)]
+    pub allow_extreme: bool,
+
+    /// Sample the pin at startup to infer its baseline level; persisted with --save
+    #[arg(long)]
+    pub calibrate: bool,
+
+    /// Reload the configuration and templates whenever they change on disk
+    #[arg(long, conflicts_with = "save")]
+    pub watch_config: bool,
+
     /// Write configuration to disk
     #[arg(long)]
     pub save: bool,
 
+    /// After --save, send a test notification through the saved notifiers and exit
+    #[arg(long, requires = "save")]
+    pub test: bool,
+
+    /// Show what --save would change in the configuration file and exit, without writing it
+    #[arg(long, conflicts_with_all = ["save", "profile"])]
+    pub diff_config: bool,
+
+    /// Print the configuration --save would write, as TOML, and exit, without writing it
+    #[arg(long, conflicts_with_all = ["save", "diff_config", "profile"])]
+    pub config_check: bool,
+
+    /// Show how the templates differ from the current defaults and offer to replace them, keeping backups, and exit
+    #[arg(long, conflicts_with_all = ["save", "diff_config", "profile"])]
+    pub update_templates: bool,
+
+    /// Ask for the pin, notification URLs and hold times on the terminal, then write the configuration and exit
+    #[arg(long, conflicts_with_all = ["save", "diff_config", "config_check", "update_templates", "profile"])]
+    pub setup: bool,
+
+    /// Probe a single URL for reachability and exit, without sending a notification
+    #[cfg(feature = "notifications")]
+    #[arg(long, value_name = "url")]
+    pub check_url: Option<String>,
+
+    /// Print the last recorded alarms and restores and exit
+    #[arg(long, value_name = "count", num_args = 0..=1, default_missing_value = "20")]
+    pub history: Option<usize>,
+
+    /// Serve the alarm state as JSON at GET /status over HTTP (default 127.0.0.1:9240)
+    #[arg(long, value_name = "addr", num_args = 0..=1, default_missing_value = defaults::status::HTTP_ADDRESS)]
+    pub status_http: Option<SocketAddr>,
+
+    /// Query a running instance for its `status` or recent `history`, or send it `maintenance [<duration>|off]`, and exit
+    #[arg(long, value_name = "request", num_args = 0..=1, default_missing_value = "status")]
+    pub status: Option<String>,
+
+    /// Describe what the monitor will do, then trace the decisions made for a file of timed pin readings if given, and exit
+    #[arg(long, value_name = "file", num_args = 0..=1, value_hint = ValueHint::FilePath)]
+    pub explain: Option<Option<String>>,
+
+    /// Replay a recorded history file through the current configuration and compare the notifications, and exit
+    #[arg(long, value_name = "file", value_hint = ValueHint::FilePath, conflicts_with_all = ["explain", "simulate"])]
+    pub replay: Option<String>,
+
+    /// Time the given number of polls, without sleeping or sending anything, and exit
+    #[arg(long, value_name = "count", value_parser = clap::value_parser!(u64).range(1..))]
+    pub benchmark: Option<u64>,
+
+    /// Replay timed pin readings from a file instead of reading the GPIO pin
+    #[arg(long, value_name = "file", value_hint = ValueHint::FilePath)]
+    pub simulate: Option<String>,
+
+    /// Which level is an alarm and how the pin is pulled, overriding baseline_level and pull
+    #[arg(long, value_name = "mode")]
+    pub input_mode: Option<InputMode>,
+
+    /// Read a fixed or toggling level instead of the GPIO pin; requires --dry-run
+    #[arg(
+        long,
+        value_name = "level",
+        requires = "dry_run",
+        conflicts_with = "simulate"
+    )]
+    pub fake_level: Option<FakeLevel>,
+
     /// Display version information and exit
     #[arg(short = 'V', long)]
     pub version: bool,
+
+    /// Print a completion script for the given shell and exit
+    #[arg(long, value_name = "shell", hide = true)]
+    pub generate_completions: Option<Shell>,
+}
+
+// Shell to generate a completion script for with `--generate-completions`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Shell {
+    // Bash, to be sourced or put in bash-completion's completions directory.
+    Bash,
+
+    // Zsh, to be put in a directory on $fpath as `_pellx_monitor`.
+    Zsh,
+
+    // Fish, to be put in ~/.config/fish/completions.
+    Fish,
+}
+
+// Level read by the fake pin of `--fake-level`. As with `Cli`, the variants
+// aren't documented with /// so as not to expand the --help screen.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum FakeLevel {
+    // Always read HIGH.
+    High,
+
+    // Always read LOW.
+    Low,
+
+    // Toggle between LOW and HIGH every minute, starting LOW.
+    Toggle,
 }
@@ -0,0 +1,49 @@
+//! Shell completion scripts, for `--generate-completions`.
+//!
+//! The scripts are generated by `clap_complete` from the definition of the
+//! command-line arguments, so they stay in step with them.
+
+use clap::Command;
+use clap_complete::Shell as CompleteShell;
+
+use crate::cli::Shell;
+use crate::defaults;
+
+/// Returns the completion script for the passed shell, for the passed command.
+pub fn generate(shell: Shell, cmd: &mut Command) -> String {
+    let shell = match shell {
+        Shell::Bash => CompleteShell::Bash,
+        Shell::Zsh => CompleteShell::Zsh,
+        Shell::Fish => CompleteShell::Fish,
+    };
+
+    let mut script = Vec::new();
+
+    // The command is named for display; complete the name of the binary.
+    clap_complete::generate(shell, cmd, defaults::PROGRAM_ARG0, &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    #[test]
+    fn test_generate() {
+        let bash = generate(Shell::Bash, &mut Cli::command());
+        assert!(bash.contains("--dry-run"));
+        assert!(bash.contains("high low toggle"));
+        assert!(bash.contains("complete -F _pellx_monitor"));
+
+        let zsh = generate(Shell::Zsh, &mut Cli::command());
+        assert!(zsh.starts_with("#compdef pellx_monitor\n"));
+        assert!(zsh.contains("--dry-run"));
+
+        let fish = generate(Shell::Fish, &mut Cli::command());
+        assert!(fish.contains("complete -c pellx_monitor"));
+        assert!(fish.contains("-l dry-run"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Record of sent alarms, kept on disk across restarts.
+//!
+//! The notifiers only track what they have sent in memory, so a monitor that
+//! restarts repeatedly while an alarm is active would send the alarm anew
+//! after each restart. To avoid that, each alarm delivered is recorded in a
+//! small file, keyed on the pin and the start of the alarm episode. An alarm
+//! that was already going on when the monitor started is taken to be the last
+//! episode recorded for its pin, and is not sent again if that episode was
+//! last notified within the restart dedupe window. An alarm that starts while
+//! the monitor is running is a new episode, and is sent as usual.
+//!
+//! A qualified restore ends the episode and removes its record, so that the
+//! next alarm is sent as usual.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::file_config;
+
+/// Record of the last alarm sent for a pin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentAlarm {
+    /// The GPIO pin number the alarm was on.
+    pub pin_number: u8,
+
+    /// Start of the alarm episode, in seconds since the Unix epoch.
+    pub episode_start: i64,
+
+    /// When the alarm was last sent, in seconds since the Unix epoch.
+    pub sent_at: i64,
+}
+
+/// The sent alarms recorded in a file.
+pub struct SentAlarms {
+    /// Path to the file.
+    path: PathBuf,
+
+    /// The alarms recorded, at most one per pin and episode.
+    alarms: Vec<SentAlarm>,
+}
+
+impl SentAlarms {
+    /// Loads the sent alarms recorded in the passed file.
+    ///
+    /// A missing file is taken as no alarms having been sent. A file that
+    /// can't be parsed is reported and otherwise ignored, and overwritten
+    /// once an alarm is recorded.
+    pub fn load(path: &Path) -> Self {
+        let alarms = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("[!] Ignoring unreadable {}: {e}", path.display());
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            alarms,
+        }
+    }
+
+    /// Returns the recorded alarm of the passed episode on the passed pin if
+    /// it was sent within the passed window before `now`, in seconds since
+    /// the Unix epoch.
+    pub fn recent(
+        &self,
+        pin_number: u8,
+        episode_start: i64,
+        window: Duration,
+        now: i64,
+    ) -> Option<&SentAlarm> {
+        let window = i64::try_from(window.as_secs()).unwrap_or(i64::MAX);
+
+        self.alarms.iter().find(|alarm| {
+            alarm.pin_number == pin_number
+                && alarm.episode_start == episode_start
+                && now.saturating_sub(alarm.sent_at) < window
+        })
+    }
+
+    /// Returns the start of the latest episode recorded on the passed pin
+    /// that started before `before`, in seconds since the Unix epoch. This is
+    /// the episode an alarm that was already going on at startup continues.
+    pub fn episode_before(&self, pin_number: u8, before: i64) -> Option<i64> {
+        self.alarms
+            .iter()
+            .filter(|alarm| alarm.pin_number == pin_number && alarm.episode_start <= before)
+            .map(|alarm| alarm.episode_start)
+            .max()
+    }
+
+    /// Records an alarm of the passed episode on the passed pin as sent at
+    /// `now`, and writes the file.
+    ///
+    /// If the episode is already recorded, only its time of sending is
+    /// updated. Alarms sent longer ago than the passed window are dropped.
+    pub fn record(
+        &mut self,
+        pin_number: u8,
+        episode_start: i64,
+        now: i64,
+        window: Duration,
+    ) -> io::Result<()> {
+        let window = i64::try_from(window.as_secs()).unwrap_or(i64::MAX);
+        self.alarms
+            .retain(|alarm| now.saturating_sub(alarm.sent_at) < window);
+
+        match self
+            .alarms
+            .iter_mut()
+            .find(|alarm| alarm.pin_number == pin_number && alarm.episode_start == episode_start)
+        {
+            Some(alarm) => alarm.sent_at = now,
+            None => self.alarms.push(SentAlarm {
+                pin_number,
+                episode_start,
+                sent_at: now,
+            }),
+        }
+
+        self.write()
+    }
+
+    /// Removes the recorded alarms on the passed pin, if there are any, and
+    /// writes the file. Nothing is written if there were none.
+    pub fn clear(&mut self, pin_number: u8) -> io::Result<()> {
+        let count = self.alarms.len();
+        self.alarms.retain(|alarm| alarm.pin_number != pin_number);
+
+        if self.alarms.len() == count {
+            return Ok(());
+        }
+
+        self.write()
+    }
+
+    /// Writes the recorded alarms to the file.
+    fn write(&self) -> io::Result<()> {
+        file_config::write_atomic(&self.path, serde_json::to_string(&self.alarms)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sent_alarms() {
+        let path = std::env::temp_dir().join(format!(
+            "pellx_monitor_sent_alarms_test_{}.json",
+            std::process::id()
+        ));
+        let window = Duration::from_secs(3600);
+        let _ = fs::remove_file(&path);
+
+        let mut sent = SentAlarms::load(&path);
+        assert!(sent.recent(24, 900, window, 1000).is_none());
+        sent.record(24, 900, 1000, window).unwrap();
+
+        // A restart reads the alarm back, and the episode carries on.
+        let mut sent = SentAlarms::load(&path);
+        assert_eq!(sent.episode_before(24, 1500), Some(900));
+        assert_eq!(sent.episode_before(24, 800), None);
+        assert_eq!(sent.recent(24, 900, window, 2000).unwrap().sent_at, 1000);
+        assert!(sent.recent(17, 900, window, 2000).is_none());
+        assert!(sent.recent(24, 900, window, 1000 + 3600).is_none());
+
+        sent.record(24, 900, 2000, window).unwrap();
+        let alarm = sent.recent(24, 900, window, 2000).unwrap();
+        assert_eq!((alarm.episode_start, alarm.sent_at), (900, 2000));
+
+        // A new episode on the same pin within the window is not the old one.
+        assert!(sent.recent(24, 1900, window, 2100).is_none());
+        sent.record(24, 1900, 2100, window).unwrap();
+        assert_eq!(sent.recent(24, 1900, window, 2100).unwrap().sent_at, 2100);
+        assert_eq!(sent.recent(24, 900, window, 2100).unwrap().sent_at, 2000);
+        assert_eq!(sent.episode_before(24, 2500), Some(1900));
+
+        // A restore ends the episodes.
+        sent.clear(24).unwrap();
+        assert!(
+            SentAlarms::load(&path)
+                .recent(24, 1900, window, 2100)
+                .is_none()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
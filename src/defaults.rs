@@ -11,8 +11,92 @@ pub mod gpio {
     /// Poll interval for checking the GPIO pin.
     pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
 
-    /// Duration the pin must be HIGH or LOW before qualifying as a valid change.
-    pub const HOLD: Duration = Duration::from_secs(10);
+    /// Duration the pin must be HIGH before qualifying as an alarm.
+    pub const QUALIFY_HIGH_HOLD: Duration = Duration::from_secs(10);
+
+    /// Duration the pin must be LOW before qualifying as restored.
+    pub const QUALIFY_LOW_HOLD: Duration = Duration::from_secs(10);
+
+    /// Default number of readings of a level needed to qualify it, in the `mofn` qualify mode.
+    pub const QUALIFY_M: u32 = 3;
+
+    /// Default number of most recent readings considered, in the `mofn` qualify mode.
+    pub const QUALIFY_N: u32 = 5;
+
+    /// Time after sending a notification during which readings are ignored.
+    pub const POST_NOTIFY_DEADTIME: Duration = Duration::ZERO;
+
+    /// Time to wait before the first poll, to let the system settle after boot.
+    pub const STARTUP_DELAY: Duration = Duration::ZERO;
+
+    /// Upper bound of a random extra wait added to the startup delay.
+    pub const STARTUP_JITTER: Duration = Duration::ZERO;
+
+    /// Time the pin is sampled for with `--calibrate`, to infer its baseline level.
+    pub const CALIBRATION_DURATION: Duration = Duration::from_secs(10);
+
+    /// How long each level is read for with `--fake-level toggle`.
+    pub const FAKE_TOGGLE_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// How long to wait for the kernel to create a pin's sysfs files after exporting it.
+    #[cfg(feature = "sysfs-gpio")]
+    pub const SYSFS_EXPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// How often to check for a pin's sysfs files while waiting for them after exporting it.
+    #[cfg(feature = "sysfs-gpio")]
+    pub const SYSFS_EXPORT_STEP: Duration = Duration::from_millis(50);
+
+    /// How often to check for a shutdown request while waiting out the startup delay.
+    pub const STARTUP_DELAY_STEP: Duration = Duration::from_millis(500);
+
+    /// Duration reading the pin must keep failing before an alert is sent.
+    pub const READ_ERROR_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    /// Minimum time between logging failed pin reads.
+    pub const READ_ERROR_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Shortest poll interval not considered a mistake.
+    pub const MIN_SENSIBLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Longest poll interval not considered a mistake.
+    pub const MAX_SENSIBLE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Longest qualify hold or post-notification dead time not considered a mistake.
+    pub const MAX_SENSIBLE_HOLD: Duration = Duration::from_secs(3600); // 1 hour
+
+    /// Default window within which level changes are counted for flap detection.
+    pub const FLAP_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+    /// Subject of the notice sent when the signal starts flapping.
+    pub const FLAP_STARTED_SUBJECT: &str = "PellX Signal Unstable";
+
+    /// Body of the notice sent when the signal starts flapping.
+    pub const FLAP_STARTED_BODY: &str = "Signal unstable on {pin_name}. \
+        Alarms and restores are held until it settles.";
+
+    /// Subject of the notice sent when a flapping signal has settled.
+    pub const FLAP_SETTLED_SUBJECT: &str = "PellX Signal Stable";
+
+    /// Body of the notice sent when a flapping signal has settled.
+    pub const FLAP_SETTLED_BODY: &str = "Signal stable on {pin_name}, now {level} ({state}).";
+
+    /// Subject of the alert sent when reading the pin keeps failing.
+    pub const READ_ERROR_SUBJECT: &str = "PellX Read Errors";
+
+    /// Body of the alert sent when reading the pin keeps failing.
+    pub const READ_ERROR_BODY: &str =
+        "Reading {pin_name} has been failing for {since}. The state of the burner is unknown.";
+
+    /// Subject of the notice sent when reading the pin works again after an alert.
+    pub const READ_RECOVERED_SUBJECT: &str = "PellX Reads Recovered";
+
+    /// Body of the notice sent when reading the pin works again after an alert.
+    pub const READ_RECOVERED_BODY: &str = "Reading {pin_name} works again.";
+
+    /// How to fix being denied access to the GPIO pins.
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+    pub const PERMISSION_HINT: &str = "Run as root, or add the user to the `gpio` group \
+        (e.g. `sudo usermod -aG gpio <user>`) and log in again.";
 }
 
 pub mod slack {
@@ -60,6 +144,230 @@ pub mod batsign {
 
     /// Default time to wait before retrying to send a mail after a failure.
     pub const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60); // 5 min
+
+    /// Default time a URL that failed is skipped over in failover mode.
+    pub const FAILOVER_HEALTH_WINDOW: Duration = Duration::from_secs(15 * 60); // 15 min
+
+    /// Default name of the header carrying the HMAC signature of the request body.
+    pub const HMAC_HEADER: &str = "X-Signature";
+
+    /// Resource file name of the cached copy of the remote Batsign URL list.
+    pub const URLS_CACHE_FILENAME: &str = "batsign_urls.cache";
+
+    /// Environment variable with additional Batsign URLs, separated by
+    /// whitespace or commas.
+    #[cfg(feature = "notifications")]
+    pub const URLS_ENV: &str = "PELLX_MONITOR_BATSIGN_URLS";
+
+    /// Timeout for fetching the Batsign URL list from a remote source.
+    #[cfg(feature = "notifications")]
+    pub const URLS_SOURCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Default time to keep retrying at startup for a Batsign host to become
+    /// reachable, if that is required.
+    pub const REACHABLE_WAIT: Duration = Duration::from_secs(60);
+
+    /// Timeout for connecting to a Batsign host when checking that it is reachable.
+    #[cfg(feature = "notifications")]
+    pub const REACHABLE_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Time between checks for a reachable Batsign host at startup.
+    #[cfg(feature = "notifications")]
+    pub const REACHABLE_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Time after which pinned Batsign addresses are resolved anew, if `pin_dns` is set.
+    pub const DNS_REFRESH_INTERVAL: Duration = Duration::from_secs(3600); // 1 hour
+}
+
+pub mod webhook {
+    use super::*;
+
+    /// Resource file name for the webhook alarm payload template.
+    pub const ALARM_PAYLOAD_TEMPLATE_FILENAME: &str = "webhook_alarm.json";
+
+    /// Resource file name for the webhook restored payload template.
+    pub const RESTORED_PAYLOAD_TEMPLATE_FILENAME: &str = "webhook_restored.json";
+
+    /// Default webhook alarm payload template.
+    pub const ALARM_PAYLOAD_TEMPLATE_BODY: &str = r#"{
+  "event": "alarm",
+  "pin": {pin_number},
+  "message": "Pellets burner is in an error state.",
+  "since": "{since}"
+}"#;
+
+    /// Default webhook restored payload template.
+    pub const RESTORED_PAYLOAD_TEMPLATE_BODY: &str = r#"{
+  "event": "restored",
+  "pin": {pin_number},
+  "message": "Pellets burner has been restored.",
+  "since": "{since}"
+}"#;
+
+    /// Default minimum time between sending webhook notifications.
+    pub const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(6 * 3600); // 6 hours
+
+    /// Default time to wait before retrying to send a webhook notification after a failure.
+    pub const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60); // 5 min
+
+    /// Default size in bytes above which payloads to the `gzip_urls` are compressed.
+    pub const GZIP_MIN_BYTES: usize = 1024;
+}
+
+pub mod command {
+    use super::*;
+
+    /// Default minimum time between running the alarm command.
+    pub const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(12 * 3600); // 12 hours
+
+    /// Default time to wait before retrying a command after a failure.
+    pub const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60); // 5 min
+}
+
+pub mod pipe {
+    use super::*;
+
+    /// Default minimum time between piping the alarm message.
+    pub const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(12 * 3600); // 12 hours
+
+    /// Default time to wait before retrying to pipe a message after a failure.
+    pub const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60); // 5 min
+
+    /// Default template of the alarm message piped to the command.
+    pub const ALARM_MESSAGE: &str =
+        "Subject: PellX Alarm\nPellets burner is in an error state, since {since} ago.\n";
+
+    /// Default template of the restored message piped to the command.
+    pub const RESTORED_MESSAGE: &str =
+        "Subject: PellX Restored\nPellets burner has been restored, since {since} ago.\n";
+}
+
+pub mod notifications {
+    use super::*;
+
+    /// Default maximum size of a notification message body, in bytes.
+    pub const MAX_MESSAGE_BYTES: usize = 8 * 1024;
+
+    /// Default window within which a message identical to the last one sent is skipped.
+    pub const DEDUPE_WINDOW: Duration = Duration::from_secs(24 * 3600); // 24 hours
+
+    /// Default filename of the record of sent alarms, kept across restarts.
+    pub const SENT_ALARMS_FILENAME: &str = "sent_alarms.json";
+
+    /// Default number of sends that may be queued for the send worker.
+    pub const SEND_QUEUE_SIZE: usize = 16;
+
+    /// Default time each notifier is given to send before it is considered failed.
+    pub const SEND_TIMEOUT: Duration = Duration::from_secs(2 * 60); // 2 min
+
+    /// Default time maintenance mode lasts when entered without a duration.
+    pub const MAINTENANCE_DURATION: Duration = Duration::from_secs(3600); // 1 hour
+
+    /// Maximum number of recent events that may be appended to an alarm.
+    pub const MAX_LOG_TAIL: usize = 20;
+
+    /// Line separating an alarm message from the recent events appended to it.
+    pub const LOG_TAIL_SEPARATOR: &str = "--- recent events ---";
+
+    /// Format of `maintenance_until`, in local time.
+    pub const MAINTENANCE_UNTIL_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+    /// Subject of the notice sent when only some notifiers succeeded.
+    pub const PARTIAL_FAILURE_SUBJECT: &str = "PellX Delivery Degraded";
+
+    /// Default body of the notice sent when only some notifiers succeeded.
+    pub const PARTIAL_FAILURE_MESSAGE: &str = "Delivery degraded: {succeeded} of {total} \
+        {state} notifications for {pin_name} succeeded. Failed: {failed}.";
+
+    /// Subject of the notice sent when the GPIO pin can't be opened at startup.
+    pub const STARTUP_FAILURE_SUBJECT: &str = "PellX Monitor Failed to Start";
+
+    /// Body of the notice sent when the GPIO pin can't be opened at startup,
+    /// followed by the reason.
+    pub const STARTUP_FAILURE_BODY: &str =
+        "The monitor of {pin_name} could not start, and is not watching it:";
+
+    /// Subject of the scheduled report sent while there is no alarm.
+    pub const STATUS_REPORT_SUBJECT: &str = "PellX All Nominal";
+
+    /// Default body of the scheduled report sent while there is no alarm.
+    pub const STATUS_REPORT_MESSAGE: &str =
+        "Still watching {pin_name}. All nominal, with no alarm for {since}.";
+}
+
+pub mod status {
+    /// File name of the status socket.
+    pub const SOCKET_FILENAME: &str = "status.sock";
+
+    /// Default number of recent alarms and restores kept in memory for the status socket.
+    pub const HISTORY_SIZE: usize = 50;
+
+    /// Default address to serve the status over HTTP on, with `--status-http`.
+    pub const HTTP_ADDRESS: &str = "127.0.0.1:9240";
+}
+
+pub mod watch {
+    use super::*;
+
+    /// How long watched files must stay unchanged before a change is acted upon.
+    pub const DEBOUNCE: Duration = Duration::from_secs(2);
+}
+
+pub mod adc {
+    /// Default SPI bus the MCP3008 is connected to.
+    pub const SPI_BUS: u8 = 0;
+
+    /// Default chip select line of the MCP3008.
+    pub const CHIP_SELECT: u8 = 0;
+
+    /// Default MCP3008 channel the sensor is connected to.
+    pub const CHANNEL: u8 = 0;
+
+    /// Default reading at or above which the input is HIGH.
+    pub const THRESHOLD: u16 = 512;
+
+    /// Default margin below the threshold a reading must fall to turn LOW again.
+    pub const HYSTERESIS: u16 = 16;
+
+    /// Highest reading of the 10-bit MCP3008.
+    pub const MAX_VALUE: u16 = 1023;
+
+    /// Highest channel of the MCP3008.
+    pub const MAX_CHANNEL: u8 = 7;
+
+    /// Highest SPI bus of a Raspberry Pi.
+    pub const MAX_SPI_BUS: u8 = 6;
+
+    /// Highest chip select line of a Raspberry Pi SPI bus.
+    pub const MAX_CHIP_SELECT: u8 = 2;
+
+    /// SPI clock speed, within what the MCP3008 supports at 3.3 V.
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+    pub const CLOCK_SPEED: u32 = 1_000_000;
+
+    /// Hint on how to fix being denied access to the SPI bus.
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+    pub const PERMISSION_HINT: &str = "Run as root, or add the user to the `spi` group \
+        (e.g. `sudo usermod -aG spi <user>`) and log in again. Make sure SPI is enabled, \
+        e.g. with `sudo raspi-config`.";
+}
+
+pub mod history {
+    /// Default filename of the alarm history file.
+    pub const FILENAME: &str = "history.jsonl";
+
+    /// Default maximum number of entries kept in the alarm history file.
+    pub const MAX_ENTRIES: usize = 1000;
+}
+
+pub mod http {
+    use super::*;
+
+    /// Default timeout for HTTP requests.
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Default number of bytes of a failed response's body to include in error messages.
+    pub const MAX_ERROR_BODY_BYTES: usize = 300;
 }
 
 /// Program name string.
@@ -71,6 +379,9 @@ pub const PROGRAM_ARG0: &str = "pellx_monitor";
 /// Configuration file name.
 pub const CONFIG_FILENAME: &str = "config.toml";
 
+/// Lockfile name, holding the process ID of the running instance.
+pub const PID_FILENAME: &str = "pellx_monitor.pid";
+
 /// Author string.
 pub const AUTHOR: &str = "jr <zorael@gmail.com>";
 
@@ -84,6 +395,7 @@ pub const VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"), SEMVER_PATCH);
 pub const SOURCE_REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
 
 pub mod exit_codes {
+    #[cfg(not(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio"))))]
     pub const WRONG_PLATFORM: u8 = 100;
     pub const FAILED_TO_CREATE_CONFIG_DIR: u8 = 10;
     pub const FAILED_TO_WRITE_CONFIG_FILE: u8 = 11;
@@ -91,13 +403,40 @@ pub mod exit_codes {
     pub const FAILED_TO_WRITE_SLACK_RESTORED_TEMPLATE: u8 = 13;
     pub const FAILED_TO_WRITE_BATSIGN_ALARM_TEMPLATE: u8 = 14;
     pub const FAILED_TO_WRITE_BATSIGN_RESTORED_TEMPLATE: u8 = 15;
+    pub const FAILED_TO_WRITE_WEBHOOK_ALARM_TEMPLATE: u8 = 16;
+    pub const FAILED_TO_WRITE_WEBHOOK_RESTORED_TEMPLATE: u8 = 17;
     pub const CONFIGURATION_ERROR: u8 = 20;
     pub const NO_NOTIFIERS_CONFIGURED: u8 = 21;
+    pub const TEST_NOTIFICATION_FAILED: u8 = 22;
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
     pub const FAILED_TO_INITIALISE_GPIO: u8 = 30;
+    #[cfg(any(
+        all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")),
+        feature = "sysfs-gpio"
+    ))]
     pub const FAILED_TO_SET_PIN_MODE: u8 = 31;
+    #[cfg(feature = "notifications")]
+    pub const FAILED_TO_INITIALISE_HTTP_CLIENT: u8 = 32;
+    pub const ALREADY_RUNNING: u8 = 33;
+    pub const CALIBRATION_FAILED: u8 = 34;
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+    pub const FAILED_TO_OPEN_ADC: u8 = 35;
     pub const FAILED_TO_RESOLVE_CONFIG_DIR: u8 = 40;
     pub const CONFIG_DIR_DOES_NOT_EXIST: u8 = 41;
     pub const FAILED_TO_LOAD_RESOURCES: u8 = 42;
     pub const FAILED_TO_READ_CONFIG_FILE: u8 = 43;
     pub const CONFIG_FILE_DOES_NOT_EXIST: u8 = 44;
+    pub const UNKNOWN_PROFILE: u8 = 45;
+    pub const FAILED_TO_DIFF_CONFIG_FILE: u8 = 46;
+    pub const FAILED_TO_UPDATE_TEMPLATES: u8 = 47;
+    pub const FAILED_TO_RENDER_CONFIG: u8 = 48;
+    pub const SETUP_ABORTED: u8 = 49;
+    #[cfg(feature = "notifications")]
+    pub const URL_CHECK_FAILED: u8 = 50;
+    pub const FAILED_TO_READ_READINGS_FILE: u8 = 51;
+    pub const FAILED_TO_READ_HISTORY: u8 = 52;
+    pub const STATUS_QUERY_FAILED: u8 = 53;
+    #[cfg(feature = "notifications")]
+    pub const BATSIGN_UNREACHABLE: u8 = 54;
+    pub const REPLAY_DIFFERS: u8 = 55;
 }
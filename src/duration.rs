@@ -0,0 +1,76 @@
+//! Formatting of durations for display, in templates and terminal output.
+//!
+//! Durations can be shown either in humantime's compact form, e.g. `1m 10s`,
+//! or as a fixed `H:MM:SS` clock, e.g. `0:01:10`. Sub-second remainders are
+//! shown as milliseconds in both forms, and anything finer is left out, so
+//! that e.g. a `500ms` poll interval isn't shown with trailing microseconds.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// How durations are formatted for display.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationFormat {
+    /// humantime's compact form, e.g. `1m 10s 500ms`.
+    Compact,
+
+    /// A fixed `H:MM:SS` form, e.g. `0:01:10.500`.
+    Clock,
+}
+
+impl fmt::Display for DurationFormat {
+    /// Formats the duration format as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compact => write!(f, "compact"),
+            Self::Clock => write!(f, "clock"),
+        }
+    }
+}
+
+/// Formats a duration in the passed style.
+pub fn format_duration(d: Duration, style: DurationFormat) -> String {
+    match style {
+        DurationFormat::Compact => {
+            let d = Duration::new(d.as_secs(), d.subsec_millis() * 1_000_000);
+            humantime::format_duration(d).to_string()
+        }
+        DurationFormat::Clock => {
+            let secs = d.as_secs();
+            let clock = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+
+            match d.subsec_millis() {
+                0 => clock,
+                millis => format!("{clock}.{millis:03}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        let d = Duration::from_millis(3_670_500);
+        assert_eq!(
+            format_duration(d, DurationFormat::Compact),
+            "1h 1m 10s 500ms"
+        );
+        assert_eq!(format_duration(d, DurationFormat::Clock), "1:01:10.500");
+
+        let d = Duration::from_secs(70);
+        assert_eq!(format_duration(d, DurationFormat::Clock), "0:01:10");
+        assert_eq!(
+            format_duration(Duration::from_millis(100), DurationFormat::Clock),
+            "0:00:00.100"
+        );
+
+        let d = Duration::new(70, 500_123_456);
+        assert_eq!(format_duration(d, DurationFormat::Compact), "1m 10s 500ms");
+        assert_eq!(format_duration(d, DurationFormat::Clock), "0:01:10.500");
+    }
+}
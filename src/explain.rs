@@ -0,0 +1,476 @@
+//! Plain descriptions and step-by-step tracing of the decisions made by the
+//! monitor loop.
+//!
+//! Without a file of readings, `--explain` describes what the monitor will
+//! do with the resolved settings, in words rather than the key-value listing
+//! of `--show`.
+//!
+//! Given a sequence of timed pin readings, this module runs them through the
+//! same `Monitor` and `TwoLevelNotifier` logic as the real loop and prints why
+//! each reading did or didn't qualify, and which notifications would be sent.
+//! Nothing is actually sent; the notifiers are backed by a silent stand-in.
+//!
+//! The sequence is read from a file in the same format as `--simulate` uses;
+//! see the `gpio` module.
+
+use std::time::{Duration, Instant};
+
+use crate::backend::Backend;
+use crate::duration::format_duration;
+use crate::gpio::{Level, Sample};
+use crate::monitor::Monitor;
+use crate::notify::{self, Context, NotificationResult};
+use crate::settings::{BaselineLevel, BatsignMode, PullMode, QualifyMode, Settings};
+
+/// Stand-in backend that silently succeeds, used to simulate notification timing.
+struct TraceBackend {
+    /// Name of the channel being simulated.
+    name: &'static str,
+}
+
+impl Backend for TraceBackend {
+    /// Returns the name of the simulated channel.
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    /// Returns the template verbatim; the message is never shown.
+    fn build_message(&self, _ctx: &Context, template: &str) -> String {
+        template.to_owned()
+    }
+
+    /// Pretends to deliver the message.
+    fn send_message(&mut self, _ctx: &Context, _message: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Builds one simulated notifier per enabled channel, with that channel's intervals.
+pub fn build_trace_notifiers(settings: &Settings) -> Vec<Box<dyn notify::Notifier>> {
+    let channels = [
+        (
+            "slack",
+            settings.slack.enabled,
+            settings.slack.notification_interval,
+            settings.slack.retry_interval,
+        ),
+        (
+            "batsign",
+            settings.batsign.enabled,
+            settings.batsign.notification_interval,
+            settings.batsign.retry_interval,
+        ),
+        (
+            "webhook",
+            settings.webhook.enabled,
+            settings.webhook.notification_interval,
+            settings.webhook.retry_interval,
+        ),
+        (
+            "command",
+            settings.command.enabled,
+            settings.command.notification_interval,
+            settings.command.retry_interval,
+        ),
+        (
+            "pipe",
+            settings.pipe.enabled,
+            settings.pipe.notification_interval,
+            settings.pipe.retry_interval,
+        ),
+    ];
+
+    let mut notifiers: Vec<Box<dyn notify::Notifier>> = Vec::new();
+
+    for (name, enabled, notification_interval, retry_interval) in channels {
+        if enabled {
+            notifiers.push(Box::new(notify::TwoLevelNotifier::new(
+                TraceBackend { name },
+                Some(notification_interval),
+                retry_interval,
+                "",
+                "",
+                false,
+            )));
+        }
+    }
+
+    notifiers
+}
+
+/// Describes in plain words what the monitor will do with the passed settings,
+/// one sentence per line.
+pub fn describe(settings: &Settings) -> String {
+    let gpio = &settings.gpio;
+    let fmt = |duration: Duration| format_duration(duration, settings.duration_format);
+    let mut lines = Vec::new();
+
+    let pull = match gpio.pull {
+        PullMode::Up => "pull-up",
+        PullMode::Down => "pull-down",
+        PullMode::None => "no pull resistor",
+    };
+    let pin_name = settings.pin_name(gpio.pin_number);
+    let pin = if pin_name == format!("GPIO{}", gpio.pin_number) {
+        format!("{pin_name} ({pull})")
+    } else {
+        format!("{pin_name} (GPIO{}, {pull})", gpio.pin_number)
+    };
+
+    lines.push(match gpio.alarm_poll_interval {
+        Some(alarm_poll_interval) => format!(
+            "Every {} I read {pin}, or every {} during an alarm.",
+            fmt(gpio.poll_interval),
+            fmt(alarm_poll_interval)
+        ),
+        None => format!("Every {} I read {pin}.", fmt(gpio.poll_interval)),
+    });
+
+    let (alarm, normal) = match gpio.baseline_level {
+        BaselineLevel::Low => ("HIGH", "LOW again"),
+        BaselineLevel::High => ("LOW", "HIGH again"),
+        BaselineLevel::Auto => {
+            lines.push("Whatever it reads at startup is taken as its normal level.".to_string());
+            ("away from its normal level", "back at its normal level")
+        }
+    };
+
+    let (alarm_when, restored_when) = match gpio.qualify_mode {
+        QualifyMode::MOfN => (
+            format!(
+                "If it reads {alarm} at least {} of the last {} polls",
+                gpio.qualify_m, gpio.qualify_n
+            ),
+            format!(
+                "When it reads {normal} at least {} of the last {} polls",
+                gpio.qualify_m, gpio.qualify_n
+            ),
+        ),
+        QualifyMode::Hold => (
+            if gpio.qualify_high_hold.is_zero() {
+                format!("As soon as it reads {alarm}")
+            } else {
+                format!("If it stays {alarm} for {}", fmt(gpio.qualify_high_hold))
+            },
+            if gpio.qualify_low_hold.is_zero() {
+                format!("As soon as it reads {normal}")
+            } else {
+                format!(
+                    "When it has stayed {normal} for {}",
+                    fmt(gpio.qualify_low_hold)
+                )
+            },
+        ),
+    };
+
+    let mut channels = Vec::new();
+
+    if cfg!(feature = "notifications") {
+        let urls = |n: usize, kind: &str| match n {
+            1 => format!("1 {kind} URL"),
+            n => format!("{n} {kind} URLs"),
+        };
+
+        if settings.slack.enabled {
+            channels.push((
+                urls(settings.slack.urls.len(), "Slack"),
+                settings.slack.notification_interval,
+                settings.slack.retry_interval,
+            ));
+        }
+
+        if settings.batsign.enabled {
+            let n = settings.batsign.urls.len();
+            let to = match settings.batsign.mode {
+                BatsignMode::Failover if n > 1 => {
+                    format!("the first of {n} Batsign URLs that works")
+                }
+                _ => urls(n, "Batsign"),
+            };
+
+            channels.push((
+                to,
+                settings.batsign.notification_interval,
+                settings.batsign.retry_interval,
+            ));
+        }
+
+        if settings.webhook.enabled {
+            channels.push((
+                urls(settings.webhook.urls.len(), "webhook"),
+                settings.webhook.notification_interval,
+                settings.webhook.retry_interval,
+            ));
+        }
+    }
+
+    if settings.command.enabled {
+        channels.push((
+            "the alarm command".to_string(),
+            settings.command.notification_interval,
+            settings.command.retry_interval,
+        ));
+    }
+
+    if settings.pipe.enabled {
+        channels.push((
+            "the pipe command".to_string(),
+            settings.pipe.notification_interval,
+            settings.pipe.retry_interval,
+        ));
+    }
+
+    if channels.is_empty() {
+        lines.push(format!(
+            "{alarm_when}, that's an alarm, but no notifiers are enabled to send it."
+        ));
+    } else {
+        lines.push(format!("{alarm_when}, that's an alarm."));
+
+        for (to, notification_interval, retry_interval) in channels {
+            lines.push(format!(
+                "I'll send it to {to}, then at most once per {}, retrying every {} on failure.",
+                fmt(notification_interval),
+                fmt(retry_interval)
+            ));
+        }
+
+        if settings.notifications.send_restored {
+            lines.push(format!("{restored_when}, I'll send a restored message."));
+        } else {
+            lines.push(format!(
+                "{restored_when}, it's restored, but I won't send a restored message."
+            ));
+        }
+    }
+
+    if gpio.notify_on_startup_alarm {
+        lines.push(format!(
+            "If it already reads {alarm} at startup, I'll send the alarm right away."
+        ));
+    }
+
+    if !gpio.post_notify_deadtime.is_zero() {
+        lines.push(format!(
+            "After each notification I ignore readings for {}.",
+            fmt(gpio.post_notify_deadtime)
+        ));
+    }
+
+    if let Some(threshold) = gpio.flap_threshold {
+        lines.push(format!(
+            "If it changes more than {threshold} times within {}, \
+            I hold notifications until it settles.",
+            fmt(gpio.flap_window)
+        ));
+    }
+
+    lines.push(format!(
+        "If reading it keeps failing for {}, I'll send an alert.",
+        fmt(gpio.read_error_timeout)
+    ));
+
+    if let Some(interval) = settings.notifications.status_report_interval {
+        lines.push(format!(
+            "While all is well, I'll send a status report every {}.",
+            fmt(interval)
+        ));
+    }
+
+    match (gpio.startup_delay.is_zero(), gpio.startup_jitter.is_zero()) {
+        (true, true) => {}
+        (false, true) => lines.push(format!(
+            "I wait {} before the first poll.",
+            fmt(gpio.startup_delay)
+        )),
+        (_, false) => lines.push(format!(
+            "I wait {} plus up to {} more at random before the first poll.",
+            fmt(gpio.startup_delay),
+            fmt(gpio.startup_jitter)
+        )),
+    }
+
+    if settings.dry_run {
+        lines.push("This is a dry run, so messages are printed rather than sent.".to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Runs the samples through the monitor logic, printing a trace of each decision.
+pub fn explain(settings: &Settings, samples: &[Sample]) {
+    let mut monitor = Monitor::new(
+        settings.gpio.qualify_high_hold,
+        settings.gpio.qualify_low_hold,
+    )
+    .with_startup_alarm(settings.gpio.notify_on_startup_alarm)
+    .with_m_of_n(settings.gpio.m_of_n());
+    let mut notifiers = build_trace_notifiers(settings);
+    let t0 = Instant::now();
+
+    // Mirror `apply_baseline`, taking the first sample as the normal level if
+    // the baseline is `auto`.
+    let baseline = match settings.gpio.baseline_level {
+        BaselineLevel::Low => Level::Low,
+        BaselineLevel::High => Level::High,
+        BaselineLevel::Auto => {
+            let level = samples.first().map_or(Level::Low, |sample| sample.level);
+            println!("baseline: {level}");
+            level
+        }
+    };
+
+    if baseline == Level::High {
+        println!("readings are inverted, so that HIGH means an alarm");
+    }
+
+    for sample in samples {
+        let now = t0 + sample.offset;
+        let level = match baseline {
+            Level::Low => sample.level,
+            Level::High => !sample.level,
+        };
+
+        let (name, hold) = match level {
+            Level::High => ("HIGH", settings.gpio.qualify_high_hold),
+            Level::Low => ("LOW", settings.gpio.qualify_low_hold),
+        };
+
+        let qualified = monitor.poll(level, now);
+        let since = monitor.since(level).unwrap_or(now);
+        let held = now.saturating_duration_since(since);
+
+        print!(
+            "t={:<8} {name:<4}  {}_since={}, held {}",
+            format_duration(sample.offset, settings.duration_format),
+            name.to_lowercase(),
+            format_duration(
+                since.saturating_duration_since(t0),
+                settings.duration_format
+            ),
+            format_duration(held, settings.duration_format)
+        );
+
+        if let Some((highs, len)) = monitor.window_highs() {
+            let m = settings.gpio.qualify_m;
+
+            match qualified {
+                Some(level) => println!(", {highs} of last {len} HIGH: {level} qualified"),
+                None if highs < m && len - highs < m => {
+                    println!(", {highs} of last {len} HIGH, need {m}: not qualified")
+                }
+                None => {
+                    println!(", {highs} of last {len} HIGH, but no alarm has been sent: ignored")
+                }
+            }
+
+            if let Some(level) = qualified {
+                dispatch(settings, &mut notifiers, &mut monitor, level, now);
+            }
+            continue;
+        }
+
+        let Some(level) = qualified else {
+            if held < hold {
+                println!(
+                    " < hold {}: not qualified",
+                    format_duration(hold, settings.duration_format)
+                );
+            } else {
+                println!(
+                    " >= hold {}, but no alarm has been sent: ignored",
+                    format_duration(hold, settings.duration_format)
+                );
+            }
+            continue;
+        };
+
+        println!(
+            " >= hold {}: qualified",
+            format_duration(hold, settings.duration_format)
+        );
+
+        dispatch(settings, &mut notifiers, &mut monitor, level, now);
+    }
+}
+
+/// Traces what the notifiers would do with a qualified level.
+fn dispatch(
+    settings: &Settings,
+    notifiers: &mut [Box<dyn notify::Notifier>],
+    monitor: &mut Monitor,
+    level: Level,
+    now: Instant,
+) {
+    let ctx = Context::new(settings, level, now, monitor.since(level).unwrap_or(now));
+
+    let kind = match level {
+        Level::High => "alarm",
+        Level::Low => "restored",
+    };
+
+    for n in notifiers.iter_mut() {
+        match n.send_notification(&ctx) {
+            NotificationResult::Success | NotificationResult::DryRun => {
+                println!("{:<16}{}: {kind} notification would be sent", "", n.name());
+
+                if level == Level::High {
+                    monitor.mark_alarm_notified();
+                }
+            }
+            NotificationResult::NotYetTime => {
+                println!("{:<16}{}: not yet time", "", n.name());
+            }
+            NotificationResult::Failure(_)
+            | NotificationResult::Skipped
+            | NotificationResult::Deduplicated => {}
+        }
+    }
+
+    if notifiers.is_empty() && level == Level::High {
+        monitor.mark_alarm_notified();
+    }
+}
+
+#[cfg(all(test, feature = "notifications"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let mut settings = Settings::default();
+        settings.slack.enabled = false;
+        settings.batsign.urls = vec![
+            "https://batsign.me/at/first@example.com/token".to_string(),
+            "https://batsign.me/at/second@example.com/token".to_string(),
+        ];
+
+        let description = describe(&settings);
+        let lines: Vec<&str> = description.lines().collect();
+
+        assert_eq!(
+            lines[..4],
+            [
+                "Every 1s I read GPIO24 (pull-up).",
+                "If it stays HIGH for 10s, that's an alarm.",
+                "I'll send it to 2 Batsign URLs, then at most once per 6h, \
+                retrying every 5m on failure.",
+                "When it has stayed LOW again for 10s, I'll send a restored message.",
+            ]
+        );
+
+        settings.gpio.pin_label = Some("boiler".to_string());
+        settings.gpio.qualify_mode = QualifyMode::MOfN;
+        settings.batsign.mode = BatsignMode::Failover;
+
+        let description = describe(&settings);
+        let lines: Vec<&str> = description.lines().collect();
+
+        assert_eq!(lines[0], "Every 1s I read boiler (GPIO24, pull-up).");
+        assert_eq!(
+            lines[1],
+            "If it reads HIGH at least 3 of the last 5 polls, that's an alarm."
+        );
+        assert!(lines[2].starts_with("I'll send it to the first of 2 Batsign URLs that works"));
+    }
+}
@@ -1,23 +1,115 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::{env, time};
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io, time};
 use users::get_current_uid;
 
 use crate::defaults;
-use crate::settings::Settings;
+use crate::duration::DurationFormat;
+use crate::settings::{
+    BaselineLevel, BatsignMode, BatsignRequestStyle, ExtremeDurations, HmacAlgorithm, InputMode,
+    InputSource, LineEnding, PullMode, QualifyMode, Settings, Severity, Signal,
+};
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GpioConfig {
     /// GPIO pin number to monitor.
     pub pin_number: Option<u8>,
 
+    /// Human-readable label of the monitored pin, e.g. "Boiler overheat".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin_label: Option<String>,
+
+    /// How urgent alarms of the pin are: `info`, `warning` or `critical`.
+    pub severity: Option<Severity>,
+
     /// Poll interval for checking the GPIO pin.
     #[serde(with = "humantime_serde")]
     pub poll_interval: Option<time::Duration>,
 
-    /// Duration the pin must be HIGH or LOW before qualifying as a valid change.
-    #[serde(with = "humantime_serde")]
+    /// Optional shorter poll interval to use while the pin reads HIGH.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub alarm_poll_interval: Option<time::Duration>,
+
+    /// Deprecated alias that sets both `qualify_high_hold` and `qualify_low_hold`.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
     pub hold: Option<time::Duration>,
+
+    /// Duration the pin must be HIGH before qualifying as an alarm.
+    #[serde(with = "humantime_serde")]
+    pub qualify_high_hold: Option<time::Duration>,
+
+    /// Duration the pin must be LOW before qualifying as restored.
+    #[serde(with = "humantime_serde")]
+    pub qualify_low_hold: Option<time::Duration>,
+
+    /// How levels qualify: `hold` or `mofn`.
+    pub qualify_mode: Option<QualifyMode>,
+
+    /// Number of readings of a level out of the last `qualify_n` needed to qualify it.
+    pub qualify_m: Option<u32>,
+
+    /// Number of most recent readings considered in the `mofn` qualify mode.
+    pub qualify_n: Option<u32>,
+
+    /// Preset of the baseline level and pull resistor, e.g. `alarm-active-high-pullup`,
+    /// which `baseline_level` and `pull` override.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_mode: Option<InputMode>,
+
+    /// Level the pin reads normally: `low`, `high` or `auto`.
+    pub baseline_level: Option<BaselineLevel>,
+
+    /// Internal resistor the pin is configured with: `up`, `down` or `none`.
+    pub pull: Option<PullMode>,
+
+    /// Sysfs GPIO directory to read the pin through instead of `rppal`, e.g. `/sys/class/gpio`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sysfs_base: Option<PathBuf>,
+
+    /// Where the input is read from: `gpio`, or `adc` as set up in `[adc]`.
+    pub input_source: Option<InputSource>,
+
+    /// Whether a HIGH reading at startup is notified right away, without waiting out the hold.
+    pub notify_on_startup_alarm: Option<bool>,
+
+    /// Duration reading the pin must keep failing before an alert is sent.
+    #[serde(with = "humantime_serde")]
+    pub read_error_timeout: Option<time::Duration>,
+
+    /// Time after sending a notification during which readings are ignored.
+    #[serde(with = "humantime_serde")]
+    pub post_notify_deadtime: Option<time::Duration>,
+
+    /// Time to wait before the first poll.
+    #[serde(with = "humantime_serde")]
+    pub startup_delay: Option<time::Duration>,
+
+    /// Upper bound of a random extra wait added to the startup delay.
+    #[serde(with = "humantime_serde")]
+    pub startup_jitter: Option<time::Duration>,
+
+    /// Time the pin is sampled for with `--calibrate`.
+    #[serde(with = "humantime_serde")]
+    pub calibration_duration: Option<time::Duration>,
+
+    /// Number of level changes within the flap window beyond which the signal is unstable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flap_threshold: Option<usize>,
+
+    /// Window within which level changes are counted for flap detection.
+    #[serde(with = "humantime_serde")]
+    pub flap_window: Option<time::Duration>,
+
+    /// Optional window for which a restore is held back, to coalesce brief dropouts.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub coalesce_window: Option<time::Duration>,
+
+    /// Whether durations outside their sensible ranges are reported as a `warn`ing or an `error`.
+    pub extreme_durations: Option<ExtremeDurations>,
 }
 
 impl Default for GpioConfig {
@@ -25,13 +117,37 @@ impl Default for GpioConfig {
     fn default() -> Self {
         Self {
             pin_number: None,
+            pin_label: None,
+            severity: None,
             poll_interval: None,
+            alarm_poll_interval: None,
             hold: None,
+            qualify_high_hold: None,
+            qualify_low_hold: None,
+            qualify_mode: None,
+            qualify_m: None,
+            qualify_n: None,
+            input_mode: None,
+            baseline_level: None,
+            pull: None,
+            sysfs_base: None,
+            input_source: None,
+            notify_on_startup_alarm: None,
+            read_error_timeout: None,
+            post_notify_deadtime: None,
+            startup_delay: None,
+            startup_jitter: None,
+            calibration_duration: None,
+            flap_threshold: None,
+            flap_window: None,
+            coalesce_window: None,
+            extreme_durations: None,
         }
     }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SlackConfig {
     /// Whether Slack notifications are enabled.
     pub enabled: Option<bool>,
@@ -39,6 +155,14 @@ pub struct SlackConfig {
     /// Optional Slack webhook URL for sending notifications to Slack.
     pub urls: Option<Vec<String>>,
 
+    /// Alternate path to the alarm template file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alarm_template: Option<PathBuf>,
+
+    /// Alternate path to the restored template file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_template: Option<PathBuf>,
+
     /// Minimum time between sending Slack notifications.
     #[serde(with = "humantime_serde")]
     pub notification_interval: Option<time::Duration>,
@@ -46,6 +170,9 @@ pub struct SlackConfig {
     /// Time to wait before retrying to send a Slack notification after a failure.
     #[serde(with = "humantime_serde")]
     pub retry_interval: Option<time::Duration>,
+
+    /// Severities of alarm the channel accepts: any of `info`, `warning` and `critical`.
+    pub severities: Option<Vec<Severity>>,
 }
 
 impl Default for SlackConfig {
@@ -54,13 +181,17 @@ impl Default for SlackConfig {
         Self {
             enabled: None,
             urls: None,
+            alarm_template: None,
+            restored_template: None,
             notification_interval: None,
             retry_interval: None,
+            severities: None,
         }
     }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BatsignConfig {
     /// Whether Batsign notifications are enabled.
     pub enabled: Option<bool>,
@@ -68,6 +199,41 @@ pub struct BatsignConfig {
     /// List of URLs to send Batsign notifications to.
     pub urls: Option<Vec<String>>,
 
+    /// Optional `http(s)://` URL of a remote list of additional Batsign URLs, one per line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urls_source: Option<String>,
+
+    /// How messages are packaged into the HTTP request: `raw_subject`, `form_encoded` or `json`.
+    pub request_style: Option<BatsignRequestStyle>,
+
+    /// Line ending used in messages: `lf` or `crlf`.
+    pub line_ending: Option<LineEnding>,
+
+    /// How notifications are spread over the URLs: `all` or `failover`.
+    pub mode: Option<BatsignMode>,
+
+    /// How long a URL that failed is skipped over in `failover` mode.
+    #[serde(with = "humantime_serde")]
+    pub failover_health_window: Option<time::Duration>,
+
+    /// Optional shared secret with which to sign request bodies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac_secret: Option<String>,
+
+    /// Name of the header carrying the signature.
+    pub hmac_header: Option<String>,
+
+    /// Hash function used for the signature: `sha256`, `sha384` or `sha512`.
+    pub hmac_algorithm: Option<HmacAlgorithm>,
+
+    /// Alternate path to the alarm template file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alarm_template: Option<PathBuf>,
+
+    /// Alternate path to the restored template file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_template: Option<PathBuf>,
+
     /// Minimum time between sending Batsign notifications.
     #[serde(with = "humantime_serde")]
     pub notification_interval: Option<time::Duration>,
@@ -75,6 +241,20 @@ pub struct BatsignConfig {
     /// Time to wait before retrying to send a Batsign notification after a failure.
     #[serde(with = "humantime_serde")]
     pub retry_interval: Option<time::Duration>,
+
+    /// Severities of alarm the channel accepts: any of `info`, `warning` and `critical`.
+    pub severities: Option<Vec<Severity>>,
+
+    /// Whether to require a Batsign host to be reachable before starting to monitor.
+    pub require_reachable_on_start: Option<bool>,
+
+    /// How long to keep retrying at startup for a Batsign host to become reachable.
+    #[serde(with = "humantime_serde")]
+    pub reachable_wait: Option<time::Duration>,
+
+    /// Whether to pin the addresses the Batsign hosts resolve to, riding out
+    /// transient DNS failures.
+    pub pin_dns: Option<bool>,
 }
 
 impl Default for BatsignConfig {
@@ -83,8 +263,383 @@ impl Default for BatsignConfig {
         Self {
             enabled: None,
             urls: None,
+            urls_source: None,
+            request_style: None,
+            line_ending: None,
+            mode: None,
+            failover_health_window: None,
+            hmac_secret: None,
+            hmac_header: None,
+            hmac_algorithm: None,
+            alarm_template: None,
+            restored_template: None,
+            notification_interval: None,
+            retry_interval: None,
+            severities: None,
+            require_reachable_on_start: None,
+            reachable_wait: None,
+            pin_dns: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// Whether webhook notifications are enabled.
+    pub enabled: Option<bool>,
+
+    /// List of URLs to POST the JSON payloads to.
+    pub urls: Option<Vec<String>>,
+
+    /// Alternate path to the alarm template file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alarm_template: Option<PathBuf>,
+
+    /// Alternate path to the restored template file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_template: Option<PathBuf>,
+
+    /// Minimum time between sending webhook notifications.
+    #[serde(with = "humantime_serde")]
+    pub notification_interval: Option<time::Duration>,
+
+    /// Time to wait before retrying to send a webhook notification after a failure.
+    #[serde(with = "humantime_serde")]
+    pub retry_interval: Option<time::Duration>,
+
+    /// Severities of alarm the channel accepts: any of `info`, `warning` and `critical`.
+    pub severities: Option<Vec<Severity>>,
+
+    /// Webhook URLs whose receivers accept gzip-compressed payloads.
+    pub gzip_urls: Option<Vec<String>>,
+
+    /// Size in bytes above which payloads to the gzip URLs are compressed.
+    pub gzip_min_bytes: Option<usize>,
+}
+
+impl Default for WebhookConfig {
+    /// Default values for the webhook settings.
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            urls: None,
+            alarm_template: None,
+            restored_template: None,
             notification_interval: None,
             retry_interval: None,
+            severities: None,
+            gzip_urls: None,
+            gzip_min_bytes: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+    /// Whether to run commands on alarm and restore.
+    pub enabled: Option<bool>,
+
+    /// Shell command to run when an alarm is raised.
+    pub on_alarm_command: Option<String>,
+
+    /// Shell command to run when the alarm is restored.
+    pub on_restore_command: Option<String>,
+
+    /// Minimum time between running the alarm command.
+    #[serde(with = "humantime_serde")]
+    pub notification_interval: Option<time::Duration>,
+
+    /// Time to wait before retrying a command after a failure.
+    #[serde(with = "humantime_serde")]
+    pub retry_interval: Option<time::Duration>,
+
+    /// Severities of alarm the channel accepts: any of `info`, `warning` and `critical`.
+    pub severities: Option<Vec<Severity>>,
+}
+
+impl Default for CommandConfig {
+    /// Default values for the command settings.
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            on_alarm_command: None,
+            on_restore_command: None,
+            notification_interval: None,
+            retry_interval: None,
+            severities: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipeConfig {
+    /// Whether to pipe messages to a command on alarm and restore.
+    pub enabled: Option<bool>,
+
+    /// Shell command to pipe the messages to.
+    pub command: Option<String>,
+
+    /// Template of the alarm message.
+    pub alarm_message: Option<String>,
+
+    /// Template of the restored message.
+    pub restored_message: Option<String>,
+
+    /// Minimum time between piping the alarm message.
+    #[serde(with = "humantime_serde")]
+    pub notification_interval: Option<time::Duration>,
+
+    /// Time to wait before retrying to pipe a message after a failure.
+    #[serde(with = "humantime_serde")]
+    pub retry_interval: Option<time::Duration>,
+
+    /// Severities of alarm the channel accepts: any of `info`, `warning` and `critical`.
+    pub severities: Option<Vec<Severity>>,
+}
+
+impl Default for PipeConfig {
+    /// Default values for the pipe settings.
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            command: None,
+            alarm_message: None,
+            restored_message: None,
+            notification_interval: None,
+            retry_interval: None,
+            severities: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// Maximum size of a Slack, Batsign or pipe message body, in bytes.
+    pub max_message_bytes: Option<usize>,
+
+    /// Whether `{config_summary}` is substituted with a summary of the key settings.
+    pub include_config_in_notification: Option<bool>,
+
+    /// Whether restored notifications are sent, or only alarms.
+    pub send_restored: Option<bool>,
+
+    /// Whether to send a notice when only some notifiers succeeded.
+    pub notify_on_partial_failure: Option<bool>,
+
+    /// Whether to send a notice when the GPIO pin can't be opened at startup.
+    pub notify_on_startup_failure: Option<bool>,
+
+    /// Template of the partial failure notice.
+    pub partial_failure_message: Option<String>,
+
+    /// Optional interval at which to report that all is nominal while there is no alarm.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub status_report_interval: Option<time::Duration>,
+
+    /// Template of the status report.
+    pub status_report_message: Option<String>,
+
+    /// Whether to skip sending a message identical to the last one sent.
+    pub dedupe_identical: Option<bool>,
+
+    /// Window within which identical messages are skipped.
+    #[serde(with = "humantime_serde")]
+    pub dedupe_window: Option<time::Duration>,
+
+    /// Optional window within which an alarm already sent before a restart
+    /// is not sent again.
+    #[serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")]
+    pub restart_dedupe_window: Option<time::Duration>,
+
+    /// Maximum number of sends queued for the send worker.
+    pub send_queue_size: Option<usize>,
+
+    /// Time each notifier is given to send before it is considered failed.
+    #[serde(with = "humantime_serde")]
+    pub send_timeout: Option<time::Duration>,
+
+    /// Local time until which notifications are silenced for maintenance, as `YYYY-MM-DD HH:MM:SS`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_until: Option<String>,
+
+    /// How long maintenance mode lasts when entered without a duration.
+    #[serde(with = "humantime_serde")]
+    pub maintenance_duration: Option<time::Duration>,
+
+    /// Number of recent alarms and restores to append to alarm messages.
+    pub include_log_tail: Option<usize>,
+}
+
+impl Default for NotificationsConfig {
+    /// Default values for the cross-backend notification settings.
+    fn default() -> Self {
+        Self {
+            max_message_bytes: None,
+            include_config_in_notification: None,
+            send_restored: None,
+            notify_on_partial_failure: None,
+            notify_on_startup_failure: None,
+            partial_failure_message: None,
+            status_report_interval: None,
+            status_report_message: None,
+            dedupe_identical: None,
+            dedupe_window: None,
+            restart_dedupe_window: None,
+            send_queue_size: None,
+            send_timeout: None,
+            maintenance_until: None,
+            maintenance_duration: None,
+            include_log_tail: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Whether to record qualified alarms and restores in the history file.
+    pub enabled: Option<bool>,
+
+    /// Optional path to the history file, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+
+    /// Maximum number of entries to keep in the history file.
+    pub max_entries: Option<usize>,
+
+    /// Whether to rotate a full history file out and gzip it, rather than
+    /// dropping its oldest entries.
+    pub compress_rotated_logs: Option<bool>,
+}
+
+impl Default for HistoryConfig {
+    /// Default values for the history settings.
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            file: None,
+            max_entries: None,
+            compress_rotated_logs: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdcConfig {
+    /// SPI bus the MCP3008 is connected to, 0 to 6.
+    pub spi_bus: Option<u8>,
+
+    /// Chip select line of the MCP3008 on the bus, 0 to 2.
+    pub chip_select: Option<u8>,
+
+    /// MCP3008 channel the sensor is connected to, 0 to 7.
+    pub channel: Option<u8>,
+
+    /// Reading at or above which the input is HIGH, 0 to 1023.
+    pub threshold: Option<u16>,
+
+    /// Margin below the threshold a reading must fall to turn the input LOW again.
+    pub hysteresis: Option<u16>,
+}
+
+impl Default for AdcConfig {
+    /// Default values for the ADC settings.
+    fn default() -> Self {
+        Self {
+            spi_bus: None,
+            chip_select: None,
+            channel: None,
+            threshold: None,
+            hysteresis: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusConfig {
+    /// Whether to listen on the status socket.
+    pub enabled: Option<bool>,
+
+    /// Optional path to the status socket, relative to the configuration directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+
+    /// Number of recent alarms and restores to keep in memory for the status socket.
+    pub history_size: Option<usize>,
+}
+
+impl Default for StatusConfig {
+    /// Default values for the status socket settings.
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            socket: None,
+            history_size: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignalsConfig {
+    /// Signal that reloads the configuration.
+    pub reload: Option<Signal>,
+
+    /// Signal that sends the alarm message through all notifiers as a test.
+    pub test: Option<Signal>,
+
+    /// Signal that prints the current runtime state.
+    pub dump_state: Option<Signal>,
+
+    /// Signal that enters or leaves maintenance mode.
+    pub maintenance: Option<Signal>,
+}
+
+impl Default for SignalsConfig {
+    /// Default values for the signal settings.
+    fn default() -> Self {
+        Self {
+            reload: None,
+            test: None,
+            dump_state: None,
+            maintenance: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Timeout for HTTP requests.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Option<time::Duration>,
+
+    /// How much of a failed response's body to include in error messages, in bytes.
+    pub max_error_body_bytes: Option<usize>,
+
+    /// The HTTP status codes that count as delivered, instead of any 2xx status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success_statuses: Option<Vec<u16>>,
+
+    /// Timeouts of consecutive attempts, instead of the fixed timeout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_timeout_schedule: Option<Vec<humantime_serde::Serde<time::Duration>>>,
+}
+
+impl Default for HttpConfig {
+    /// Default values for the HTTP settings.
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_error_body_bytes: None,
+            success_statuses: None,
+            retry_timeout_schedule: None,
         }
     }
 }
@@ -93,6 +648,13 @@ impl Default for BatsignConfig {
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FileConfig {
+    /// How durations are formatted: `compact` (e.g. `1m 10s`) or `clock` (e.g. `0:01:10`).
+    pub duration_format: Option<DurationFormat>,
+
+    /// Whether to reload the configuration and templates whenever they change
+    /// on disk, as with `--watch-config`.
+    pub auto_reload: Option<bool>,
+
     /// GPIO settings loaded from the configuration file.
     pub gpio: GpioConfig,
 
@@ -101,15 +663,70 @@ pub struct FileConfig {
 
     /// Batsign settings loaded from the configuration file.
     pub batsign: BatsignConfig,
+
+    /// Webhook settings loaded from the configuration file.
+    pub webhook: WebhookConfig,
+
+    /// Command settings loaded from the configuration file.
+    pub command: CommandConfig,
+
+    /// Pipe settings loaded from the configuration file.
+    pub pipe: PipeConfig,
+
+    /// Cross-backend notification settings loaded from the configuration file.
+    pub notifications: NotificationsConfig,
+
+    /// History settings loaded from the configuration file.
+    pub history: HistoryConfig,
+
+    /// ADC settings loaded from the configuration file.
+    pub adc: AdcConfig,
+
+    /// Status socket settings loaded from the configuration file.
+    pub status: StatusConfig,
+
+    /// Signal settings loaded from the configuration file.
+    pub signals: SignalsConfig,
+
+    /// HTTP settings loaded from the configuration file.
+    pub http: HttpConfig,
+
+    /// Friendly names of GPIO pins, keyed by BCM pin number.
+    pub pin_names: BTreeMap<String, String>,
+
+    /// Named sets of settings that override the rest of the file when selected
+    /// with `--profile`. Profiles within profiles are ignored.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, FileConfig>,
+}
+
+impl FileConfig {
+    /// Returns the named profile, if there is one.
+    pub fn profile(&self, name: &str) -> Option<&FileConfig> {
+        self.profiles.get(name)
+    }
 }
 
 impl Default for FileConfig {
     /// Default values for the configuration file.
     fn default() -> Self {
         Self {
+            duration_format: None,
+            auto_reload: None,
             gpio: GpioConfig::default(),
             slack: SlackConfig::default(),
             batsign: BatsignConfig::default(),
+            webhook: WebhookConfig::default(),
+            command: CommandConfig::default(),
+            pipe: PipeConfig::default(),
+            notifications: NotificationsConfig::default(),
+            history: HistoryConfig::default(),
+            adc: AdcConfig::default(),
+            status: StatusConfig::default(),
+            signals: SignalsConfig::default(),
+            http: HttpConfig::default(),
+            pin_names: BTreeMap::new(),
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -118,41 +735,343 @@ impl From<&Settings> for FileConfig {
     /// Converts the resolved settings into a FileConfig, which can be saved to disk.
     fn from(s: &Settings) -> Self {
         Self {
+            duration_format: Some(s.duration_format),
+            auto_reload: Some(s.auto_reload),
             gpio: GpioConfig {
                 pin_number: Some(s.gpio.pin_number),
                 poll_interval: Some(s.gpio.poll_interval),
-                hold: Some(s.gpio.hold),
+                pin_label: s.gpio.pin_label.clone(),
+                severity: Some(s.gpio.severity),
+                alarm_poll_interval: s.gpio.alarm_poll_interval,
+                hold: None,
+                qualify_high_hold: Some(s.gpio.qualify_high_hold),
+                qualify_low_hold: Some(s.gpio.qualify_low_hold),
+                qualify_mode: Some(s.gpio.qualify_mode),
+                qualify_m: Some(s.gpio.qualify_m),
+                qualify_n: Some(s.gpio.qualify_n),
+                input_mode: None,
+                baseline_level: Some(s.gpio.baseline_level),
+                pull: Some(s.gpio.pull),
+                sysfs_base: s.gpio.sysfs_base.clone(),
+                input_source: Some(s.gpio.input_source),
+                notify_on_startup_alarm: Some(s.gpio.notify_on_startup_alarm),
+                read_error_timeout: Some(s.gpio.read_error_timeout),
+                post_notify_deadtime: Some(s.gpio.post_notify_deadtime),
+                startup_delay: Some(s.gpio.startup_delay),
+                startup_jitter: Some(s.gpio.startup_jitter),
+                calibration_duration: Some(s.gpio.calibration_duration),
+                flap_threshold: s.gpio.flap_threshold,
+                flap_window: Some(s.gpio.flap_window),
+                coalesce_window: s.gpio.coalesce_window,
+                extreme_durations: Some(s.gpio.extreme_durations),
             },
 
             slack: SlackConfig {
                 enabled: Some(s.slack.enabled),
                 urls: Some(s.slack.urls.clone()),
+                alarm_template: template_override(
+                    s,
+                    &s.paths.slack_alarm_template,
+                    defaults::slack::ALARM_MESSAGE_TEMPLATE_FILENAME,
+                ),
+                restored_template: template_override(
+                    s,
+                    &s.paths.slack_restored_template,
+                    defaults::slack::RESTORED_MESSAGE_TEMPLATE_FILENAME,
+                ),
                 notification_interval: Some(s.slack.notification_interval),
                 retry_interval: Some(s.slack.retry_interval),
+                severities: Some(s.slack.severities.clone()),
             },
 
             batsign: BatsignConfig {
                 enabled: Some(s.batsign.enabled),
                 urls: Some(s.batsign.urls.clone()),
+                urls_source: s.batsign.urls_source.clone(),
+                request_style: Some(s.batsign.request_style),
+                line_ending: Some(s.batsign.line_ending),
+                mode: Some(s.batsign.mode),
+                failover_health_window: Some(s.batsign.failover_health_window),
+                hmac_secret: s.batsign.hmac_secret.clone(),
+                hmac_header: Some(s.batsign.hmac_header.clone()),
+                hmac_algorithm: Some(s.batsign.hmac_algorithm),
+                alarm_template: template_override(
+                    s,
+                    &s.paths.batsign_alarm_template,
+                    defaults::batsign::ALARM_MESSAGE_TEMPLATE_FILENAME,
+                ),
+                restored_template: template_override(
+                    s,
+                    &s.paths.batsign_restored_template,
+                    defaults::batsign::RESTORED_MESSAGE_TEMPLATE_FILENAME,
+                ),
                 notification_interval: Some(s.batsign.notification_interval),
                 retry_interval: Some(s.batsign.retry_interval),
+                severities: Some(s.batsign.severities.clone()),
+                require_reachable_on_start: Some(s.batsign.require_reachable_on_start),
+                reachable_wait: Some(s.batsign.reachable_wait),
+                pin_dns: Some(s.batsign.pin_dns),
             },
+
+            webhook: WebhookConfig {
+                enabled: Some(s.webhook.enabled),
+                urls: Some(s.webhook.urls.clone()),
+                alarm_template: template_override(
+                    s,
+                    &s.paths.webhook_alarm_template,
+                    defaults::webhook::ALARM_PAYLOAD_TEMPLATE_FILENAME,
+                ),
+                restored_template: template_override(
+                    s,
+                    &s.paths.webhook_restored_template,
+                    defaults::webhook::RESTORED_PAYLOAD_TEMPLATE_FILENAME,
+                ),
+                notification_interval: Some(s.webhook.notification_interval),
+                retry_interval: Some(s.webhook.retry_interval),
+                severities: Some(s.webhook.severities.clone()),
+                gzip_urls: Some(s.webhook.gzip_urls.clone()),
+                gzip_min_bytes: Some(s.webhook.gzip_min_bytes),
+            },
+
+            command: CommandConfig {
+                enabled: Some(s.command.enabled),
+                on_alarm_command: Some(s.command.alarm_command.clone()),
+                on_restore_command: Some(s.command.restored_command.clone()),
+                notification_interval: Some(s.command.notification_interval),
+                retry_interval: Some(s.command.retry_interval),
+                severities: Some(s.command.severities.clone()),
+            },
+
+            pipe: PipeConfig {
+                enabled: Some(s.pipe.enabled),
+                command: Some(s.pipe.command.clone()),
+                alarm_message: Some(s.pipe.alarm_message.clone()),
+                restored_message: Some(s.pipe.restored_message.clone()),
+                notification_interval: Some(s.pipe.notification_interval),
+                retry_interval: Some(s.pipe.retry_interval),
+                severities: Some(s.pipe.severities.clone()),
+            },
+
+            notifications: NotificationsConfig {
+                max_message_bytes: Some(s.notifications.max_message_bytes),
+                include_config_in_notification: Some(
+                    s.notifications.include_config_in_notification,
+                ),
+                send_restored: Some(s.notifications.send_restored),
+                notify_on_partial_failure: Some(s.notifications.notify_on_partial_failure),
+                notify_on_startup_failure: Some(s.notifications.notify_on_startup_failure),
+                partial_failure_message: Some(s.notifications.partial_failure_message.clone()),
+                status_report_interval: s.notifications.status_report_interval,
+                status_report_message: Some(s.notifications.status_report_message.clone()),
+                dedupe_identical: Some(s.notifications.dedupe_identical),
+                dedupe_window: Some(s.notifications.dedupe_window),
+                restart_dedupe_window: s.notifications.restart_dedupe_window,
+                send_queue_size: Some(s.notifications.send_queue_size),
+                send_timeout: Some(s.notifications.send_timeout),
+                maintenance_until: s.notifications.maintenance_until.clone(),
+                maintenance_duration: Some(s.notifications.maintenance_duration),
+                include_log_tail: Some(s.notifications.include_log_tail),
+            },
+
+            history: HistoryConfig {
+                enabled: Some(s.history.enabled),
+                file: template_override(s, &s.paths.history_file, defaults::history::FILENAME),
+                max_entries: Some(s.history.max_entries),
+                compress_rotated_logs: Some(s.history.compress_rotated_logs),
+            },
+
+            adc: AdcConfig {
+                spi_bus: Some(s.adc.spi_bus),
+                chip_select: Some(s.adc.chip_select),
+                channel: Some(s.adc.channel),
+                threshold: Some(s.adc.threshold),
+                hysteresis: Some(s.adc.hysteresis),
+            },
+
+            status: StatusConfig {
+                enabled: Some(s.status.enabled),
+                socket: template_override(
+                    s,
+                    &s.paths.status_socket,
+                    defaults::status::SOCKET_FILENAME,
+                ),
+                history_size: Some(s.status.history_size),
+            },
+
+            signals: SignalsConfig {
+                reload: Some(s.signals.reload),
+                test: Some(s.signals.test),
+                dump_state: Some(s.signals.dump_state),
+                maintenance: Some(s.signals.maintenance),
+            },
+
+            http: HttpConfig {
+                timeout: Some(s.http.timeout),
+                max_error_body_bytes: Some(s.http.max_error_body_bytes),
+                success_statuses: s.http.success_statuses.clone(),
+                retry_timeout_schedule: s.http.retry_timeout_schedule.as_ref().map(|schedule| {
+                    schedule
+                        .iter()
+                        .map(|&timeout| humantime_serde::Serde::from(timeout))
+                        .collect()
+                }),
+            },
+
+            pin_names: s.pin_names.clone(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+/// Returns the path to a template or other resource file if it differs from the
+/// default path, so that only actual overrides are saved to the configuration file.
+fn template_override(s: &Settings, path: &Path, default_filename: &str) -> Option<PathBuf> {
+    (path != s.paths.config_dir.join(default_filename)).then(|| path.to_path_buf())
+}
+
+/// Format of a configuration file, as decided by its extension.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigFormat {
+    /// TOML, the default.
+    Toml,
+
+    /// JSON.
+    Json,
+
+    /// YAML.
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Decides the format of the passed configuration file path by its extension,
+    /// falling back to TOML for unknown or missing extensions.
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            _ => Ok(Self::Toml),
         }
     }
 }
 
 /// Deserializes the configuration file from disk, returning an optional FileConfig.
-pub fn deserialize_config_file(
-    config_file: &PathBuf,
-) -> Result<Option<FileConfig>, confy::ConfyError> {
+///
+/// The format is decided by the file extension; see `ConfigFormat::from_path`.
+pub fn deserialize_config_file(config_file: &PathBuf) -> Result<Option<FileConfig>, String> {
     if !config_file.exists() {
         return Ok(None);
     }
 
-    match confy::load_path(config_file) {
-        Ok(cfg) => Ok(Some(cfg)),
-        Err(e) => Err(e),
+    match ConfigFormat::from_path(config_file)? {
+        ConfigFormat::Toml => confy::load_path(config_file).map_err(|e| e.to_string()),
+        ConfigFormat::Json => {
+            let text = fs::read_to_string(config_file).map_err(|e| e.to_string())?;
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        }
+        ConfigFormat::Yaml => {
+            let text = fs::read_to_string(config_file).map_err(|e| e.to_string())?;
+            serde_yaml::from_str(&text).map_err(|e| e.to_string())
+        }
+    }
+    .map(Some)
+}
+
+/// Serializes the passed FileConfig to text, as it would be written to the
+/// passed file, in the format decided by the file extension.
+pub fn render_config(config_file: &Path, config: &FileConfig) -> Result<String, String> {
+    match ConfigFormat::from_path(config_file)? {
+        ConfigFormat::Toml => toml::to_string_pretty(config).map_err(|e| e.to_string()),
+        ConfigFormat::Json => serde_json::to_string_pretty(config).map_err(|e| e.to_string()),
+        ConfigFormat::Yaml => serde_yaml::to_string(config).map_err(|e| e.to_string()),
+    }
+}
+
+/// Serializes the passed FileConfig to disk, in the format decided by the file extension.
+pub fn store_config_file(config_file: &Path, config: FileConfig) -> Result<(), String> {
+    let text = render_config(config_file, &config)?;
+    write_atomic(config_file, text).map_err(|e| e.to_string())
+}
+
+/// Writes `contents` to `path` by way of a temporary sibling file that is
+/// synced and then renamed over the target, so that a power loss midway
+/// leaves either the old or the new file on disk, never a truncated one.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    drop(file);
+
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    // Sync the directory too, so the rename itself survives a power loss.
+    // Not all platforms allow opening a directory; that part is best-effort.
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty())
+        && let Ok(dir) = fs::File::open(dir)
+    {
+        let _ = dir.sync_all();
     }
+
+    Ok(())
+}
+
+/// Returns a unified diff between the configuration file on disk and the
+/// passed FileConfig as `store_config_file` would write it, or `None` if
+/// they are the same. A missing file is diffed as if it were empty.
+pub fn diff_config_file(config_file: &Path, config: &FileConfig) -> Result<Option<String>, String> {
+    let current = match fs::read_to_string(config_file) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let proposed = render_config(config_file, config)?;
+    Ok(diff_text(config_file, &current, &proposed))
+}
+
+/// Returns a unified diff between the template file on disk and the passed
+/// default template, or `None` if they are the same. Leading and trailing
+/// whitespace is ignored, as it is when templates are loaded. A missing file
+/// is diffed as if it were empty.
+pub fn diff_template_file(path: &Path, default: &str) -> io::Result<Option<String>> {
+    let current = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let with_newline = |text: &str| match text.trim() {
+        "" => String::new(),
+        trimmed => format!("{trimmed}\n"),
+    };
+
+    Ok(diff_text(
+        path,
+        &with_newline(&current),
+        &with_newline(default),
+    ))
+}
+
+/// Returns a unified diff from `current` to `proposed`, both labelled with the
+/// passed path, or `None` if they are the same.
+fn diff_text(path: &Path, current: &str, proposed: &str) -> Option<String> {
+    if current == proposed {
+        return None;
+    }
+
+    let name = path.display().to_string();
+    let diff = TextDiff::from_lines(current, proposed)
+        .unified_diff()
+        .header(&name, &name)
+        .to_string();
+
+    Some(diff)
 }
 
 /// Resolves the configuration directory path, returning the directory as a string and an optional PathBuf.
@@ -175,3 +1094,107 @@ pub fn resolve_default_config_directory_from_env() -> Result<PathBuf, String> {
 
     Err("could not resolve default configuration directory from environment variables".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/config.toml")),
+            Ok(ConfigFormat::Toml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/config.json")),
+            Ok(ConfigFormat::Json)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/config")),
+            Ok(ConfigFormat::Toml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/config.yml")),
+            Ok(ConfigFormat::Yaml)
+        );
+    }
+
+    #[test]
+    fn test_yaml_round_trips() {
+        let path = env::temp_dir().join(format!("pellx_yaml_{}.yaml", std::process::id()));
+        let mut config = FileConfig::default();
+        config.gpio.pin_number = Some(17);
+        config.gpio.poll_interval = Some(time::Duration::from_secs(90));
+        store_config_file(&path, config).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("pin_number: 17\n"));
+
+        let read = deserialize_config_file(&path).unwrap().unwrap();
+        assert_eq!(read.gpio.pin_number, Some(17));
+        assert_eq!(read.gpio.poll_interval, Some(time::Duration::from_secs(90)));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_durations_parse_in_json() {
+        let json: FileConfig =
+            serde_json::from_str(r#"{ "gpio": { "poll_interval": "1m 30s" } }"#).unwrap();
+
+        assert_eq!(json.gpio.poll_interval, Some(time::Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_diff_config_file() {
+        let path = env::temp_dir().join(format!("pellx_diff_{}.toml", std::process::id()));
+        let mut config = FileConfig::default();
+        config.gpio.pin_number = Some(24);
+        fs::write(&path, render_config(&path, &config).unwrap()).unwrap();
+
+        assert_eq!(diff_config_file(&path, &config), Ok(None));
+
+        config.gpio.pin_number = Some(17);
+        let diff = diff_config_file(&path, &config).unwrap().unwrap();
+        assert!(diff.contains("-pin_number = 24\n"));
+        assert!(diff.contains("+pin_number = 17\n"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_diff_template_file() {
+        let path = env::temp_dir().join(format!("pellx_template_{}.txt", std::process::id()));
+
+        let diff = diff_template_file(&path, "Alarm!\n").unwrap().unwrap();
+        assert!(diff.contains("+Alarm!\n"));
+
+        fs::write(&path, "  Alarm!").unwrap();
+        assert_eq!(diff_template_file(&path, "Alarm!\n").unwrap(), None);
+
+        fs::write(&path, "Subject: Alarm\nBurner down.\n").unwrap();
+        let diff = diff_template_file(&path, "Subject: Alarm\nBurner is down.")
+            .unwrap()
+            .unwrap();
+        assert!(diff.contains("-Burner down.\n"));
+        assert!(diff.contains("+Burner is down.\n"));
+        assert!(!diff.contains("-Subject"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let path = env::temp_dir().join(format!("pellx_atomic_{}.toml", std::process::id()));
+        fs::write(&path, "old contents, much longer than the new ones\n").unwrap();
+
+        write_atomic(&path, "new\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        assert!(!Path::new(&tmp).exists());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,676 @@
+//! Reading of the monitored GPIO pin.
+//!
+//! This module defines our own `Level` enum and the `PinReader` trait, so that
+//! the rest of the program doesn't depend on `rppal` directly. There are five
+//! implementations of `PinReader` that read a level:
+//!
+//! * `RppalPin`, reading an actual GPIO pin. Only available on Linux with the
+//!   `real-gpio` feature enabled (which it is by default), and not when the
+//!   `mock-gpio` feature is.
+//! * `SysfsPin`, reading an actual GPIO pin through the deprecated sysfs
+//!   interface, for kernels where `rppal` doesn't work. Only available with
+//!   the `sysfs-gpio` feature enabled, and used if `sysfs_base` is set.
+//! * `Mcp3008Pin`, reading a channel of an MCP3008 ADC over SPI and comparing
+//!   it against a threshold, for analog sensors. Available under the same
+//!   conditions as `RppalPin`, and used if `input_source` is `adc`.
+//! * `ScriptedPin`, replaying a sequence of timed readings from a file, as
+//!   passed with `--simulate`. Always available.
+//! * `FakePin`, reading a fixed or regularly toggling level, as passed with
+//!   `--fake-level`. Always available.
+//!
+//! Any of them can be wrapped in an `InvertedPin`, which reports the opposite
+//! level, for pins that idle HIGH and read LOW on an alarm.
+//!
+//! The `--simulate` file format is one reading per line, in the form
+//! `<offset> <level>`, where the offset is a duration since the start (e.g.
+//! `90s` or `1m 30s`) and the level is `HIGH` or `LOW`. Empty lines and lines
+//! starting with `#` are ignored. The same format is used by `--explain`.
+
+use std::fmt;
+use std::fs;
+use std::ops;
+#[cfg(feature = "sysfs-gpio")]
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(any(
+    feature = "sysfs-gpio",
+    all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio"))
+))]
+use crate::defaults;
+
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+use rppal::gpio::InputPin;
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+
+/// Logic level of a GPIO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// The pin reads LOW; the burner is fine.
+    Low,
+
+    /// The pin reads HIGH; the burner is in an error state.
+    High,
+}
+
+impl fmt::Display for Level {
+    /// Formats the level as `HIGH` or `LOW`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "LOW"),
+            Self::High => write!(f, "HIGH"),
+        }
+    }
+}
+
+impl ops::Not for Level {
+    type Output = Self;
+
+    /// Returns the opposite level.
+    fn not(self) -> Self {
+        match self {
+            Self::Low => Self::High,
+            Self::High => Self::Low,
+        }
+    }
+}
+
+/// Something that can be read for the current level of the monitored pin.
+pub trait PinReader {
+    /// Reads the current level of the pin.
+    ///
+    /// Errors are expected to be transient; the monitor loop logs them and
+    /// keeps polling.
+    fn read(&mut self) -> Result<Level, String>;
+}
+
+/// An actual GPIO pin, set up as an input with pull-up.
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+pub struct RppalPin {
+    /// The underlying `rppal` input pin.
+    pin: InputPin,
+}
+
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+impl RppalPin {
+    /// Creates a new `RppalPin` reading the passed input pin.
+    pub fn new(pin: InputPin) -> Self {
+        Self { pin }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+impl PinReader for RppalPin {
+    /// Reads the pin, translating the `rppal` level into our own.
+    fn read(&mut self) -> Result<Level, String> {
+        match self.pin.read() {
+            rppal::gpio::Level::Low => Ok(Level::Low),
+            rppal::gpio::Level::High => Ok(Level::High),
+        }
+    }
+}
+
+/// A channel of an MCP3008 ADC read over SPI, compared against a threshold to
+/// give a level.
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+pub struct Mcp3008Pin {
+    /// The SPI bus the ADC is on.
+    spi: Spi,
+
+    /// The ADC channel read.
+    channel: u8,
+
+    /// The threshold the readings are compared against.
+    threshold: Threshold,
+}
+
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+impl Mcp3008Pin {
+    /// Opens the MCP3008 on the passed SPI bus and chip select line, to read
+    /// the passed channel. A bus above 6 or a chip select line above 2 is an
+    /// error, as the Raspberry Pi has none.
+    pub fn open(
+        spi_bus: u8,
+        chip_select: u8,
+        channel: u8,
+        threshold: Threshold,
+    ) -> Result<Self, rppal::spi::Error> {
+        let bus = match spi_bus {
+            0 => Bus::Spi0,
+            1 => Bus::Spi1,
+            2 => Bus::Spi2,
+            3 => Bus::Spi3,
+            4 => Bus::Spi4,
+            5 => Bus::Spi5,
+            6 => Bus::Spi6,
+            _ => return Err(invalid_spi_setting(format!("no SPI bus {spi_bus}"))),
+        };
+
+        let slave_select = match chip_select {
+            0 => SlaveSelect::Ss0,
+            1 => SlaveSelect::Ss1,
+            2 => SlaveSelect::Ss2,
+            _ => {
+                return Err(invalid_spi_setting(format!(
+                    "no chip select line {chip_select}"
+                )));
+            }
+        };
+
+        let spi = Spi::new(bus, slave_select, defaults::adc::CLOCK_SPEED, Mode::Mode0)?;
+
+        Ok(Self {
+            spi,
+            channel,
+            threshold,
+        })
+    }
+
+    /// Returns the request for a single-ended reading of the passed channel:
+    /// a start bit, then the single-ended bit and the channel number.
+    fn request(channel: u8) -> [u8; 3] {
+        [0x01, (0x08 | (channel & 0x07)) << 4, 0x00]
+    }
+
+    /// Returns the 10-bit reading in the passed response, which ends with
+    /// the two most significant bits and then the eight least.
+    fn value(response: [u8; 3]) -> u16 {
+        (u16::from(response[1] & 0x03) << 8) | u16::from(response[2])
+    }
+}
+
+/// Returns an SPI error for a bus or chip select line that doesn't exist.
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+fn invalid_spi_setting(message: String) -> rppal::spi::Error {
+    rppal::spi::Error::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        message,
+    ))
+}
+
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+impl PinReader for Mcp3008Pin {
+    /// Reads the channel and compares the reading against the threshold.
+    fn read(&mut self) -> Result<Level, String> {
+        let mut response = [0; 3];
+
+        self.spi
+            .transfer(&mut response, &Self::request(self.channel))
+            .map_err(|e| format!("failed to read ADC channel {}: {e}", self.channel))?;
+
+        Ok(self.threshold.level(Self::value(response)))
+    }
+}
+
+/// Compares readings of an analog input against a threshold, with hysteresis.
+///
+/// A reading at or above the threshold is HIGH. Once HIGH, the input stays
+/// HIGH until a reading falls below the threshold by more than the
+/// hysteresis, so that a reading hovering around the threshold doesn't flap.
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+pub struct Threshold {
+    /// Reading at or above which the input is HIGH.
+    threshold: u16,
+
+    /// Margin below the threshold a reading must fall to turn LOW again.
+    hysteresis: u16,
+
+    /// The level of the last reading.
+    level: Level,
+}
+
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+impl Threshold {
+    /// Creates a new `Threshold` with the passed threshold and hysteresis,
+    /// starting out LOW.
+    pub fn new(threshold: u16, hysteresis: u16) -> Self {
+        Self {
+            threshold,
+            hysteresis,
+            level: Level::Low,
+        }
+    }
+
+    /// Returns the level of the passed reading.
+    fn level(&mut self, value: u16) -> Level {
+        self.level = match self.level {
+            Level::Low if value >= self.threshold => Level::High,
+            Level::High if value < self.threshold.saturating_sub(self.hysteresis) => Level::Low,
+            level => level,
+        };
+
+        self.level
+    }
+}
+
+/// An actual GPIO pin read through the sysfs interface, e.g.
+/// `/sys/class/gpio/gpio24/value`.
+///
+/// The pin is exported if it isn't already, and then unexported again when
+/// the `SysfsPin` is dropped. The pull resistor can't be configured this way.
+#[cfg(feature = "sysfs-gpio")]
+pub struct SysfsPin {
+    /// The sysfs GPIO directory, e.g. `/sys/class/gpio`.
+    base: PathBuf,
+
+    /// The GPIO pin number.
+    pin_number: u8,
+
+    /// Whether the pin was exported by us, and should be unexported when done.
+    exported: bool,
+}
+
+#[cfg(feature = "sysfs-gpio")]
+impl SysfsPin {
+    /// Opens the passed pin under the passed sysfs GPIO directory as an input,
+    /// exporting it first if needed.
+    pub fn open(base: &Path, pin_number: u8) -> Result<Self, String> {
+        let mut pin = Self {
+            base: base.to_path_buf(),
+            pin_number,
+            exported: false,
+        };
+
+        if !pin.pin_dir().exists() {
+            fs::write(base.join("export"), pin_number.to_string())
+                .map_err(|e| format!("failed to export GPIO{pin_number}: {e}"))?;
+            pin.exported = true;
+            pin.wait_for_export()?;
+        }
+
+        fs::write(pin.pin_dir().join("direction"), "in")
+            .map_err(|e| format!("failed to set GPIO{pin_number} as an input: {e}"))?;
+
+        Ok(pin)
+    }
+
+    /// Returns the directory of the pin, e.g. `/sys/class/gpio/gpio24`.
+    fn pin_dir(&self) -> PathBuf {
+        self.base.join(format!("gpio{}", self.pin_number))
+    }
+
+    /// Waits for the kernel to create the files of a newly exported pin,
+    /// which may take a moment.
+    fn wait_for_export(&self) -> Result<(), String> {
+        let deadline = Instant::now() + defaults::gpio::SYSFS_EXPORT_TIMEOUT;
+
+        while !self.pin_dir().join("value").exists() {
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "GPIO{} was exported but {} did not appear",
+                    self.pin_number,
+                    self.pin_dir().join("value").display()
+                ));
+            }
+
+            std::thread::sleep(defaults::gpio::SYSFS_EXPORT_STEP);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sysfs-gpio")]
+impl PinReader for SysfsPin {
+    /// Reads the `value` file of the pin, which holds `0` or `1`.
+    fn read(&mut self) -> Result<Level, String> {
+        let value = fs::read_to_string(self.pin_dir().join("value")).map_err(|e| e.to_string())?;
+
+        match value.trim() {
+            "0" => Ok(Level::Low),
+            "1" => Ok(Level::High),
+            other => Err(format!("unexpected value \"{other}\"")),
+        }
+    }
+}
+
+#[cfg(feature = "sysfs-gpio")]
+impl Drop for SysfsPin {
+    /// Unexports the pin if it was exported by us.
+    fn drop(&mut self) {
+        if self.exported
+            && let Err(e) = fs::write(self.base.join("unexport"), self.pin_number.to_string())
+        {
+            eprintln!("[!] Failed to unexport GPIO{}: {e}", self.pin_number);
+        }
+    }
+}
+
+/// A pin read inverted, for pins that idle HIGH and read LOW on an alarm.
+pub struct InvertedPin {
+    /// The underlying pin.
+    pin: Box<dyn PinReader>,
+}
+
+impl InvertedPin {
+    /// Creates a new `InvertedPin` reading the passed pin.
+    pub fn new(pin: Box<dyn PinReader>) -> Self {
+        Self { pin }
+    }
+}
+
+impl PinReader for InvertedPin {
+    /// Reads the underlying pin and returns the opposite level.
+    fn read(&mut self) -> Result<Level, String> {
+        self.pin.read().map(|level| !level)
+    }
+}
+
+/// Counts of the levels read while sampling the pin to infer its baseline
+/// level, for `--calibrate`.
+#[derive(Debug, Default)]
+pub struct Calibration {
+    /// Number of HIGH readings.
+    pub highs: u32,
+
+    /// Number of LOW readings.
+    pub lows: u32,
+
+    /// Number of failed readings.
+    pub errors: u32,
+}
+
+impl Calibration {
+    /// Records a reading of the pin.
+    pub fn record(&mut self, reading: &Result<Level, String>) {
+        match reading {
+            Ok(Level::High) => self.highs += 1,
+            Ok(Level::Low) => self.lows += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+
+    /// Returns the level read most often, taken to be the normal level, or
+    /// `None` if no reading succeeded. A tie is taken as LOW.
+    pub fn baseline(&self) -> Option<Level> {
+        match (self.highs, self.lows) {
+            (0, 0) => None,
+            (highs, lows) if highs > lows => Some(Level::High),
+            _ => Some(Level::Low),
+        }
+    }
+}
+
+/// A single pin reading at a given offset from the start of a sequence.
+#[derive(Debug, PartialEq)]
+pub struct Sample {
+    /// Time since the start of the sequence.
+    pub offset: Duration,
+
+    /// Level read from the pin.
+    pub level: Level,
+}
+
+/// Parses a sequence of samples, one per line.
+pub fn parse_samples(text: &str) -> Result<Vec<Sample>, String> {
+    let mut samples: Vec<Sample> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((offset, level)) = line.rsplit_once(char::is_whitespace) else {
+            return Err(format!("line {}: expected `<offset> <level>`", i + 1));
+        };
+
+        let offset = humantime::parse_duration(offset.trim())
+            .map_err(|e| format!("line {}: invalid offset: {e}", i + 1))?;
+
+        let level = match level.to_ascii_uppercase().as_str() {
+            "HIGH" | "1" => Level::High,
+            "LOW" | "0" => Level::Low,
+            _ => return Err(format!("line {}: invalid level \"{level}\"", i + 1)),
+        };
+
+        if let Some(last) = samples.last()
+            && offset < last.offset
+        {
+            return Err(format!("line {}: offsets must not decrease", i + 1));
+        }
+
+        samples.push(Sample { offset, level });
+    }
+
+    Ok(samples)
+}
+
+/// Reads and parses a file of samples.
+pub fn read_samples(path: &str) -> Result<Vec<Sample>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_samples(&text)
+}
+
+/// A simulated pin, replaying a sequence of timed readings in real time.
+///
+/// Before the first sample the pin reads LOW, and after the last one it keeps
+/// reading the level of the last sample.
+pub struct ScriptedPin {
+    /// The samples to replay, in order of increasing offset.
+    samples: Vec<Sample>,
+
+    /// The timestamp the offsets are relative to.
+    started: Instant,
+}
+
+impl ScriptedPin {
+    /// Creates a new `ScriptedPin` replaying the passed samples, starting now.
+    pub fn new(samples: Vec<Sample>) -> Self {
+        Self {
+            samples,
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns the level of the pin at the passed offset from the start.
+    fn level_at(&self, elapsed: Duration) -> Level {
+        self.samples
+            .iter()
+            .take_while(|sample| sample.offset <= elapsed)
+            .last()
+            .map_or(Level::Low, |sample| sample.level)
+    }
+}
+
+impl PinReader for ScriptedPin {
+    /// Reads the level the script says the pin should be at right now.
+    fn read(&mut self) -> Result<Level, String> {
+        Ok(self.level_at(self.started.elapsed()))
+    }
+}
+
+/// A fake pin, reading either a fixed level or one that toggles between LOW
+/// and HIGH on a schedule, starting LOW.
+pub struct FakePin {
+    /// The level read, if fixed.
+    level: Option<Level>,
+
+    /// How long each level is read for, if toggling.
+    toggle_interval: Duration,
+
+    /// The timestamp the toggling is relative to.
+    started: Instant,
+}
+
+impl FakePin {
+    /// Creates a new `FakePin` always reading the passed level.
+    pub fn steady(level: Level) -> Self {
+        Self {
+            level: Some(level),
+            toggle_interval: Duration::ZERO,
+            started: Instant::now(),
+        }
+    }
+
+    /// Creates a new `FakePin` toggling its level every `toggle_interval`,
+    /// starting now.
+    pub fn toggling(toggle_interval: Duration) -> Self {
+        Self {
+            level: None,
+            toggle_interval,
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns the level of the pin at the passed offset from the start.
+    fn level_at(&self, elapsed: Duration) -> Level {
+        if let Some(level) = self.level {
+            return level;
+        }
+
+        match (elapsed.as_nanos() / self.toggle_interval.as_nanos().max(1)) % 2 {
+            0 => Level::Low,
+            _ => Level::High,
+        }
+    }
+}
+
+impl PinReader for FakePin {
+    /// Reads the fixed level, or the level the toggling is at right now.
+    fn read(&mut self) -> Result<Level, String> {
+        Ok(self.level_at(self.started.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_samples() {
+        let text = "# Burner trips and recovers\n\
+            0s HIGH\n\
+            \n\
+            1m 30s low\n\
+            2m 1\n";
+
+        let samples = parse_samples(text).unwrap();
+
+        assert_eq!(
+            samples,
+            vec![
+                Sample {
+                    offset: Duration::ZERO,
+                    level: Level::High
+                },
+                Sample {
+                    offset: Duration::from_secs(90),
+                    level: Level::Low
+                },
+                Sample {
+                    offset: Duration::from_secs(120),
+                    level: Level::High
+                },
+            ]
+        );
+
+        assert!(parse_samples("10s HIGH\n5s LOW").is_err());
+        assert!(parse_samples("10s MAYBE").is_err());
+        assert!(parse_samples("HIGH").is_err());
+    }
+
+    #[test]
+    fn test_scripted_pin_level_at() {
+        let pin = ScriptedPin::new(parse_samples("10s HIGH\n20s LOW").unwrap());
+
+        assert_eq!(pin.level_at(Duration::from_secs(5)), Level::Low);
+        assert_eq!(pin.level_at(Duration::from_secs(10)), Level::High);
+        assert_eq!(pin.level_at(Duration::from_secs(19)), Level::High);
+        assert_eq!(pin.level_at(Duration::from_secs(600)), Level::Low);
+    }
+
+    #[test]
+    fn test_fake_pin_level_at() {
+        let pin = FakePin::steady(Level::High);
+        assert_eq!(pin.level_at(Duration::from_secs(600)), Level::High);
+
+        let pin = FakePin::toggling(Duration::from_secs(60));
+        assert_eq!(pin.level_at(Duration::from_secs(59)), Level::Low);
+        assert_eq!(pin.level_at(Duration::from_secs(60)), Level::High);
+        assert_eq!(pin.level_at(Duration::from_secs(150)), Level::Low);
+    }
+
+    #[test]
+    fn test_calibration_baseline() {
+        let mut calibration = Calibration::default();
+        assert_eq!(calibration.baseline(), None);
+
+        calibration.record(&Err("busy".to_string()));
+        assert_eq!(calibration.baseline(), None);
+
+        calibration.record(&Ok(Level::High));
+        calibration.record(&Ok(Level::Low));
+        assert_eq!(calibration.baseline(), Some(Level::Low));
+
+        calibration.record(&Ok(Level::High));
+        assert_eq!(calibration.baseline(), Some(Level::High));
+        assert_eq!(calibration.errors, 1);
+    }
+
+    #[cfg(feature = "sysfs-gpio")]
+    #[test]
+    fn test_sysfs_pin() {
+        let base =
+            std::env::temp_dir().join(format!("pellx_monitor_sysfs_test_{}", std::process::id()));
+        let pin_dir = base.join("gpio24");
+        fs::create_dir_all(&pin_dir).unwrap();
+        fs::write(pin_dir.join("value"), "1\n").unwrap();
+
+        let mut pin = SysfsPin::open(&base, 24).unwrap();
+        assert_eq!(fs::read_to_string(pin_dir.join("direction")).unwrap(), "in");
+        assert_eq!(pin.read(), Ok(Level::High));
+
+        fs::write(pin_dir.join("value"), "0\n").unwrap();
+        assert_eq!(pin.read(), Ok(Level::Low));
+
+        fs::write(pin_dir.join("value"), "x\n").unwrap();
+        assert!(pin.read().is_err());
+
+        // Already exported, so it is left exported when dropped
+        drop(pin);
+        assert!(!base.join("unexport").exists());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_inverted_pin() {
+        let scripted = ScriptedPin::new(parse_samples("0s HIGH").unwrap());
+        let mut pin = InvertedPin::new(Box::new(scripted));
+
+        assert_eq!(pin.read(), Ok(Level::Low));
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+    fn test_threshold() {
+        let mut threshold = Threshold::new(512, 16);
+
+        assert_eq!(threshold.level(100), Level::Low);
+        assert_eq!(threshold.level(511), Level::Low);
+        assert_eq!(threshold.level(512), Level::High);
+
+        // Stays HIGH within the hysteresis
+        assert_eq!(threshold.level(500), Level::High);
+        assert_eq!(threshold.level(496), Level::High);
+        assert_eq!(threshold.level(495), Level::Low);
+
+        // Stays LOW until the threshold itself is reached again
+        assert_eq!(threshold.level(505), Level::Low);
+        assert_eq!(threshold.level(1023), Level::High);
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+    fn test_mcp3008_frames() {
+        assert_eq!(Mcp3008Pin::request(0), [0x01, 0x80, 0x00]);
+        assert_eq!(Mcp3008Pin::request(7), [0x01, 0xf0, 0x00]);
+
+        assert_eq!(Mcp3008Pin::value([0xff, 0xfb, 0xff]), 1023);
+        assert_eq!(Mcp3008Pin::value([0x00, 0x02, 0x00]), 512);
+        assert_eq!(Mcp3008Pin::value([0x00, 0x00, 0x2a]), 42);
+    }
+}
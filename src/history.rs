@@ -0,0 +1,374 @@
+//! Persistent record of qualified alarms and restores.
+//!
+//! Each time notifications are attempted for a qualified alarm or restore,
+//! an entry is appended to the history file as a line of JSON. The file is
+//! capped to a configured number of entries, dropping the oldest ones, unless
+//! rotated logs are compressed. A full file is then instead rotated out to a
+//! timestamped segment next to it, which is gzipped on a background thread so
+//! as not to hold up the monitor loop, and a new file is started.
+//!
+//! Unlike terminal output, this is a curated record meant to be reviewed after
+//! the fact, with `--history`. Each outcome carries a receipt of the delivery
+//! where one was attempted, so that it can be shown which endpoint was sent
+//! what, and when.
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+use crate::duration::{DurationFormat, format_duration};
+use crate::file_config;
+
+/// Receipt of a single delivery attempt by a notifier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// Where the message was delivered to, with any secret part of the URL
+    /// left out, e.g. `https://hooks.slack.com/…`.
+    pub target: String,
+
+    /// HTTP status of the response, if there was one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Time the attempt took, in milliseconds.
+    pub latency_ms: u64,
+
+    /// Number of attempts at sending the message so far, including this one.
+    pub attempts: u32,
+}
+
+/// Outcome of a single notifier's attempt at sending a notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Outcome {
+    /// Name of the notifier, e.g. `slack[0]`.
+    pub notifier: String,
+
+    /// Error message if the attempt failed, `None` if it succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Receipt of the delivery attempt, if one was made. Missing in entries
+    /// written before receipts were recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<Receipt>,
+}
+
+/// A single entry in the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Local wall-clock time of the event.
+    pub timestamp: String,
+
+    /// `alarm` or `restored`.
+    pub event: String,
+
+    /// The GPIO pin number being monitored.
+    pub pin_number: u8,
+
+    /// Friendly name of the GPIO pin.
+    pub pin_name: String,
+
+    /// Number of seconds the pin had been at its level when the notifications were attempted.
+    pub since_secs: u64,
+
+    /// Outcomes of the notification attempts.
+    pub outcomes: Vec<Outcome>,
+}
+
+/// Appends an entry to the history file, then drops the oldest entries if
+/// there are more than `max_entries`.
+pub fn append(path: &Path, entry: &Entry, max_entries: usize) -> io::Result<()> {
+    let line = serde_json::to_string(entry)?;
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{line}")?;
+    drop(file);
+
+    let contents = fs::read_to_string(path)?;
+    let lines: Vec<&str> = contents.lines().collect();
+
+    if lines.len() > max_entries {
+        let mut kept = lines[lines.len() - max_entries..].join("\n");
+        kept.push('\n');
+
+        file_config::write_atomic(path, kept)?;
+    }
+
+    Ok(())
+}
+
+/// Renames the history file to a segment named after it and the current
+/// time, e.g. `history.jsonl.20260101-120000`, if it holds `max_entries` or
+/// more entries. Returns the path of the segment, if the file was rotated.
+pub fn rotate(path: &Path, max_entries: usize) -> io::Result<Option<PathBuf>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    if contents.lines().count() < max_entries {
+        return Ok(None);
+    }
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(chrono::Local::now().format(".%Y%m%d-%H%M%S").to_string());
+    let segment = path.with_file_name(name);
+
+    fs::rename(path, &segment)?;
+    Ok(Some(segment))
+}
+
+/// Gzips the passed rotated segment into a file of the same name with `.gz`
+/// appended, and removes the segment.
+pub fn compress(segment: &Path) -> io::Result<PathBuf> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&fs::read(segment)?)?;
+
+    let mut name = segment.file_name().unwrap_or_default().to_os_string();
+    name.push(".gz");
+    let compressed = segment.with_file_name(name);
+
+    file_config::write_atomic(&compressed, encoder.finish()?)?;
+    fs::remove_file(segment)?;
+    Ok(compressed)
+}
+
+/// Compresses the passed rotated segment on a thread of its own, reporting
+/// any failure. A segment left uncompressed, such as by the monitor exiting
+/// midway, stays on disk as it is.
+pub fn compress_in_background(segment: PathBuf) -> JoinHandle<()> {
+    thread::spawn(move || {
+        if let Err(e) = compress(&segment) {
+            eprintln!("[!] Failed to compress {}: {e}", segment.display());
+        }
+    })
+}
+
+/// Reads the last `count` entries from the history file, oldest first.
+///
+/// Lines that cannot be parsed are skipped.
+pub fn read_last(path: &Path, count: usize) -> io::Result<Vec<Entry>> {
+    let contents = fs::read_to_string(path)?;
+
+    let entries: Vec<Entry> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    let skip = entries.len().saturating_sub(count);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+/// Prints the passed entries as a table.
+pub fn print(entries: &[Entry], duration_format: DurationFormat) {
+    println!(
+        "{:<20} {:<9} {:<16} {:<10} Notifications",
+        "Time", "Event", "Pin", "Held"
+    );
+
+    for entry in entries {
+        println!(
+            "{:<20} {:<9} {:<16} {:<10} {}",
+            entry.timestamp,
+            entry.event,
+            format!("{} ({})", entry.pin_name, entry.pin_number),
+            format_duration(
+                std::time::Duration::from_secs(entry.since_secs),
+                duration_format
+            ),
+            format_outcomes(&entry.outcomes)
+        );
+    }
+}
+
+/// Formats the passed entries one per line, for appending to a message.
+pub fn format_tail<'a>(
+    entries: impl IntoIterator<Item = &'a Entry>,
+    duration_format: DurationFormat,
+) -> String {
+    entries
+        .into_iter()
+        .map(|entry| {
+            format!(
+                "{} {} {} ({}) after {}: {}",
+                entry.timestamp,
+                entry.event,
+                entry.pin_name,
+                entry.pin_number,
+                format_duration(
+                    std::time::Duration::from_secs(entry.since_secs),
+                    duration_format
+                ),
+                format_outcomes(&entry.outcomes)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the outcomes of an entry's notification attempts as a list.
+fn format_outcomes(outcomes: &[Outcome]) -> String {
+    outcomes
+        .iter()
+        .map(|outcome| {
+            let receipt = outcome.receipt.as_ref().map_or(String::new(), |receipt| {
+                format!(" [{}]", format_receipt(receipt))
+            });
+
+            match &outcome.error {
+                None => format!("{}: ok{receipt}", outcome.notifier),
+                Some(e) => format!("{}: failed ({e}){receipt}", outcome.notifier),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats a receipt as its target, status, latency and number of attempts.
+fn format_receipt(receipt: &Receipt) -> String {
+    let mut parts = vec![receipt.target.clone()];
+
+    if let Some(status) = receipt.status {
+        parts.push(format!("HTTP {status}"));
+    }
+
+    parts.push(format!("{}ms", receipt.latency_ms));
+
+    if receipt.attempts > 1 {
+        parts.push(format!("attempt {}", receipt.attempts));
+    }
+
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(i: u64) -> Entry {
+        Entry {
+            timestamp: format!("2026-01-01 00:00:{i:02}"),
+            event: "alarm".to_string(),
+            pin_number: 24,
+            pin_name: "boiler".to_string(),
+            since_secs: i,
+            outcomes: vec![
+                Outcome {
+                    notifier: "slack[0]".to_string(),
+                    error: None,
+                    receipt: Some(Receipt {
+                        target: "https://hooks.slack.com/…".to_string(),
+                        status: Some(200),
+                        latency_ms: 120,
+                        attempts: 1,
+                    }),
+                },
+                Outcome {
+                    notifier: "batsign[0]".to_string(),
+                    error: Some("HTTP 500".to_string()),
+                    receipt: Some(Receipt {
+                        target: "https://batsign.me/…".to_string(),
+                        status: Some(500),
+                        latency_ms: 80,
+                        attempts: 2,
+                    }),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_append_rotates_and_read_last() {
+        let path = std::env::temp_dir().join(format!(
+            "pellx_monitor_history_test_{}.jsonl",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        for i in 0..5 {
+            append(&path, &entry(i), 3).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        assert_eq!(
+            read_last(&path, 10).unwrap(),
+            vec![entry(2), entry(3), entry(4)]
+        );
+        assert_eq!(read_last(&path, 1).unwrap(), vec![entry(4)]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_and_compress() {
+        let dir = std::env::temp_dir().join(format!(
+            "pellx_monitor_history_rotate_test_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+
+        assert_eq!(rotate(&path, 3).unwrap(), None);
+
+        append(&path, &entry(0), 3).unwrap();
+        append(&path, &entry(1), 3).unwrap();
+        assert_eq!(rotate(&path, 3).unwrap(), None);
+
+        // Once full, the file is rotated out and a new one started.
+        append(&path, &entry(2), 3).unwrap();
+        let segment = rotate(&path, 3).unwrap().unwrap();
+        assert!(!path.exists());
+        append(&path, &entry(3), 3).unwrap();
+        assert_eq!(read_last(&path, 10).unwrap(), vec![entry(3)]);
+
+        let contents = fs::read_to_string(&segment).unwrap();
+        compress_in_background(segment.clone()).join().unwrap();
+        assert!(!segment.exists());
+
+        let mut gz = segment.into_os_string();
+        gz.push(".gz");
+        let mut decompressed = String::new();
+        io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(fs::File::open(gz).unwrap()),
+            &mut decompressed,
+        )
+        .unwrap();
+        assert_eq!(decompressed, contents);
+        assert_eq!(decompressed.lines().count(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_receipts_are_nested_and_optional() {
+        let line = serde_json::to_string(&entry(1).outcomes[0]).unwrap();
+        assert_eq!(
+            line,
+            r#"{"notifier":"slack[0]","receipt":{"target":"https://hooks.slack.com/…","status":200,"latency_ms":120,"attempts":1}}"#
+        );
+
+        // Entries written before receipts were recorded
+        let outcome: Outcome = serde_json::from_str(r#"{"notifier":"slack[0]"}"#).unwrap();
+        assert_eq!(outcome.receipt, None);
+    }
+
+    #[test]
+    fn test_format_tail() {
+        assert_eq!(
+            format_tail(&[entry(1), entry(2)], DurationFormat::Compact),
+            "2026-01-01 00:00:01 alarm boiler (24) after 1s: \
+            slack[0]: ok [https://hooks.slack.com/…, HTTP 200, 120ms], \
+            batsign[0]: failed (HTTP 500) [https://batsign.me/…, HTTP 500, 80ms, attempt 2]\n\
+            2026-01-01 00:00:02 alarm boiler (24) after 2s: \
+            slack[0]: ok [https://hooks.slack.com/…, HTTP 200, 120ms], \
+            batsign[0]: failed (HTTP 500) [https://batsign.me/…, HTTP 500, 80ms, attempt 2]"
+        );
+        assert_eq!(format_tail(&[], DurationFormat::Compact), "");
+    }
+}
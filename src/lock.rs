@@ -0,0 +1,107 @@
+//! PID lockfile, keeping a second instance from monitoring the same pin.
+//!
+//! The lockfile holds the process ID of the running instance and is removed
+//! when the `PidLock` is dropped. A lockfile left behind by an instance that
+//! crashed or was killed is detected as stale, by checking whether a process
+//! with the recorded ID still exists, and is taken over.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Reasons a lockfile could not be acquired.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another running process holds the lock.
+    Held(u32),
+
+    /// The lockfile could not be read or written.
+    Io(io::Error),
+}
+
+/// A held PID lockfile, removed again on drop.
+#[derive(Debug)]
+pub struct PidLock {
+    /// Path to the lockfile.
+    path: PathBuf,
+}
+
+impl PidLock {
+    /// Acquires the lockfile at the passed path, writing our process ID to it.
+    ///
+    /// If the file already exists and names a process that is still running,
+    /// `LockError::Held` is returned with its process ID. If the process is
+    /// gone, the stale lockfile is replaced.
+    pub fn acquire(path: &Path) -> Result<Self, LockError> {
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    write!(file, "{}", process::id()).map_err(LockError::Io)?;
+
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let contents = fs::read_to_string(path).map_err(LockError::Io)?;
+
+                    if let Ok(pid) = contents.trim().parse::<u32>()
+                        && is_running(pid)
+                    {
+                        return Err(LockError::Held(pid));
+                    }
+
+                    fs::remove_file(path).map_err(LockError::Io)?;
+                }
+                Err(e) => return Err(LockError::Io(e)),
+            }
+        }
+
+        // Someone else created the file between our removing it and retrying.
+        Err(LockError::Io(io::Error::from(io::ErrorKind::AlreadyExists)))
+    }
+}
+
+impl Drop for PidLock {
+    /// Removes the lockfile.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Returns whether a process with the passed ID is currently running.
+fn is_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_held_and_stale() {
+        let path =
+            std::env::temp_dir().join(format!("pellx_monitor_lock_test_{}.pid", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let lock = PidLock::acquire(&path).unwrap();
+        assert!(matches!(
+            PidLock::acquire(&path),
+            Err(LockError::Held(pid)) if pid == process::id()
+        ));
+
+        drop(lock);
+        assert!(!path.exists());
+
+        // A lockfile naming a process that doesn't exist is stale.
+        fs::write(&path, "999999999").unwrap();
+        let lock = PidLock::acquire(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            process::id().to_string()
+        );
+
+        drop(lock);
+    }
+}
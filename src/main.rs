@@ -14,20 +14,44 @@
 //! some thinsg must be set in file.
 
 mod backend;
+mod benchmark;
 mod cli;
+mod completions;
+mod dedupe;
 mod defaults;
+mod duration;
+mod explain;
 mod file_config;
+mod gpio;
+mod history;
+mod lock;
+mod monitor;
 mod notify;
+mod replay;
+mod sanity;
 mod settings;
+mod setup;
+mod signals;
+mod status;
+mod systemd;
+mod template;
+mod watch;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+#[cfg(feature = "notifications")]
 use reqwest::blocking::Client;
-use rppal::gpio::{Gpio, InputPin, Level};
-use std::sync::Arc;
-use std::time::Instant;
-use std::{fs, process, thread};
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+use rppal::gpio::Gpio;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fs, io, process, thread};
 
-use crate::settings::Settings;
+use crate::duration::format_duration;
+use crate::gpio::Level;
+use crate::settings::{BaselineLevel, InputSource, Settings};
 
 /// Prints the program banner with version information.
 ///
@@ -44,257 +68,2356 @@ fn print_banner() {
 
 /// Program entrypoint.
 fn main() -> process::ExitCode {
-    if !cfg!(target_os = "linux") {
-        eprintln!("[!] This program can only be run on Linux.");
-        return process::ExitCode::from(defaults::exit_codes::WRONG_PLATFORM);
+    let cli = cli::Cli::parse();
+
+    // Before the banner, so that the script can be redirected as it is.
+    if let Some(shell) = cli.generate_completions {
+        print!("{}", completions::generate(shell, &mut cli::Cli::command()));
+        return process::ExitCode::SUCCESS;
+    }
+
+    // Not with --config-check either, so that its output can be redirected.
+    if !cli.quiet && !cli.config_check {
+        print_banner();
+        println!();
+    }
+
+    if cli.version {
+        // This is the only way to get a neat --version output.
+        // The banner with version is already printed just prior to this unless --quiet was passed,
+        // so we can just exit successfully here after echoing the licenses.
+        println!(
+            "This project is dual-licensed under the MIT License and the Apache License (Version 2.0)."
+        );
+        return process::ExitCode::SUCCESS;
+    }
+
+    // Apart from the full settings, so that a URL can be checked before
+    // there is any configuration to speak of.
+    #[cfg(feature = "notifications")]
+    if let Some(url) = &cli.check_url {
+        return match build_http_client(&check_url_settings(&cli)) {
+            Ok(client) => check_url(&client, url),
+            Err(e) => {
+                eprintln!("[!] Failed to initialize HTTP client: {e}");
+                process::ExitCode::from(defaults::exit_codes::FAILED_TO_INITIALISE_HTTP_CLIENT)
+            }
+        };
+    }
+
+    let settings = match init_settings(&cli) {
+        Ok(s) => s,
+        Err(code) => return code,
+    };
+
+    #[cfg(feature = "notifications")]
+    let client = match build_http_client(&settings) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("[!] Failed to initialize HTTP client: {e}");
+            return process::ExitCode::from(defaults::exit_codes::FAILED_TO_INITIALISE_HTTP_CLIENT);
+        }
+    };
+
+    if cli.test {
+        #[cfg(feature = "notifications")]
+        return send_test_notifications(&settings, Arc::new(client));
+
+        #[cfg(not(feature = "notifications"))]
+        return send_test_notifications(&settings);
+    }
+
+    if cli.show {
+        settings.print();
+        return process::ExitCode::SUCCESS;
+    }
+
+    if let Some(count) = cli.history {
+        return print_history(&settings, count);
+    }
+
+    if let Some(request) = &cli.status {
+        return query_status(&settings, request);
+    }
+
+    // Replaying and explaining run readings through the monitor logic, which
+    // is meaningless with an invalid M-of-N even though it can't fail.
+    if (cli.replay.is_some() || cli.explain.is_some())
+        && let Some(error) = settings.gpio.invalid_m_of_n()
+    {
+        eprintln!("[!] {error}");
+        return process::ExitCode::from(defaults::exit_codes::CONFIGURATION_ERROR);
+    }
+
+    if let Some(path) = &cli.replay {
+        return replay_history(&settings, path);
+    }
+
+    if let Some(path) = &cli.explain {
+        println!("{}", explain::describe(&settings));
+
+        let Some(path) = path else {
+            return process::ExitCode::SUCCESS;
+        };
+
+        println!();
+
+        let samples = match gpio::read_samples(path) {
+            Ok(samples) => samples,
+            Err(e) => {
+                eprintln!("[!] Failed to read readings from {path}: {e}");
+                return process::ExitCode::from(defaults::exit_codes::FAILED_TO_READ_READINGS_FILE);
+            }
+        };
+
+        explain::explain(&settings, &samples);
+        return process::ExitCode::SUCCESS;
+    }
+
+    for warning in settings.warnings() {
+        eprintln!("[!] {warning}");
+    }
+
+    if let Err(vec) = settings.sanity_check() {
+        eprintln!("[!] Configuration has errors:");
+
+        for error in vec {
+            eprintln!("  * {error}");
+        }
+
+        if settings.dry_run && settings.gpio.invalid_m_of_n().is_none() {
+            println!("[!] Continuing anyway because --dry-run is set.");
+            println!();
+        } else {
+            if settings.dry_run {
+                eprintln!("[!] Not continuing with an invalid M-of-N, even with --dry-run.");
+            }
+
+            return process::ExitCode::from(defaults::exit_codes::CONFIGURATION_ERROR);
+        }
+    }
+
+    if !settings.quiet() {
+        settings.print();
+        println!();
+    }
+
+    // Only lock when reading the actual pin; simulated runs can't fight over it.
+    let _lock = match &cli.simulate {
+        _ if cli.no_lock || cli.no_config || cli.fake_level.is_some() => None,
+        Some(_) => None,
+        None => match acquire_lock(&settings) {
+            Ok(lock) => lock,
+            Err(code) => return code,
+        },
+    };
+
+    #[cfg(feature = "notifications")]
+    let client = Arc::new(client);
+
+    let pin = match open_reader(&cli, &settings) {
+        Ok(pin) => pin,
+        Err(e) => {
+            if settings.notifications.notify_on_startup_failure && cli.simulate.is_none() {
+                #[cfg(feature = "notifications")]
+                notify_startup_failure(&settings, Arc::clone(&client), &e.reason);
+
+                #[cfg(not(feature = "notifications"))]
+                notify_startup_failure(&settings, &e.reason);
+            }
+
+            return e.code;
+        }
+    };
+
+    if let Some(count) = cli.benchmark {
+        return run_benchmark(pin, &settings, count);
+    }
+
+    signals::install_handlers();
+
+    if !wait_startup_delay(&settings) {
+        if !settings.quiet() {
+            println!("Shutting down.");
+        }
+        return process::ExitCode::SUCCESS;
+    }
+
+    #[cfg(feature = "notifications")]
+    if let Some(code) = wait_batsign_reachable(&settings) {
+        return code;
+    }
+
+    let pin = apply_baseline(pin, &settings);
+
+    #[cfg(feature = "notifications")]
+    let notifiers = build_notifiers(&settings, Arc::clone(&client));
+
+    #[cfg(not(feature = "notifications"))]
+    let notifiers = build_notifiers(&settings);
+
+    if notifiers.is_empty() && !settings.dry_run {
+        eprintln!("[!] No notifiers are configured.");
+        return process::ExitCode::from(defaults::exit_codes::NO_NOTIFIERS_CONFIGURED);
+    }
+
+    let started = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let status: status::SharedStatus = Arc::new(Mutex::new(status::Status::new(
+        status::Snapshot {
+            pin_number: settings.gpio.pin_number,
+            pin_name: settings.pin_name(settings.gpio.pin_number),
+            started: started.clone(),
+            since: started,
+            ..status::Snapshot::default()
+        },
+        settings.status.history_size,
+    )));
+
+    let _status_socket = if settings.status.enabled {
+        match status::serve(&settings.paths.status_socket, Arc::clone(&status)) {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                eprintln!(
+                    "[!] Failed to listen on status socket {}: {e}",
+                    settings.paths.status_socket.display()
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(addr) = cli.status_http {
+        if !addr.ip().is_loopback() {
+            eprintln!(
+                "[!] Serving the unauthenticated status endpoint on {addr}, \
+                which is not a loopback address."
+            );
+        }
+
+        match status::serve_http(addr, Arc::clone(&status)) {
+            Ok(addr) => {
+                if !settings.quiet() {
+                    println!("Serving status at http://{addr}/status.");
+                }
+            }
+            Err(e) => eprintln!("[!] Failed to serve status over HTTP on {addr}: {e}"),
+        }
+    }
+
+    if let Err(e) = systemd::notify_ready() {
+        eprintln!("[!] Failed to notify systemd of readiness: {e}");
+    }
+
+    let reloader = Reloader {
+        watcher: (cli.watch_config || settings.auto_reload).then(|| {
+            watch::ConfigWatcher::new(settings.watched_paths(), defaults::watch::DEBOUNCE)
+        }),
+        cli: cli.clone(),
+        #[cfg(feature = "notifications")]
+        client,
+    };
+
+    run_loop(pin, notifiers, settings, status, reloader)
+}
+
+/// Reloads the configuration and templates on request, and when they change
+/// on disk with `--watch-config` or `auto_reload`.
+struct Reloader {
+    /// The command-line arguments, applied on top of the reloaded configuration.
+    cli: cli::Cli,
+
+    /// Watches the configuration file and the templates for changes, if
+    /// running with `--watch-config` or `auto_reload`.
+    watcher: Option<watch::ConfigWatcher>,
+
+    /// The HTTP client shared by the notifiers.
+    #[cfg(feature = "notifications")]
+    client: Arc<Client>,
+}
+
+impl Reloader {
+    /// Reloads the configuration if `force` is set or it has changed on disk,
+    /// returning the new settings and notifiers built from them if they are
+    /// valid.
+    ///
+    /// Invalid changes are reported and otherwise ignored, leaving the current
+    /// settings in place. Settings of the pin itself take effect on restart.
+    fn poll(
+        &mut self,
+        settings: &Settings,
+        now: Instant,
+        force: bool,
+    ) -> Option<(Settings, Vec<Box<dyn notify::Notifier>>)> {
+        let changed = self.watcher.as_mut().is_some_and(|w| w.poll(now));
+
+        if !force && !changed {
+            return None;
+        }
+
+        if !settings.quiet() {
+            if force {
+                println!("Reload requested; reloading the configuration.");
+            } else {
+                println!("Configuration changed on disk; reloading.");
+            }
+        }
+
+        let Ok(mut new_settings) = init_settings(&self.cli) else {
+            eprintln!("[!] Keeping the current configuration.");
+            return None;
+        };
+
+        for warning in new_settings.warnings() {
+            eprintln!("[!] {warning}");
+        }
+
+        if let Err(vec) = new_settings.sanity_check() {
+            eprintln!("[!] Reloaded configuration has errors:");
+
+            for error in vec {
+                eprintln!("  * {error}");
+            }
+
+            eprintln!("[!] Keeping the current configuration.");
+            return None;
+        }
+
+        let gpio_differed = new_settings.gpio.retain_fixed(&settings.gpio);
+        let adc_differed = new_settings.adc.retain_fixed(&settings.adc);
+
+        if gpio_differed || adc_differed {
+            eprintln!(
+                "[!] Changes to the pin number, pull, baseline level, qualify mode, \
+                input source and ADC settings take effect on restart."
+            );
+        }
+
+        #[cfg(feature = "notifications")]
+        let notifiers = build_notifiers(&new_settings, Arc::clone(&self.client));
+
+        #[cfg(not(feature = "notifications"))]
+        let notifiers = build_notifiers(&new_settings);
+
+        if notifiers.is_empty() && !new_settings.dry_run {
+            eprintln!("[!] No notifiers are configured; keeping the current configuration.");
+            return None;
+        }
+
+        // The templates may have moved.
+        if self.watcher.is_some() {
+            self.watcher = Some(watch::ConfigWatcher::new(
+                new_settings.watched_paths(),
+                defaults::watch::DEBOUNCE,
+            ));
+        }
+
+        if !new_settings.quiet() {
+            println!("Configuration reloaded.");
+        }
+
+        Some((new_settings, notifiers))
+    }
+}
+
+/// Waits out the configured startup delay and jitter before the first poll.
+///
+/// Returns `false` if a shutdown was requested while waiting.
+fn wait_startup_delay(settings: &Settings) -> bool {
+    let random = RandomState::new().build_hasher().finish();
+    let wait = settings.gpio.startup_wait(random);
+
+    if wait == Duration::ZERO {
+        return true;
+    }
+
+    if !settings.quiet() {
+        let jitter = wait.saturating_sub(settings.gpio.startup_delay);
+
+        if settings.gpio.startup_jitter.is_zero() {
+            println!(
+                "Waiting {} before the first poll.",
+                format_duration(wait, settings.duration_format)
+            );
+        } else {
+            println!(
+                "Waiting {} ({} delay plus {} of jitter) before the first poll.",
+                format_duration(wait, settings.duration_format),
+                format_duration(settings.gpio.startup_delay, settings.duration_format),
+                format_duration(jitter, settings.duration_format)
+            );
+        }
+    }
+
+    let until = Instant::now() + wait;
+
+    while let Some(remaining) = until.checked_duration_since(Instant::now()) {
+        if signals::shutdown_requested() {
+            return false;
+        }
+
+        thread::sleep(remaining.min(defaults::gpio::STARTUP_DELAY_STEP));
+    }
+
+    !signals::shutdown_requested()
+}
+
+/// Checks that at least one of the Batsign hosts is reachable, if required by
+/// `require_reachable_on_start`, retrying for up to `reachable_wait`.
+///
+/// Returns the code to exit with if none became reachable, or if a shutdown
+/// was requested while waiting. Hosts that aren't reachable are reported
+/// either way. With `--dry-run` the monitor carries on regardless.
+#[cfg(feature = "notifications")]
+fn wait_batsign_reachable(settings: &Settings) -> Option<process::ExitCode> {
+    let batsign = &settings.batsign;
+
+    if !batsign.enabled
+        || !batsign.require_reachable_on_start
+        || batsign.urls.is_empty()
+        || !settings.routes_to(&batsign.severities)
+    {
+        return None;
+    }
+
+    let hosts = backend::batsign::hosts_of(&batsign.urls);
+    let give_up_at = Instant::now() + batsign.reachable_wait;
+
+    loop {
+        let mut reachable = 0;
+
+        for host in &hosts {
+            match backend::batsign::check_reachable(
+                host,
+                defaults::batsign::REACHABLE_CONNECT_TIMEOUT,
+            ) {
+                Ok(()) => reachable += 1,
+                Err(e) => eprintln!("[!] Batsign host {host} is unreachable: {e}"),
+            }
+        }
+
+        if reachable > 0 {
+            if !settings.quiet() {
+                println!("{reachable} of {} Batsign host(s) reachable.", hosts.len());
+            }
+            return None;
+        }
+
+        if Instant::now() >= give_up_at {
+            eprintln!(
+                "[!] None of the Batsign hosts became reachable within {}.",
+                format_duration(batsign.reachable_wait, settings.duration_format)
+            );
+
+            if settings.dry_run {
+                println!("[!] Continuing anyway because --dry-run is set.");
+                return None;
+            }
+
+            return Some(process::ExitCode::from(
+                defaults::exit_codes::BATSIGN_UNREACHABLE,
+            ));
+        }
+
+        if !settings.quiet() {
+            println!(
+                "Retrying in {}.",
+                format_duration(
+                    defaults::batsign::REACHABLE_RETRY_INTERVAL,
+                    settings.duration_format
+                )
+            );
+        }
+
+        let until = Instant::now() + defaults::batsign::REACHABLE_RETRY_INTERVAL;
+
+        while let Some(remaining) = until.checked_duration_since(Instant::now()) {
+            if signals::shutdown_requested() {
+                if !settings.quiet() {
+                    println!("Shutting down.");
+                }
+                return Some(process::ExitCode::SUCCESS);
+            }
+
+            thread::sleep(remaining.min(defaults::gpio::STARTUP_DELAY_STEP));
+        }
+    }
+}
+
+/// Runs the read and qualify path of the monitor loop `count` times without
+/// sleeping, and prints how long it took.
+///
+/// No notifications are sent.
+fn run_benchmark(
+    mut pin: Box<dyn gpio::PinReader>,
+    settings: &Settings,
+    count: u64,
+) -> process::ExitCode {
+    if !settings.quiet() {
+        println!("Running {count} iterations of the poll loop without sleeping.");
+        println!();
+    }
+
+    benchmark::run(pin.as_mut(), settings, count).print(settings);
+    process::ExitCode::SUCCESS
+}
+
+/// A failure to open the pin, with the exit code to return.
+struct PinError {
+    /// Exit code to return.
+    code: process::ExitCode,
+
+    /// What went wrong, as printed.
+    reason: String,
+}
+
+impl PinError {
+    /// Prints the passed reason and returns it as an error with the passed exit code.
+    fn new(code: u8, reason: String) -> Self {
+        eprintln!("[!] {reason}");
+
+        Self {
+            code: process::ExitCode::from(code),
+            reason,
+        }
+    }
+}
+
+/// Opens the configured GPIO pin as an input with the configured pull resistor.
+///
+/// On failure, the error is printed and passed back. Being denied access to
+/// the GPIO pins is told apart from other failures, with a hint on how to fix it.
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+fn open_pin(settings: &Settings) -> Result<Box<dyn gpio::PinReader>, PinError> {
+    use rppal::gpio::Error;
+
+    let gpio = match Gpio::new() {
+        Ok(g) => g,
+        Err(Error::PermissionDenied(path)) => {
+            return Err(PinError::new(
+                defaults::exit_codes::FAILED_TO_INITIALISE_GPIO,
+                format!(
+                    "Permission denied accessing {path}. {}",
+                    defaults::gpio::PERMISSION_HINT
+                ),
+            ));
+        }
+        Err(e) => {
+            return Err(PinError::new(
+                defaults::exit_codes::FAILED_TO_INITIALISE_GPIO,
+                format!("Failed to initialize GPIO: {e}"),
+            ));
+        }
+    };
+
+    match gpio.get(settings.gpio.pin_number) {
+        Ok(p) => {
+            use settings::PullMode;
+
+            let input = match settings.gpio.pull {
+                PullMode::Up => p.into_input_pullup(),
+                PullMode::Down => p.into_input_pulldown(),
+                PullMode::None => p.into_input(),
+            };
+
+            Ok(Box::new(gpio::RppalPin::new(input)))
+        }
+        Err(Error::PinUsed(_)) => Err(PinError::new(
+            defaults::exit_codes::FAILED_TO_SET_PIN_MODE,
+            format!(
+                "GPIO{} is already in use. Is another instance of {} running?",
+                settings.gpio.pin_number,
+                defaults::PROGRAM_NAME
+            ),
+        )),
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::ResourceBusy => Err(PinError::new(
+            defaults::exit_codes::FAILED_TO_SET_PIN_MODE,
+            format!(
+                "GPIO{} is busy ({e}). Is another instance of {} \
+                or another program using the pin running?",
+                settings.gpio.pin_number,
+                defaults::PROGRAM_NAME
+            ),
+        )),
+        Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Err(PinError::new(
+                defaults::exit_codes::FAILED_TO_SET_PIN_MODE,
+                format!(
+                    "Permission denied setting mode of GPIO{} ({e}). {}",
+                    settings.gpio.pin_number,
+                    defaults::gpio::PERMISSION_HINT
+                ),
+            ))
+        }
+        Err(e) => Err(PinError::new(
+            defaults::exit_codes::FAILED_TO_SET_PIN_MODE,
+            format!(
+                "Failed to set mode of GPIO{}: {e}",
+                settings.gpio.pin_number
+            ),
+        )),
+    }
+}
+
+/// Acquires the lockfile in the configuration directory, so that a second
+/// instance refuses to start instead of fighting over the same pin.
+///
+/// If the lockfile can't be written, a warning is printed and the program
+/// continues without it.
+fn acquire_lock(settings: &Settings) -> Result<Option<lock::PidLock>, process::ExitCode> {
+    match lock::PidLock::acquire(&settings.paths.pid_file) {
+        Ok(lock) => Ok(Some(lock)),
+        Err(lock::LockError::Held(pid)) => {
+            eprintln!(
+                "[!] Another instance of {} is already running (PID {pid}). \
+                If it isn't, remove the stale lockfile {}.",
+                defaults::PROGRAM_NAME,
+                settings.paths.pid_file.display()
+            );
+            Err(process::ExitCode::from(
+                defaults::exit_codes::ALREADY_RUNNING,
+            ))
+        }
+        Err(lock::LockError::Io(e)) => {
+            eprintln!(
+                "[!] Failed to write lockfile {}: {e}. Continuing without it.",
+                settings.paths.pid_file.display()
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Opens the configured GPIO pin through the sysfs interface under the passed
+/// directory, exporting it if needed.
+///
+/// On failure, the error is printed and the exit code to return is passed back.
+#[cfg(feature = "sysfs-gpio")]
+fn open_sysfs_pin(
+    settings: &Settings,
+    base: &std::path::Path,
+) -> Result<Box<dyn gpio::PinReader>, PinError> {
+    match gpio::SysfsPin::open(base, settings.gpio.pin_number) {
+        Ok(pin) => Ok(Box::new(pin)),
+        Err(e) => Err(PinError::new(
+            defaults::exit_codes::FAILED_TO_SET_PIN_MODE,
+            format!(
+                "Failed to open GPIO{} through sysfs: {e}",
+                settings.gpio.pin_number
+            ),
+        )),
+    }
+}
+
+/// Fallback for builds without real GPIO support, which can only be run with
+/// simulated pin readings.
+#[cfg(not(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio"))))]
+fn open_pin(_settings: &Settings) -> Result<Box<dyn gpio::PinReader>, PinError> {
+    Err(PinError::new(
+        defaults::exit_codes::WRONG_PLATFORM,
+        String::from(
+            "This build has no GPIO support. Pass `--simulate` to replay pin readings from a file.",
+        ),
+    ))
+}
+
+/// Opens the configured channel of the MCP3008 ADC, to be compared against
+/// the configured threshold.
+///
+/// On failure, the error is printed and passed back.
+#[cfg(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio")))]
+fn open_adc(settings: &Settings) -> Result<Box<dyn gpio::PinReader>, PinError> {
+    let adc = &settings.adc;
+    let threshold = gpio::Threshold::new(adc.threshold, adc.hysteresis);
+
+    match gpio::Mcp3008Pin::open(adc.spi_bus, adc.chip_select, adc.channel, threshold) {
+        Ok(pin) => Ok(Box::new(pin)),
+        Err(rppal::spi::Error::Io(e)) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Err(PinError::new(
+                defaults::exit_codes::FAILED_TO_OPEN_ADC,
+                format!(
+                    "Permission denied opening SPI bus {} ({e}). {}",
+                    adc.spi_bus,
+                    defaults::adc::PERMISSION_HINT
+                ),
+            ))
+        }
+        Err(e) => Err(PinError::new(
+            defaults::exit_codes::FAILED_TO_OPEN_ADC,
+            format!(
+                "Failed to open ADC on SPI bus {}, chip select {}: {e}",
+                adc.spi_bus, adc.chip_select
+            ),
+        )),
+    }
+}
+
+/// Fallback for builds without real GPIO support, which can't read an ADC
+/// either. Not reached in practice, as the sanity check refuses an ADC input
+/// source in such builds.
+#[cfg(not(all(target_os = "linux", feature = "real-gpio", not(feature = "mock-gpio"))))]
+fn open_adc(_settings: &Settings) -> Result<Box<dyn gpio::PinReader>, PinError> {
+    Err(PinError::new(
+        defaults::exit_codes::WRONG_PLATFORM,
+        String::from("This build has no ADC support."),
+    ))
+}
+
+/// Opens the pin to read, which is either the configured GPIO pin, read
+/// through sysfs if `sysfs_base` is set, the configured ADC channel if
+/// `input_source` is `adc`, or, with `--simulate`, a replay of
+/// timed readings from a file. With `--fake-level`,
+/// it is a fake pin reading a fixed or toggling level, and the GPIO pin isn't
+/// touched at all.
+fn open_reader(cli: &cli::Cli, settings: &Settings) -> Result<Box<dyn gpio::PinReader>, PinError> {
+    if let Some(fake_level) = cli.fake_level {
+        return Ok(Box::new(match fake_level {
+            cli::FakeLevel::High => gpio::FakePin::steady(Level::High),
+            cli::FakeLevel::Low => gpio::FakePin::steady(Level::Low),
+            cli::FakeLevel::Toggle => gpio::FakePin::toggling(defaults::gpio::FAKE_TOGGLE_INTERVAL),
+        }));
+    }
+
+    match &cli.simulate {
+        Some(path) => match gpio::read_samples(path) {
+            Ok(samples) => Ok(Box::new(gpio::ScriptedPin::new(samples))),
+            Err(e) => Err(PinError::new(
+                defaults::exit_codes::FAILED_TO_READ_READINGS_FILE,
+                format!("Failed to read readings from {path}: {e}"),
+            )),
+        },
+        None if settings.gpio.input_source == InputSource::Adc => open_adc(settings),
+        None => match &settings.gpio.sysfs_base {
+            #[cfg(feature = "sysfs-gpio")]
+            Some(base) => open_sysfs_pin(settings, base),
+            _ => open_pin(settings),
+        },
+    }
+}
+
+/// Samples the pin for the calibration duration and sets the baseline level
+/// to the level it read most often, assuming that the pin was in its normal
+/// state throughout.
+///
+/// The result applies to this session, and is persisted if `--save` was passed.
+fn calibrate(cli: &cli::Cli, settings: &mut Settings) -> Result<(), process::ExitCode> {
+    let mut pin = open_reader(cli, settings).map_err(|e| e.code)?;
+    let pin_name = settings.pin_name(settings.gpio.pin_number);
+    let until = Instant::now() + settings.gpio.calibration_duration;
+    let mut calibration = gpio::Calibration::default();
+
+    if !settings.quiet() {
+        println!(
+            "Calibrating: sampling {pin_name} for {}. \
+            Make sure the burner is in its normal state.",
+            format_duration(settings.gpio.calibration_duration, settings.duration_format)
+        );
+    }
+
+    loop {
+        calibration.record(&pin.read());
+
+        if Instant::now() + settings.gpio.poll_interval > until {
+            break;
+        }
+
+        thread::sleep(settings.gpio.poll_interval);
+    }
+
+    let Some(level) = calibration.baseline() else {
+        eprintln!(
+            "[!] Failed to read {pin_name} while calibrating ({} failed reads).",
+            calibration.errors
+        );
+        return Err(process::ExitCode::from(
+            defaults::exit_codes::CALIBRATION_FAILED,
+        ));
+    };
+
+    settings.gpio.baseline_level = match level {
+        Level::Low => BaselineLevel::Low,
+        Level::High => BaselineLevel::High,
+    };
+
+    if !settings.quiet() {
+        println!(
+            "{pin_name} read HIGH {} times and LOW {} times, with {} failed reads.",
+            calibration.highs, calibration.lows, calibration.errors
+        );
+        println!(
+            "Inferred configuration: baseline_level = \"{}\"",
+            settings.gpio.baseline_level
+        );
+
+        if !cli.save {
+            println!("Applying it for this session. Run with `--save` to persist it.");
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Wraps the pin so that it reads inverted if its baseline level is HIGH,
+/// leaving HIGH to mean an alarm for the rest of the program.
+///
+/// With an `auto` baseline the pin is read once here, and whatever it reads
+/// is taken as the normal level.
+fn apply_baseline(
+    mut pin: Box<dyn gpio::PinReader>,
+    settings: &Settings,
+) -> Box<dyn gpio::PinReader> {
+    let baseline = match settings.gpio.baseline_level {
+        BaselineLevel::Low => Level::Low,
+        BaselineLevel::High => Level::High,
+        BaselineLevel::Auto => {
+            let level = pin.read().unwrap_or_else(|e| {
+                eprintln!("[!] Failed to read the pin to establish its baseline: {e}");
+                eprintln!("Assuming it normally reads LOW.");
+                Level::Low
+            });
+
+            if !settings.quiet() {
+                println!(
+                    "Established baseline: {} normally reads {level}.",
+                    settings.pin_name(settings.gpio.pin_number)
+                );
+            }
+
+            level
+        }
+    };
+
+    match baseline {
+        Level::Low => pin,
+        Level::High => Box::new(gpio::InvertedPin::new(pin)),
+    }
+}
+
+/// Builds the HTTP client shared by all HTTP-based backends, applying the
+/// configured HTTP settings.
+///
+/// # Example
+/// ```
+/// let client = build_http_client(&settings)?;
+/// let notifiers = build_notifiers(&settings, Arc::new(client));
+/// ```
+#[cfg(feature = "notifications")]
+fn build_http_client(settings: &Settings) -> reqwest::Result<Client> {
+    http_client_builder(settings.http.timeout, settings.http.follow_redirects()).build()
+}
+
+/// Returns a builder of an HTTP client with the passed timeout, following
+/// redirects or not.
+#[cfg(feature = "notifications")]
+fn http_client_builder(
+    timeout: Duration,
+    follow_redirects: bool,
+) -> reqwest::blocking::ClientBuilder {
+    let builder = Client::builder().timeout(timeout);
+
+    if follow_redirects {
+        builder
+    } else {
+        builder.redirect(reqwest::redirect::Policy::none())
+    }
+}
+
+/// Returns the settings `--check-url` uses, of which only the `[http]` ones
+/// matter. Unlike `init_settings`, nothing needs to exist and nothing is
+/// fetched; a configuration file is applied if there is one to read, and
+/// the defaults are used otherwise.
+#[cfg(feature = "notifications")]
+fn check_url_settings(cli: &cli::Cli) -> Settings {
+    let mut settings = Settings::default();
+
+    if cli.no_config || settings.inherit_config_dir(&cli.config_dir).is_err() {
+        return settings;
+    }
+
+    settings.resolve_resource_paths();
+
+    if let Some(path) = &cli.config_file {
+        settings.paths.config_file = PathBuf::from(path);
+    }
+
+    if let Ok(config) = file_config::deserialize_config_file(&settings.paths.config_file) {
+        settings.apply_file(&config, cli.profile.as_deref());
+    }
+
+    settings
+}
+
+/// Probes a single URL for reachability, reporting the HTTP status and
+/// round-trip time.
+///
+/// A lightweight HEAD request is made first. If the server does not support
+/// HEAD (HTTP 405 or 501), the request is retried as a GET. No notification
+/// is sent.
+///
+/// # Example
+/// ```
+/// if let Some(url) = &cli.check_url {
+///     let client = build_http_client(&check_url_settings(&cli))?;
+///     return check_url(&client, url);
+/// }
+/// ```
+#[cfg(feature = "notifications")]
+fn check_url(client: &Client, url: &str) -> process::ExitCode {
+    println!("Checking {url} ...");
+
+    let mut result = probe_url(client.head(url));
+
+    if let Ok(status) = result
+        && (status == reqwest::StatusCode::METHOD_NOT_ALLOWED
+            || status == reqwest::StatusCode::NOT_IMPLEMENTED)
+    {
+        println!("HEAD not supported; falling back to GET ...");
+        result = probe_url(client.get(url));
+    }
+
+    match result {
+        Ok(status) if status.is_success() => process::ExitCode::SUCCESS,
+        _ => process::ExitCode::from(defaults::exit_codes::URL_CHECK_FAILED),
+    }
+}
+
+/// Sends a single probe request and reports its outcome and round-trip time,
+/// returning the HTTP status if a response was received.
+#[cfg(feature = "notifications")]
+fn probe_url(request: reqwest::blocking::RequestBuilder) -> Result<reqwest::StatusCode, ()> {
+    let start = Instant::now();
+    let result = request.send();
+    let latency = start.elapsed();
+
+    match result {
+        Ok(resp) if resp.status().is_success() => {
+            println!("HTTP {} in {}ms", resp.status(), latency.as_millis());
+            Ok(resp.status())
+        }
+        Ok(resp) => {
+            eprintln!("[!] HTTP {} in {}ms", resp.status(), latency.as_millis());
+            Ok(resp.status())
+        }
+        Err(e) => {
+            eprintln!("[!] Request failed after {}ms: {e}", latency.as_millis());
+            Err(())
+        }
+    }
+}
+
+/// Sends the alarm message once through all notifiers as a test, waiting for
+/// each to finish and reporting its outcome, for `--save --test`.
+///
+/// # Example
+/// ```
+/// if cli.test {
+///     return send_test_notifications(&settings, Arc::new(client));
+/// }
+/// ```
+fn send_test_notifications(
+    settings: &Settings,
+    #[cfg(feature = "notifications")] client: Arc<Client>,
+) -> process::ExitCode {
+    #[cfg(feature = "notifications")]
+    let mut notifiers = build_notifiers(settings, client);
+
+    #[cfg(not(feature = "notifications"))]
+    let mut notifiers = build_notifiers(settings);
+
+    println!();
+
+    if notifiers.is_empty() {
+        eprintln!("[!] No notifiers are configured; nothing to test.");
+        return process::ExitCode::from(defaults::exit_codes::NO_NOTIFIERS_CONFIGURED);
+    }
+
+    println!(
+        "Sending test notifications through {} notifier(s) ...",
+        notifiers.len()
+    );
+
+    let now = Instant::now();
+    let ctx = notify::Context::new(settings, Level::High, now, now);
+
+    let mut failures = 0;
+
+    for n in notifiers.iter_mut() {
+        match n.send_test(&ctx) {
+            notify::NotificationResult::Success => println!("{}: test success", n.name()),
+            notify::NotificationResult::Failure(message) => {
+                failures += 1;
+                println!("{}: test failure: {message}", n.name());
+            }
+            _ => {}
+        }
+    }
+
+    if failures > 0 {
+        eprintln!(
+            "[!] {failures} of {} test notification(s) failed.",
+            notifiers.len()
+        );
+        return process::ExitCode::from(defaults::exit_codes::TEST_NOTIFICATION_FAILED);
+    }
+
+    process::ExitCode::SUCCESS
+}
+
+/// Sends a notice through all notifiers that the pin couldn't be opened, with
+/// the passed reason, so that a broken deployment doesn't go unnoticed.
+///
+/// As the monitor exits right after, the notice is sent right away rather than
+/// through the send worker, and isn't retried.
+fn notify_startup_failure(
+    settings: &Settings,
+    #[cfg(feature = "notifications")] client: Arc<Client>,
+    reason: &str,
+) {
+    #[cfg(feature = "notifications")]
+    let mut notifiers = build_notifiers(settings, client);
+
+    #[cfg(not(feature = "notifications"))]
+    let mut notifiers = build_notifiers(settings);
+
+    let now = Instant::now();
+    let ctx = notify::Context {
+        config_summary: String::new(),
+        ..notify::Context::new(settings, Level::High, now, now)
+    };
+    let body = format!(
+        "{}\n{reason}",
+        defaults::notifications::STARTUP_FAILURE_BODY
+    );
+
+    for n in notifiers.iter_mut() {
+        match n.send_oneshot(
+            &ctx,
+            defaults::notifications::STARTUP_FAILURE_SUBJECT,
+            &body,
+        ) {
+            notify::NotificationResult::Success => {
+                println!("{}: startup failure notice sent", n.name());
+            }
+            notify::NotificationResult::Failure(message) => {
+                eprintln!(
+                    "[!] {}: failed to send startup failure notice: {message}",
+                    n.name()
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Initialises and returns a `Vec` of notifiers.
+///
+/// Notifiers are instances of `TwoLevelNotifier` with a backend of either
+/// `SlackBackend`, `BatsignBackend`, `WebhookBackend`, `CommandBackend` or
+/// `PipeBackend`, depending on the resolved settings.
+/// Their settings must have been read into the `Settings` struct before
+/// calling this function.
+///
+/// Without the `notifications` feature, only `CommandBackend` and
+/// `PipeBackend` are available and no HTTP client is passed.
+///
+/// # Example
+/// ```
+/// let settings = Settings::default();
+/// // ... apply config file and CLI overrides to settings ...
+/// let client = Arc::new(build_http_client(&settings)?);
+/// let notifiers: Vec<Box<dyn notify::Notifier>> = build_notifiers(&settings, client);
+/// ```
+fn build_notifiers(
+    settings: &Settings,
+    #[cfg(feature = "notifications")] client: Arc<Client>,
+) -> Vec<Box<dyn notify::Notifier>> {
+    let mut notifiers: Vec<Box<dyn notify::Notifier>> = Vec::new();
+
+    #[cfg(not(feature = "notifications"))]
+    if settings.slack.enabled || settings.batsign.enabled || settings.webhook.enabled {
+        eprintln!(
+            "[!] Slack, Batsign and webhook notifications are not available in this build; \
+            only commands will be run and piped to."
+        );
+    }
+
+    for (name, enabled, severities) in [
+        ("Slack", settings.slack.enabled, &settings.slack.severities),
+        (
+            "Batsign",
+            settings.batsign.enabled,
+            &settings.batsign.severities,
+        ),
+        (
+            "Webhook",
+            settings.webhook.enabled,
+            &settings.webhook.severities,
+        ),
+        (
+            "Command",
+            settings.command.enabled,
+            &settings.command.severities,
+        ),
+        ("Pipe", settings.pipe.enabled, &settings.pipe.severities),
+    ] {
+        if enabled && !settings.routes_to(severities) && !settings.quiet() {
+            println!(
+                "{name} doesn't accept {} alarms; not notifying through it.",
+                settings.gpio.severity
+            );
+        }
+    }
+
+    #[cfg(feature = "notifications")]
+    let http_options = backend::HttpOptions {
+        debug: settings.debug(),
+        max_error_body_bytes: settings.http.max_error_body_bytes,
+        success_statuses: settings.http.success_statuses.clone(),
+        retry_timeout_schedule: settings.http.retry_timeout_schedule.clone(),
+    };
+
+    #[cfg(feature = "notifications")]
+    if settings.slack.enabled && settings.routes_to(&settings.slack.severities) {
+        for (i, url) in settings.slack.urls.iter().enumerate() {
+            let n = notify::TwoLevelNotifier::new(
+                backend::slack::SlackBackend::new(
+                    i,
+                    Arc::clone(&client),
+                    url,
+                    http_options.clone(),
+                ),
+                Some(settings.slack.notification_interval),
+                settings.slack.retry_interval,
+                &settings.slack.alarm_message_template_body,
+                &settings.slack.restored_message_template_body,
+                settings.dry_run,
+            )
+            .with_max_message_bytes(settings.notifications.max_message_bytes)
+            .with_dedupe_window(settings.notifications.dedupe_window());
+
+            if settings.debug() {
+                println!("{}: initialized with URL {}", n.name(), url);
+            }
+
+            notifiers.push(Box::new(n));
+        }
+    }
+
+    #[cfg(feature = "notifications")]
+    if settings.batsign.enabled && settings.routes_to(&settings.batsign.severities) {
+        let mut batsigns: Vec<Box<dyn notify::Notifier>> = Vec::new();
+
+        for (i, url) in settings.batsign.urls.iter().enumerate() {
+            let mut batsign = backend::batsign::BatsignBackend::new(
+                i,
+                Arc::clone(&client),
+                url,
+                settings.batsign.request_style,
+                http_options.clone(),
+            )
+            .with_line_ending(settings.batsign.line_ending);
+
+            if settings.batsign.pin_dns {
+                let (timeout, follow_redirects) =
+                    (settings.http.timeout, settings.http.follow_redirects());
+
+                match backend::batsign::DnsPin::new(
+                    url,
+                    defaults::batsign::DNS_REFRESH_INTERVAL,
+                    Box::new(move || http_client_builder(timeout, follow_redirects)),
+                ) {
+                    Some(dns_pin) => batsign = batsign.with_dns_pin(dns_pin),
+                    None => println!(
+                        "Not pinning DNS of Batsign URL without a host name: {}",
+                        backend::redact_url(url)
+                    ),
+                }
+            }
+
+            if let Some(secret) = &settings.batsign.hmac_secret {
+                batsign = batsign.with_signer(backend::HmacSigner::new(
+                    secret,
+                    settings.batsign.hmac_algorithm,
+                    &settings.batsign.hmac_header,
+                ));
+            }
+
+            let n = notify::TwoLevelNotifier::new(
+                batsign,
+                Some(settings.batsign.notification_interval),
+                settings.batsign.retry_interval,
+                &settings.batsign.alarm_message_template_body,
+                &settings.batsign.restored_message_template_body,
+                settings.dry_run,
+            )
+            .with_max_message_bytes(settings.notifications.max_message_bytes)
+            .with_dedupe_window(settings.notifications.dedupe_window());
+
+            if settings.debug() {
+                println!("{}: initialized with URL {}", n.name(), url);
+            }
+
+            batsigns.push(Box::new(n));
+        }
+
+        match settings.batsign.mode {
+            crate::settings::BatsignMode::Failover if batsigns.len() > 1 => {
+                notifiers.push(Box::new(notify::FailoverNotifier::new(
+                    "batsign",
+                    batsigns,
+                    settings.batsign.failover_health_window,
+                )));
+            }
+            _ => notifiers.extend(batsigns),
+        }
+    }
+
+    #[cfg(feature = "notifications")]
+    if settings.webhook.enabled && settings.routes_to(&settings.webhook.severities) {
+        for (i, url) in settings.webhook.urls.iter().enumerate() {
+            let n = notify::TwoLevelNotifier::new(
+                backend::webhook::WebhookBackend::new(
+                    i,
+                    Arc::clone(&client),
+                    url,
+                    http_options.clone(),
+                )
+                .with_gzip(settings.webhook.gzip_min_bytes_for(url)),
+                Some(settings.webhook.notification_interval),
+                settings.webhook.retry_interval,
+                &settings.webhook.alarm_payload_template_body,
+                &settings.webhook.restored_payload_template_body,
+                settings.dry_run,
+            )
+            .with_dedupe_window(settings.notifications.dedupe_window());
+
+            if settings.debug() {
+                println!("{}: initialized with URL {}", n.name(), url);
+            }
+
+            notifiers.push(Box::new(n));
+        }
+    }
+
+    if settings.command.enabled && settings.routes_to(&settings.command.severities) {
+        let n = notify::TwoLevelNotifier::new(
+            backend::command::CommandBackend::new(0),
+            Some(settings.command.notification_interval),
+            settings.command.retry_interval,
+            &settings.command.alarm_command,
+            &settings.command.restored_command,
+            settings.dry_run,
+        )
+        .with_dedupe_window(settings.notifications.dedupe_window());
+
+        if settings.debug() {
+            println!("{}: initialized", n.name());
+        }
+
+        notifiers.push(Box::new(n));
+    }
+
+    if settings.pipe.enabled && settings.routes_to(&settings.pipe.severities) {
+        let n = notify::TwoLevelNotifier::new(
+            backend::pipe::PipeBackend::new(0, &settings.pipe.command),
+            Some(settings.pipe.notification_interval),
+            settings.pipe.retry_interval,
+            &settings.pipe.alarm_message,
+            &settings.pipe.restored_message,
+            settings.dry_run,
+        )
+        .with_max_message_bytes(settings.notifications.max_message_bytes)
+        .with_dedupe_window(settings.notifications.dedupe_window());
+
+        if settings.debug() {
+            println!("{}: initialized", n.name());
+        }
+
+        notifiers.push(Box::new(n));
+    }
+
+    notifiers
+}
+
+/// The main loop that monitors the GPIO pin and sends notifications
+/// based on the configured notifiers and settings.
+///
+/// The loop runs indefinitely unless `--run-for` was passed, in which case
+/// it exits cleanly with a summary after the given duration. It likewise
+/// exits cleanly on SIGINT or SIGTERM, once the current poll is done.
+/// The signals mapped in `[signals]` reload the configuration, send a test
+/// notification through all notifiers, or print the current state. The
+/// configuration is also reloaded whenever it changes on disk if running with
+/// `--watch-config` or `auto_reload`, keeping the state of the pin and of the notifications
+/// sent so far.
+///
+/// Notifications are sent on a worker thread, so that a slow endpoint doesn't
+/// delay the next read of the pin, and their outcomes logged once reported
+/// back. Sends still queued are waited for before exiting.
+///
+/// Notifiers must have been initialised before calling this function,
+/// and the pin must likewise have been opened, either as an actual GPIO pin
+/// or as a simulated one replaying readings from a file.
+///
+/// # Example
+/// ```
+/// let pin = open_pin(&settings)?;
+/// let notifiers = build_notifiers(&settings, client);
+/// run_loop(pin, notifiers, settings, status, reloader)
+/// ```
+fn run_loop(
+    mut pin: Box<dyn gpio::PinReader>,
+    notifiers: Vec<Box<dyn notify::Notifier>>,
+    mut settings: Settings,
+    status: status::SharedStatus,
+    mut reloader: Reloader,
+) -> process::ExitCode {
+    let mut monitor = monitor::Monitor::new(
+        settings.gpio.qualify_high_hold,
+        settings.gpio.qualify_low_hold,
+    )
+    .with_startup_alarm(settings.gpio.notify_on_startup_alarm)
+    .with_m_of_n(settings.gpio.m_of_n());
+    let mut worker = notify::SendWorker::spawn(
+        notifiers,
+        settings.notifications.send_queue_size,
+        settings.notifications.send_timeout,
+    );
+    let mut printed_qualified_low = false;
+    let mut printed_qualified_high = false;
+    let mut summary = monitor::Summary::default();
+    let mut read_errors = monitor::ReadErrors::default();
+    let mut flap = settings
+        .gpio
+        .flap_threshold
+        .map(|threshold| monitor::FlapDetector::new(threshold, settings.gpio.flap_window));
+    let mut coalesce = settings.gpio.coalesce_window.map(monitor::Coalescer::new);
+    let mut deadtime_until: Option<Instant> = None;
+    let started = Instant::now();
+    let started_at = chrono::Local::now().timestamp();
+    let mut alarm_active = false;
+    let mut last_qualified: Option<Level> = None;
+    let mut last_reading: Option<Level> = None;
+    let mut read_low = false;
+    let mut restore_marked = false;
+    let mut nominal_since = started;
+    let mut last_status_report = started;
+    let mut pin_name = settings.pin_name(settings.gpio.pin_number);
+    let mut maintenance_until = scheduled_maintenance(&settings, started);
+    let mut sent_alarms = dedupe::SentAlarms::load(&settings.paths.sent_alarms_file);
+
+    if maintenance_until.is_some() {
+        if !settings.quiet() {
+            println!(
+                "Notifications are silenced for maintenance until {}.",
+                settings
+                    .notifications
+                    .maintenance_until
+                    .as_deref()
+                    .unwrap_or_default()
+            );
+        }
+
+        if let Ok(mut status) = status.lock() {
+            status.snapshot.maintenance_until = settings.notifications.maintenance_until.clone();
+        }
+    }
+
+    loop {
+        let now = Instant::now();
+        let was_in_maintenance = maintenance_until;
+
+        if let Some(run_for) = settings.run_for
+            && now.saturating_duration_since(started) >= run_for
+        {
+            let reports = worker.finish();
+            handle_reports(
+                reports,
+                &mut worker,
+                &settings,
+                &status,
+                &mut summary,
+                &mut monitor,
+                &mut sent_alarms,
+            );
+            summary.print(
+                now.saturating_duration_since(started),
+                settings.duration_format,
+            );
+            return process::ExitCode::SUCCESS;
+        }
+
+        if signals::shutdown_requested() {
+            let reports = worker.finish();
+            handle_reports(
+                reports,
+                &mut worker,
+                &settings,
+                &status,
+                &mut summary,
+                &mut monitor,
+                &mut sent_alarms,
+            );
+
+            if !settings.quiet() {
+                println!("Shutting down.");
+                summary.print(
+                    now.saturating_duration_since(started),
+                    settings.duration_format,
+                );
+            }
+            return process::ExitCode::SUCCESS;
+        }
+
+        let reports = worker.take_reports();
+
+        if handle_reports(
+            reports,
+            &mut worker,
+            &settings,
+            &status,
+            &mut summary,
+            &mut monitor,
+            &mut sent_alarms,
+        ) {
+            deadtime_until = Some(now + settings.gpio.post_notify_deadtime);
+        }
+
+        let reload_requested = signals::take_request(settings.signals.reload);
+
+        if let Some((new_settings, mut new_notifiers)) =
+            reloader.poll(&settings, now, reload_requested)
+        {
+            monitor.set_holds(
+                new_settings.gpio.qualify_high_hold,
+                new_settings.gpio.qualify_low_hold,
+            );
+
+            // Don't send again what was already sent before the reload.
+            if let Some(level) = last_qualified {
+                for n in new_notifiers.iter_mut() {
+                    n.mark_sent(level, now);
+                }
+            }
+
+            worker.replace(new_notifiers);
+
+            if new_settings.notifications.maintenance_until
+                != settings.notifications.maintenance_until
+            {
+                maintenance_until = scheduled_maintenance(&new_settings, now);
+            }
+
+            if (
+                new_settings.gpio.flap_threshold,
+                new_settings.gpio.flap_window,
+            ) != (settings.gpio.flap_threshold, settings.gpio.flap_window)
+            {
+                flap = new_settings.gpio.flap_threshold.map(|threshold| {
+                    monitor::FlapDetector::new(threshold, new_settings.gpio.flap_window)
+                });
+            }
+
+            if new_settings.gpio.coalesce_window != settings.gpio.coalesce_window {
+                coalesce = new_settings
+                    .gpio
+                    .coalesce_window
+                    .map(monitor::Coalescer::new);
+            }
+
+            settings = new_settings;
+            pin_name = settings.pin_name(settings.gpio.pin_number);
+
+            if let Ok(mut status) = status.lock() {
+                status.snapshot.pin_name = pin_name.clone();
+            }
+        }
+
+        let mut maintenance_request = status
+            .lock()
+            .ok()
+            .and_then(|mut status| status.maintenance_request.take());
+
+        if signals::take_request(settings.signals.maintenance) {
+            maintenance_request = Some(match maintenance_until {
+                Some(_) => status::MaintenanceRequest::End,
+                None => status::MaintenanceRequest::Start(None),
+            });
+        }
+
+        match maintenance_request {
+            Some(status::MaintenanceRequest::Start(duration)) => {
+                let duration = duration.unwrap_or(settings.notifications.maintenance_duration);
+                maintenance_until = Some(now + duration);
+
+                if !settings.quiet() {
+                    println!(
+                        "Entering maintenance for {}; notifications are silenced.",
+                        format_duration(duration, settings.duration_format)
+                    );
+                }
+            }
+            Some(status::MaintenanceRequest::End) => {
+                let ended = maintenance_until.take().is_some();
+
+                if ended && !settings.quiet() {
+                    println!("Leaving maintenance; notifications resume.");
+                }
+            }
+            None => {}
+        }
+
+        if maintenance_until.is_some_and(|until| now >= until) {
+            maintenance_until = None;
+
+            if !settings.quiet() {
+                println!("Maintenance is over; notifications resume.");
+            }
+        }
+
+        if maintenance_until != was_in_maintenance
+            && let Ok(mut status) = status.lock()
+        {
+            status.snapshot.maintenance_until = maintenance_until.map(|until| {
+                let remaining = until.saturating_duration_since(now);
+                (chrono::Local::now() + remaining)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            });
+        }
+
+        worker.set_silenced(maintenance_until.is_some());
+
+        if signals::take_request(settings.signals.dump_state) {
+            println!(
+                "{pin_name}: level {}, last qualified {}, alarm {}, maintenance {}.",
+                last_reading.map_or_else(|| "unknown".to_string(), |l| l.to_string()),
+                last_qualified.map_or_else(|| "none".to_string(), |l| l.to_string()),
+                if alarm_active { "active" } else { "inactive" },
+                maintenance_until.map_or_else(
+                    || "off".to_string(),
+                    |until| format!(
+                        "for another {}",
+                        format_duration(
+                            until.saturating_duration_since(now),
+                            settings.duration_format
+                        )
+                    )
+                ),
+            );
+            summary.print(
+                now.saturating_duration_since(started),
+                settings.duration_format,
+            );
+        }
+
+        if signals::take_request(settings.signals.test) {
+            let ctx = notify::Context::new(&settings, Level::High, now, now);
+
+            if !settings.quiet() {
+                println!("Sending test notifications, as requested by signal.");
+            }
+
+            worker.send_test(&ctx);
+        }
+
+        let errors_since = read_errors.since().unwrap_or(now);
+        let reading = pin.read();
+        summary.polls += 1;
+
+        if let Ok(mut status) = status.lock() {
+            status.snapshot.polls = summary.polls;
+            status.snapshot.read_errors = summary.read_errors + u64::from(reading.is_err());
+            status.snapshot.level = reading.as_ref().ok().map(Level::to_string);
+        }
+
+        let reading = match reading {
+            Ok(reading) => {
+                if let Some((count, alerted)) = read_errors.clear() {
+                    if !settings.quiet() {
+                        println!("{pin_name}: reading recovered after {count} failed reads.");
+                    }
+
+                    if alerted {
+                        let ctx = notify::Context::new(&settings, reading, now, errors_since);
+
+                        worker.send_oneshot(
+                            &ctx,
+                            defaults::gpio::READ_RECOVERED_SUBJECT,
+                            defaults::gpio::READ_RECOVERED_BODY,
+                        );
+                    }
+                }
+
+                reading
+            }
+            Err(e) => {
+                summary.read_errors += 1;
+
+                if read_errors.record(now, defaults::gpio::READ_ERROR_LOG_INTERVAL) {
+                    eprintln!(
+                        "[!] Failed to read {pin_name} ({} in a row): {e}",
+                        read_errors.count()
+                    );
+                }
+
+                if read_errors.should_alert(now, settings.gpio.read_error_timeout) {
+                    let ctx = notify::Context::new(
+                        &settings,
+                        Level::High,
+                        now,
+                        read_errors.since().unwrap_or(now),
+                    );
+
+                    worker.send_oneshot(
+                        &ctx,
+                        defaults::gpio::READ_ERROR_SUBJECT,
+                        defaults::gpio::READ_ERROR_BODY,
+                    );
+                }
+
+                thread::sleep(settings.gpio.poll_interval);
+                continue;
+            }
+        };
+
+        last_reading = Some(reading);
+        read_low |= reading == Level::Low;
+
+        if settings.trace() {
+            println!("{pin_name}: {reading}");
+        }
+
+        let poll_interval = settings.gpio.poll_interval_for(reading);
+
+        if let Some(event) = flap.as_mut().and_then(|flap| flap.record(reading, now)) {
+            let (subject, body) = match event {
+                monitor::FlapEvent::Started => {
+                    if !settings.quiet() {
+                        println!("{pin_name}: signal unstable; holding notifications.");
+                    }
+
+                    (
+                        defaults::gpio::FLAP_STARTED_SUBJECT,
+                        defaults::gpio::FLAP_STARTED_BODY,
+                    )
+                }
+                monitor::FlapEvent::Settled => {
+                    if !settings.quiet() {
+                        println!("{pin_name}: signal stable again, now {reading}.");
+                    }
+
+                    (
+                        defaults::gpio::FLAP_SETTLED_SUBJECT,
+                        defaults::gpio::FLAP_SETTLED_BODY,
+                    )
+                }
+            };
+
+            let ctx = notify::Context::new(&settings, reading, now, now);
+
+            worker.send_oneshot(&ctx, subject, body);
+        }
+
+        let flapping = flap
+            .as_ref()
+            .is_some_and(monitor::FlapDetector::is_flapping);
+
+        if let Some(interval) = settings.notifications.status_report_interval
+            && now.saturating_duration_since(last_status_report) >= interval
+        {
+            // A report that falls due during an alarm is skipped, not postponed.
+            last_status_report = now;
+
+            if !alarm_active && reading == Level::Low {
+                let ctx = notify::Context::new(&settings, Level::Low, now, nominal_since);
+
+                worker.send_oneshot(
+                    &ctx,
+                    defaults::notifications::STATUS_REPORT_SUBJECT,
+                    &settings.notifications.status_report_message,
+                );
+            }
+        }
+
+        if let Some(until) = deadtime_until
+            && now < until
+        {
+            if settings.debug() {
+                println!("{pin_name}: {reading} ignored during post-notification dead time");
+            }
+
+            thread::sleep(poll_interval);
+            continue;
+        }
+
+        let Some(level) = monitor.poll(reading, now) else {
+            thread::sleep(poll_interval);
+            continue;
+        };
+
+        last_qualified = Some(level);
+
+        let changed = match (level, alarm_active) {
+            (Level::High, false) => {
+                alarm_active = true;
+                restore_marked = false;
+                true
+            }
+            (Level::Low, true) => {
+                alarm_active = false;
+                nominal_since = now;
+                true
+            }
+            _ => false,
+        };
+
+        if changed && let Some(coalesce) = coalesce.as_mut() {
+            if !alarm_active {
+                coalesce.restored(now);
+            } else if let Some(dropout) = coalesce.realarmed(now)
+                && !settings.quiet()
+            {
+                println!(
+                    "{pin_name}: back in alarm after a dropout of {}; \
+                    not notifying the restore or the alarm anew.",
+                    format_duration(dropout, settings.duration_format)
+                );
+            }
+        }
+
+        // An alarm going on since startup continues the last episode
+        // recorded before it, while one that starts later is a new episode.
+        if changed
+            && alarm_active
+            && !read_low
+            && let Some(window) = settings.notifications.restart_dedupe_window
+            && let Some(episode_start) =
+                sent_alarms.episode_before(settings.gpio.pin_number, started_at)
+            && let Some(sent) = sent_alarms.recent(
+                settings.gpio.pin_number,
+                episode_start,
+                window,
+                chrono::Local::now().timestamp(),
+            )
+        {
+            // The alarm was sent before a restart and is still going on, so
+            // move the notifiers on as if it had been sent by this process.
+            if !settings.quiet() {
+                println!(
+                    "{pin_name}: alarm since {} already sent before a restart; not sending it again.",
+                    chrono::DateTime::from_timestamp(sent.episode_start, 0)
+                        .map(|start| start
+                            .with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string())
+                        .unwrap_or_default()
+                );
+            }
+
+            worker.mark_sent(Level::High, now);
+            monitor.mark_alarm_notified();
+        }
+
+        if changed && maintenance_until.is_some() && !settings.quiet() {
+            println!(
+                "{pin_name}: {} during maintenance; not notifying.",
+                if alarm_active { "alarm" } else { "restored" }
+            );
+        } else if changed
+            && !alarm_active
+            && !settings.notifications.send_restored
+            && !settings.quiet()
+        {
+            println!("{pin_name}: restored; not notifying, as restored notifications are off.");
+        }
+
+        if let Ok(mut status) = status.lock() {
+            status.snapshot.qualified_level = Some(level.to_string());
+
+            if changed {
+                status.snapshot.alarm = alarm_active;
+                status.snapshot.since =
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            }
+        }
+
+        match level {
+            Level::Low => {
+                if settings.debug() && !printed_qualified_low {
+                    println!("Level::LOW");
+                    printed_qualified_low = true;
+                    printed_qualified_high = false;
+                }
+
+                let ctx = notify::Context::new(
+                    &settings,
+                    Level::Low,
+                    now,
+                    monitor.since(Level::Low).unwrap_or(now),
+                );
+
+                // A restore held back to coalesce a dropout isn't passed on
+                // until the coalesce window has passed.
+                let held = coalesce
+                    .as_ref()
+                    .is_some_and(|coalesce| coalesce.holds_restore(now));
+
+                if !flapping && !held {
+                    if !settings.dry_run
+                        && let Err(e) = sent_alarms.clear(settings.gpio.pin_number)
+                    {
+                        eprintln!(
+                            "[!] Failed to write {}: {e}",
+                            settings.paths.sent_alarms_file.display()
+                        );
+                    }
+
+                    if settings.notifications.send_restored {
+                        worker.send_notification(&ctx);
+                    } else if !restore_marked {
+                        // Move the notifiers on from the alarm without
+                        // sending anything, so the next alarm is sent anew.
+                        worker.mark_sent(Level::Low, now);
+                        restore_marked = true;
+                    }
+                }
+            }
+            Level::High => {
+                if settings.debug() && !printed_qualified_high {
+                    println!("Level::HIGH");
+                    printed_qualified_high = true;
+                    printed_qualified_low = false;
+                }
+
+                let log_tail = match settings.notifications.include_log_tail {
+                    0 => String::new(),
+                    lines => status
+                        .lock()
+                        .map(|status| {
+                            history::format_tail(status.tail(lines), settings.duration_format)
+                        })
+                        .unwrap_or_default(),
+                };
+
+                let ctx = notify::Context {
+                    log_tail,
+                    ..notify::Context::new(
+                        &settings,
+                        Level::High,
+                        now,
+                        monitor.since(Level::High).unwrap_or(now),
+                    )
+                };
+
+                if !flapping {
+                    worker.send_notification(&ctx);
+                }
+
+                if settings.dry_run && worker.is_empty() {
+                    // In dry run mode, we consider the notification "successful"
+                    // even if there are no backends configured, since the user
+                    // just wants to see what would happen.
+                    monitor.mark_alarm_notified();
+                }
+            }
+        }
+
+        thread::sleep(poll_interval)
+    }
+}
+
+/// Returns when the maintenance window set in the configuration ends, as
+/// measured from `now`, if it is set and still in the future.
+fn scheduled_maintenance(settings: &Settings, now: Instant) -> Option<Instant> {
+    let until = settings.notifications.maintenance_until()?;
+    let remaining = (until - chrono::Local::now()).to_std().ok()?;
+    Some(now + remaining)
+}
+
+/// Logs the outcomes of the sends the worker has finished, counting them in
+/// the summary, recording alarms and restores in the history, and recording
+/// delivered alarms as sent across restarts.
+///
+/// Returns whether an alarm or restored notification was delivered, or
+/// printed in a dry run, so that the post-notification dead time can start.
+fn handle_reports(
+    reports: Vec<notify::worker::Report>,
+    worker: &mut notify::SendWorker,
+    settings: &Settings,
+    status: &status::SharedStatus,
+    summary: &mut monitor::Summary,
+    monitor: &mut monitor::Monitor,
+    sent_alarms: &mut dedupe::SentAlarms,
+) -> bool {
+    let mut notified = false;
+
+    for report in reports {
+        match report.kind {
+            notify::worker::JobKind::Notification => {
+                let event = match report.ctx.level {
+                    Level::High => "alarm",
+                    Level::Low => "restored",
+                };
+                let mut outcomes = Vec::new();
+
+                for (name, result, receipt) in report.results {
+                    match result {
+                        notify::NotificationResult::NotYetTime
+                        | notify::NotificationResult::Skipped
+                        | notify::NotificationResult::Deduplicated => {}
+                        notify::NotificationResult::DryRun => notified = true,
+                        notify::NotificationResult::Success => {
+                            notified = true;
+
+                            match report.ctx.level {
+                                Level::High => {
+                                    summary.alarms_sent += 1;
+                                    monitor.mark_alarm_notified();
+                                }
+                                Level::Low => summary.restores_sent += 1,
+                            }
+
+                            if !settings.quiet() {
+                                println!("{name}: {event} success");
+                            }
+
+                            outcomes.push(history::Outcome {
+                                notifier: name,
+                                error: None,
+                                receipt,
+                            });
+                        }
+                        notify::NotificationResult::Failure(message) => {
+                            summary.failures += 1;
+                            println!("{name}: {event} failure: {message}");
+
+                            outcomes.push(history::Outcome {
+                                notifier: name,
+                                error: Some(message),
+                                receipt,
+                            });
+                        }
+                    }
+                }
+
+                if report.ctx.level == Level::High
+                    && outcomes.iter().any(|outcome| outcome.error.is_none())
+                {
+                    record_sent_alarm(settings, sent_alarms, &report.ctx);
+                }
+
+                report_partial_failure(worker, settings, &report.ctx, &outcomes);
+                record_history(settings, status, &report.ctx, outcomes);
+            }
+            notify::worker::JobKind::Oneshot => {
+                for (name, result, _) in report.results {
+                    match result {
+                        notify::NotificationResult::Success if !settings.quiet() => {
+                            println!("{name}: notice success");
+                        }
+                        notify::NotificationResult::Failure(message) => {
+                            println!("{name}: notice failure: {message}");
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            notify::worker::JobKind::Test => {
+                for (name, result, _) in report.results {
+                    match result {
+                        notify::NotificationResult::Success => println!("{name}: test success"),
+                        notify::NotificationResult::Failure(message) => {
+                            println!("{name}: test failure: {message}");
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    notified
+}
+
+/// Sends a notice through all notifiers if only some of them succeeded in a
+/// round of notification attempts, naming the ones that failed.
+///
+/// Nothing is sent unless `notify_on_partial_failure` is enabled.
+fn report_partial_failure(
+    worker: &mut notify::SendWorker,
+    settings: &Settings,
+    ctx: &notify::Context,
+    outcomes: &[history::Outcome],
+) {
+    if !settings.notifications.notify_on_partial_failure {
+        return;
+    }
+
+    let failed: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| outcome.error.is_some())
+        .map(|outcome| outcome.notifier.as_str())
+        .collect();
+
+    if failed.is_empty() || failed.len() == outcomes.len() {
+        return;
+    }
+
+    let body = settings
+        .notifications
+        .partial_failure_message
+        .replace("{succeeded}", &(outcomes.len() - failed.len()).to_string())
+        .replace("{total}", &outcomes.len().to_string())
+        .replace("{failed}", &failed.join(", "));
+
+    worker.send_oneshot(ctx, defaults::notifications::PARTIAL_FAILURE_SUBJECT, &body);
+}
+
+/// Records a round of notification attempts in the status socket's recent
+/// history, and appends it to the history file if that is enabled.
+///
+/// Nothing is recorded if this is a dry run, or if no notifier actually
+/// attempted to send anything. Failing to write the entry is reported but
+/// otherwise ignored.
+fn record_history(
+    settings: &Settings,
+    status: &status::SharedStatus,
+    ctx: &notify::Context,
+    outcomes: Vec<history::Outcome>,
+) {
+    if settings.dry_run || outcomes.is_empty() {
+        return;
+    }
+
+    let entry = history::Entry {
+        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        event: match ctx.level {
+            Level::High => "alarm",
+            Level::Low => "restored",
+        }
+        .to_string(),
+        pin_number: ctx.pin_number,
+        pin_name: ctx.pin_name.clone(),
+        since_secs: ctx.now.saturating_duration_since(ctx.since).as_secs(),
+        outcomes,
+    };
+
+    if let Ok(mut status) = status.lock() {
+        status.push(entry.clone());
+    }
+
+    if !settings.history.enabled {
+        return;
+    }
+
+    if settings.history.compress_rotated_logs {
+        match history::rotate(&settings.paths.history_file, settings.history.max_entries) {
+            Ok(Some(segment)) => {
+                history::compress_in_background(segment);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!(
+                "[!] Failed to rotate history file {}: {e}",
+                settings.paths.history_file.display()
+            ),
+        }
     }
 
-    print_banner();
-    println!();
+    if let Err(e) = history::append(
+        &settings.paths.history_file,
+        &entry,
+        settings.history.max_entries,
+    ) {
+        eprintln!(
+            "[!] Failed to write history entry to {}: {e}",
+            settings.paths.history_file.display()
+        );
+    }
+}
 
-    let cli = cli::Cli::parse();
+/// Records a delivered alarm as sent, so that it isn't sent again after a
+/// restart within the restart dedupe window.
+///
+/// Nothing is recorded if this is a dry run, or if the window isn't set.
+/// Failing to write the record is reported but otherwise ignored.
+fn record_sent_alarm(
+    settings: &Settings,
+    sent_alarms: &mut dedupe::SentAlarms,
+    ctx: &notify::Context,
+) {
+    let Some(window) = settings.notifications.restart_dedupe_window else {
+        return;
+    };
 
-    if cli.version {
-        // This is the only way to get a neat --version output.
-        // The banner with version is already printed just prior to this before clap parses arguments,
-        // so we can just exit successfully here after echoing the licenses.
-        println!(
-            "This project is dual-licensed under the MIT License and the Apache License (Version 2.0)."
+    if settings.dry_run {
+        return;
+    }
+
+    let now = chrono::Local::now().timestamp();
+    let held = ctx.now.saturating_duration_since(ctx.since).as_secs();
+    let episode_start = now.saturating_sub(i64::try_from(held).unwrap_or(i64::MAX));
+
+    if let Err(e) = sent_alarms.record(ctx.pin_number, episode_start, now, window) {
+        eprintln!(
+            "[!] Failed to write {}: {e}",
+            settings.paths.sent_alarms_file.display()
         );
-        return process::ExitCode::SUCCESS;
     }
+}
 
-    let settings = match init_settings(&cli) {
-        Ok(s) => s,
-        Err(code) => return code,
-    };
+/// Prints the last `count` entries of the history file.
+fn print_history(settings: &Settings, count: usize) -> process::ExitCode {
+    let path = &settings.paths.history_file;
 
-    if cli.show {
-        settings.print();
+    if !path.exists() {
+        println!("No history recorded yet at {}.", path.display());
         return process::ExitCode::SUCCESS;
     }
 
-    if let Err(vec) = settings.sanity_check() {
-        eprintln!("[!] Configuration has errors:");
-
-        for error in vec {
-            eprintln!("  * {error}");
+    match history::read_last(path, count) {
+        Ok(entries) => {
+            history::print(&entries, settings.duration_format);
+            process::ExitCode::SUCCESS
         }
-
-        if settings.dry_run {
-            println!("[!] Continuing anyway because --dry-run is set.");
-            println!();
-        } else {
-            return process::ExitCode::from(defaults::exit_codes::CONFIGURATION_ERROR);
+        Err(e) => {
+            eprintln!("[!] Failed to read history file {}: {e}", path.display());
+            process::ExitCode::from(defaults::exit_codes::FAILED_TO_READ_HISTORY)
         }
     }
+}
 
-    settings.print();
-    println!();
-
-    let gpio = match Gpio::new() {
-        Ok(g) => g,
+/// Replays the recorded alarms and restores of the passed history file through
+/// the current configuration, and prints how the notifications it would have
+/// sent compare with the recorded ones, for `--replay`.
+///
+/// Exits with an error if they differ, so that it can be used as a check.
+fn replay_history(settings: &Settings, path: &str) -> process::ExitCode {
+    let entries = match history::read_last(Path::new(path), usize::MAX) {
+        Ok(entries) => entries,
         Err(e) => {
-            eprintln!("[!] Failed to initialize GPIO: {e}");
-            return process::ExitCode::from(defaults::exit_codes::FAILED_TO_INITIALISE_GPIO);
+            eprintln!("[!] Failed to read history file {path}: {e}");
+            return process::ExitCode::from(defaults::exit_codes::FAILED_TO_READ_HISTORY);
         }
     };
 
-    let pin = match gpio.get(settings.gpio.pin_number) {
-        Ok(p) => p.into_input_pullup(),
+    let (recorded, changes) = match replay::recorded(&entries) {
+        Ok(recorded) => recorded,
         Err(e) => {
-            eprintln!(
-                "[!] Failed to set mode of GPIO{}: {e}",
-                settings.gpio.pin_number
-            );
-            return process::ExitCode::from(defaults::exit_codes::FAILED_TO_SET_PIN_MODE);
+            eprintln!("[!] Failed to read history file {path}: {e}");
+            return process::ExitCode::from(defaults::exit_codes::FAILED_TO_READ_HISTORY);
         }
     };
 
-    let notifiers = build_notifiers(&settings);
-
-    if notifiers.is_empty() && !settings.dry_run {
-        eprintln!("[!] No notifiers are configured.");
-        return process::ExitCode::from(defaults::exit_codes::NO_NOTIFIERS_CONFIGURED);
-    }
+    let Some(last) = recorded.last() else {
+        println!("No alarms or restores recorded in {path}; nothing to replay.");
+        return process::ExitCode::SUCCESS;
+    };
 
-    run_loop(pin, notifiers, settings)
-}
+    println!(
+        "Replaying {} recorded alarm(s) and restore(s) from {path}.",
+        recorded.len()
+    );
+    println!();
 
-/// Initialises and returns a `Vec` of notifiers.
-///
-/// Notifiers are instances of `TwoLevelNotifier` with a backend of either
-/// `SlackBackend` or `BatsignBackend`, depending on the resolved settings.
-/// Their settings must have been read into the `Settings` struct before
-/// calling this function.
-///
-/// # Example
-/// ```
-/// let settings = Settings::default();
-/// // ... apply config file and CLI overrides to settings ...
-/// let notifiers: Vec<Box<dyn notify::Notifier>> = build_notifiers(&settings);
-/// ```
-fn build_notifiers(settings: &Settings) -> Vec<Box<dyn notify::Notifier>> {
-    let client = Arc::new(Client::new());
-    let mut notifiers: Vec<Box<dyn notify::Notifier>> = Vec::new();
+    let replayed = replay::replay(settings, &changes, last.time);
 
-    if settings.slack.enabled {
-        for (i, url) in settings.slack.urls.iter().enumerate() {
-            let n = notify::TwoLevelNotifier::new(
-                backend::slack::SlackBackend::new(i, Arc::clone(&client), url),
-                Some(settings.slack.notification_interval),
-                settings.slack.retry_interval,
-                &settings.slack.alarm_message_template_body,
-                &settings.slack.restored_message_template_body,
-                settings.dry_run,
-            );
+    if replay::compare(&recorded, &replayed, settings.gpio.poll_interval) {
+        println!();
+        println!("The current configuration would have sent the same notifications.");
+        process::ExitCode::SUCCESS
+    } else {
+        println!();
+        println!("[!] The current configuration would have notified differently.");
+        process::ExitCode::from(defaults::exit_codes::REPLAY_DIFFERS)
+    }
+}
 
-            if settings.debug {
-                println!("{}: initialized with URL {}", n.name(), url);
-            }
+/// Queries the status socket of a running instance and pretty-prints the response.
+fn query_status(settings: &Settings, request: &str) -> process::ExitCode {
+    let path = &settings.paths.status_socket;
 
-            notifiers.push(Box::new(n));
+    let response = match status::query(path, request) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("[!] Failed to query status socket {}: {e}", path.display());
+            return process::ExitCode::from(defaults::exit_codes::STATUS_QUERY_FAILED);
         }
-    }
+    };
 
-    if settings.batsign.enabled {
-        for (i, url) in settings.batsign.urls.iter().enumerate() {
-            let n = notify::TwoLevelNotifier::new(
-                backend::batsign::BatsignBackend::new(i, Arc::clone(&client), url),
-                Some(settings.batsign.notification_interval),
-                settings.batsign.retry_interval,
-                &settings.batsign.alarm_message_template_body,
-                &settings.batsign.restored_message_template_body,
-                settings.dry_run,
+    match serde_json::from_str::<serde_json::Value>(&response) {
+        Ok(value) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&value).unwrap_or(response)
             );
 
-            if settings.debug {
-                println!("{}: initialized with URL {}", n.name(), url);
+            if value.get("error").is_some() {
+                return process::ExitCode::from(defaults::exit_codes::STATUS_QUERY_FAILED);
             }
 
-            notifiers.push(Box::new(n));
+            process::ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("[!] Malformed response from status socket: {e}");
+            process::ExitCode::from(defaults::exit_codes::STATUS_QUERY_FAILED)
         }
     }
-
-    notifiers
 }
 
-/// The main loop that monitors the GPIO pin and sends notifications
-/// based on the configured notifiers and settings.
-///
-/// Notifiers must have been initialised before calling this function,
-/// and the GPIO pin must likewise have been set up as an input with pull-up.
+/// Returns the configuration that `--save` writes for the passed settings.
 ///
-/// # Example
-/// ```
-/// let gpio = Gpio::new().unwrap();
-/// let pin = gpio.get(settings.gpio.pin_number).unwrap().into_input_pullup();
-/// let notifiers = build_notifiers(&settings);
-/// run_loop(pin, notifiers, settings)
-/// ```
-fn run_loop(
-    pin: InputPin,
-    mut notifiers: Vec<Box<dyn notify::Notifier>>,
-    settings: Settings,
-) -> process::ExitCode {
-    let mut low_since: Option<Instant> = None;
-    let mut high_since: Option<Instant> = None;
-    let mut seen_high = false;
-    let mut printed_qualified_low = false;
-    let mut printed_qualified_high = false;
-
-    loop {
-        let now = Instant::now();
-
-        match pin.read() {
-            Level::Low => {
-                let start = low_since.get_or_insert(now);
-                let qualified = start.elapsed() >= settings.gpio.hold;
+/// Profiles aren't part of the resolved settings, so they are carried over
+/// as-is from the configuration file read.
+fn saved_config(
+    settings: &Settings,
+    config: Option<file_config::FileConfig>,
+) -> file_config::FileConfig {
+    let profiles = config.map(|cfg| cfg.profiles).unwrap_or_default();
+    let mut saved = file_config::FileConfig::from(settings);
+    saved.profiles = profiles;
+    saved
+}
 
-                if !qualified || !seen_high {
-                    thread::sleep(settings.gpio.poll_interval);
-                    continue;
-                }
+/// Prints a unified diff between the configuration file on disk and what
+/// `--save` would write in its place, without writing anything.
+fn diff_config(settings: &Settings, config: Option<file_config::FileConfig>) -> process::ExitCode {
+    let path = &settings.paths.config_file;
 
-                if settings.debug && !printed_qualified_low {
-                    println!("Level::LOW");
-                    printed_qualified_low = true;
-                    printed_qualified_high = false;
-                }
+    match file_config::diff_config_file(path, &saved_config(settings, config)) {
+        Ok(None) => {
+            println!(
+                "No changes; --save would leave {} as it is.",
+                path.display()
+            );
+            process::ExitCode::SUCCESS
+        }
+        Ok(Some(diff)) => {
+            print!("{diff}");
+            process::ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!(
+                "[!] Failed to diff configuration file {}: {e}",
+                path.display()
+            );
+            process::ExitCode::from(defaults::exit_codes::FAILED_TO_DIFF_CONFIG_FILE)
+        }
+    }
+}
 
-                high_since = None;
+/// Prints the configuration `--save` would write, as TOML, without writing
+/// anything. Unlike `--show`, the output can be read back as a configuration
+/// file.
+fn config_check(settings: &Settings, config: Option<file_config::FileConfig>) -> process::ExitCode {
+    match toml::to_string_pretty(&saved_config(settings, config)) {
+        Ok(text) => {
+            print!("{text}");
+            process::ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("[!] Failed to render configuration: {e}");
+            process::ExitCode::from(defaults::exit_codes::FAILED_TO_RENDER_CONFIG)
+        }
+    }
+}
 
-                let ctx = notify::Context {
-                    level: Level::Low,
-                    now,
-                };
+/// Shows how each template file differs from its built-in default and asks
+/// whether to replace it. A replaced file is first copied to a `.bak` next
+/// to it, and missing files are offered to be written anew.
+fn update_templates(settings: &Settings) -> process::ExitCode {
+    let mut failed = false;
+    let mut up_to_date = 0;
 
-                for n in notifiers.iter_mut() {
-                    match n.send_notification(&ctx) {
-                        notify::NotificationResult::NotYetTime => {}
-                        notify::NotificationResult::DryRun => {}
-                        notify::NotificationResult::Success => {
-                            println!("{}: restored success", n.name());
-                        }
-                        notify::NotificationResult::Failure(message) => {
-                            println!("{}: restored failure: {message}", n.name());
-                        }
-                    }
-                }
+    for (what, path, default) in settings.template_files() {
+        let diff = match file_config::diff_template_file(path, default) {
+            Ok(Some(diff)) => diff,
+            Ok(None) => {
+                up_to_date += 1;
+                continue;
             }
-            Level::High => {
-                let start = high_since.get_or_insert(now);
-                let qualified = start.elapsed() >= settings.gpio.hold;
+            Err(e) => {
+                eprintln!("[!] Failed to read {what} {}: {e}", path.display());
+                failed = true;
+                continue;
+            }
+        };
 
-                if !qualified {
-                    thread::sleep(settings.gpio.poll_interval);
-                    continue;
-                }
+        println!("{diff}");
 
-                if settings.debug && !printed_qualified_high {
-                    println!("Level::HIGH");
-                    printed_qualified_high = true;
-                    printed_qualified_low = false;
-                }
+        let existed = path.exists();
+        let question = if existed {
+            format!("Replace {what} with the default?")
+        } else {
+            format!("Write the default {what}?")
+        };
 
-                low_since = None;
+        if !confirm(&question) {
+            if existed {
+                println!("Kept {}.", path.display());
+            } else {
+                println!("Left {} missing.", path.display());
+            }
 
-                let ctx = notify::Context {
-                    level: Level::High,
-                    now,
-                };
+            println!();
+            continue;
+        }
 
-                for n in notifiers.iter_mut() {
-                    match n.send_notification(&ctx) {
-                        notify::NotificationResult::NotYetTime => {}
-                        notify::NotificationResult::DryRun => {}
-                        notify::NotificationResult::Success => {
-                            println!("{}: alarm success", n.name());
-                            seen_high = true;
-                        }
-                        notify::NotificationResult::Failure(message) => {
-                            println!("{}: alarm failure: {message}", n.name());
-                        }
-                    }
-                }
+        let mut backup = path.as_os_str().to_os_string();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
 
-                if settings.dry_run && notifiers.is_empty() {
-                    // In dry run mode, we consider the notification "successful"
-                    // even if there are no backends configured, since the user
-                    // just wants to see what would happen.
-                    seen_high = true;
-                }
+        if existed && let Err(e) = fs::copy(path, &backup) {
+            eprintln!(
+                "[!] Failed to back up {what} to {}: {e}; leaving it as it is.",
+                backup.display()
+            );
+            failed = true;
+            continue;
+        }
+
+        match file_config::write_atomic(path, default) {
+            Ok(()) if existed => println!(
+                "Updated {}; the old version was kept as {}.",
+                path.display(),
+                backup.display()
+            ),
+            Ok(()) => println!("Wrote {}.", path.display()),
+            Err(e) => {
+                eprintln!("[!] Failed to write {what} {}: {e}", path.display());
+                failed = true;
             }
         }
 
-        thread::sleep(settings.gpio.poll_interval)
+        println!();
+    }
+
+    if up_to_date > 0 {
+        println!("{up_to_date} template(s) already match the defaults.");
+    }
+
+    if failed {
+        process::ExitCode::from(defaults::exit_codes::FAILED_TO_UPDATE_TEMPLATES)
+    } else {
+        process::ExitCode::SUCCESS
     }
 }
 
+/// Asks the passed yes/no question on the terminal, returning whether it was
+/// answered yes. Anything else, including end of input, counts as no.
+fn confirm(question: &str) -> bool {
+    setup::confirm(&mut io::stdin().lock(), question)
+}
+
 /// Initializes the settings by loading defaults, applying the config file,
 /// and then applying CLI overrides.
 ///
 /// If the `--save` flag is set, it saves the resolved configuration back to disk and exits.
+/// If `--diff-config` is, it instead shows what saving would change, and exits.
+/// If `--setup` is, it first asks for the key settings, then saves as `--save` does.
 ///
 /// # Example
 /// ```
@@ -307,6 +2430,27 @@ fn run_loop(
 fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
     let mut settings = Settings::default();
 
+    // `--diff-config`, `--config-check` and `--setup` resolve the settings the
+    // same way `--save` does, and `--update-templates` is as lenient about
+    // missing files as it is.
+    let saving =
+        cli.save || cli.diff_config || cli.config_check || cli.update_templates || cli.setup;
+
+    // Without a configuration directory there is nothing to read; start from
+    // the defaults, including the built-in templates.
+    if cli.no_config {
+        settings.apply_no_config();
+        settings.apply_env();
+        settings.apply_cli(cli);
+        settings.clean_up();
+
+        if cli.calibrate {
+            calibrate(cli, &mut settings)?;
+        }
+
+        return Ok(settings);
+    }
+
     if let Err(e) = settings.inherit_config_dir(&cli.config_dir) {
         eprintln!("[!] Error resolving default configuration directory: {}", e);
         return Err(process::ExitCode::from(
@@ -314,7 +2458,7 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
         ));
     }
 
-    if !settings.paths.config_dir.exists() && !cli.save {
+    if !settings.paths.config_dir.exists() && !saving {
         eprintln!(
             "[!] Configuration directory {} does not exist. \
             Create it or run with `--save` to generate default configuration and resources.",
@@ -327,20 +2471,8 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
 
     settings.resolve_resource_paths();
 
-    let resource_load_results = settings.load_resources_from_disk();
-
-    if !cli.save && !resource_load_results.is_empty() {
-        eprintln!("[!] Failed to load resouces from disk:");
-
-        for (pathbuf, e) in &resource_load_results {
-            eprintln!("  * {}: {e}", pathbuf.display());
-        }
-
-        if !resource_load_results.is_empty() {
-            return Err(process::ExitCode::from(
-                defaults::exit_codes::FAILED_TO_LOAD_RESOURCES,
-            ));
-        }
+    if let Some(path) = &cli.config_file {
+        settings.paths.config_file = PathBuf::from(path);
     }
 
     let config = match file_config::deserialize_config_file(&settings.paths.config_file) {
@@ -356,7 +2488,7 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
         }
     };
 
-    if !cli.save && config.is_none() {
+    if !saving && config.is_none() {
         eprintln!(
             "[!] No configuration file found at {}. \
             Create it or run with `--save` to generate default configuration and resources.",
@@ -367,11 +2499,105 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
         ));
     }
 
-    settings.apply_file(&config);
+    if let Some(cfg) = &config
+        && cfg.gpio.hold.is_some()
+    {
+        eprintln!(
+            "[!] The `hold` setting is deprecated; \
+            use `qualify_high_hold` and `qualify_low_hold` instead."
+        );
+    }
+
+    let profile = cli.profile.as_deref();
+
+    if let Some(name) = profile
+        && config
+            .as_ref()
+            .is_none_or(|cfg| cfg.profile(name).is_none())
+    {
+        eprintln!(
+            "[!] No profile named \"{name}\" in configuration file {}.",
+            settings.paths.config_file.display()
+        );
+        return Err(process::ExitCode::from(
+            defaults::exit_codes::UNKNOWN_PROFILE,
+        ));
+    }
+
+    settings.apply_path_overrides(&config, profile);
+
+    let resource_load_results = settings.load_resources_from_disk();
+
+    if !saving && !resource_load_results.is_empty() {
+        eprintln!("[!] Failed to load resources from disk:");
+
+        for (what, pathbuf, e) in &resource_load_results {
+            if e.kind() == io::ErrorKind::NotFound {
+                eprintln!("  * {what} is missing: {}", pathbuf.display());
+            } else {
+                eprintln!("  * {what} could not be read: {}: {e}", pathbuf.display());
+            }
+        }
+
+        if resource_load_results
+            .iter()
+            .any(|(_, _, e)| e.kind() == io::ErrorKind::NotFound)
+        {
+            eprintln!("Run with `--save` to recreate missing resource files with their defaults.");
+        }
+
+        return Err(process::ExitCode::from(
+            defaults::exit_codes::FAILED_TO_LOAD_RESOURCES,
+        ));
+    }
+
+    settings.apply_file(&config, profile);
+    settings.apply_env();
     settings.apply_cli(cli);
     settings.clean_up();
 
-    if cli.save {
+    if cli.calibrate {
+        calibrate(cli, &mut settings)?;
+    }
+
+    // Modes that only read and print something don't fetch the remote URL list.
+    #[cfg(feature = "notifications")]
+    let read_only = cli.show || cli.history.is_some() || cli.status.is_some();
+
+    #[cfg(feature = "notifications")]
+    if !saving && !read_only && settings.batsign.enabled && !cli.replace_urls {
+        match settings.load_remote_batsign_urls() {
+            Ok(0) => {}
+            Ok(n) => {
+                if !settings.quiet() {
+                    println!("Fetched {n} Batsign URL(s) from remote source.");
+                }
+            }
+            Err((e, n)) => {
+                eprintln!("[!] Failed to fetch Batsign URLs from remote source: {e}");
+                eprintln!("[!] Using {n} cached Batsign URL(s) instead.");
+            }
+        }
+    }
+
+    if cli.diff_config {
+        return Err(diff_config(&settings, config));
+    }
+
+    if cli.config_check {
+        return Err(config_check(&settings, config));
+    }
+
+    if cli.update_templates {
+        return Err(update_templates(&settings));
+    }
+
+    if cli.setup && !setup::run(&mut settings, &mut io::stdin().lock()) {
+        println!("Setup aborted; nothing was written.");
+        return Err(process::ExitCode::from(defaults::exit_codes::SETUP_ABORTED));
+    }
+
+    if cli.save || cli.setup {
         if !settings.paths.config_dir.exists() {
             match fs::create_dir_all(&settings.paths.config_dir) {
                 Ok(()) => {
@@ -392,9 +2618,9 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
             };
         }
 
-        let config = file_config::FileConfig::from(&settings);
+        let config = saved_config(&settings, config);
 
-        if let Err(e) = confy::store_path(&settings.paths.config_file, config) {
+        if let Err(e) = file_config::store_config_file(&settings.paths.config_file, config) {
             eprintln!(
                 "[!] Failed to write configuration file {}: {e}",
                 settings.paths.config_file.display()
@@ -404,8 +2630,8 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
             ));
         };
 
-        if fs::write(
-            settings.paths.slack_alarm_template,
+        if file_config::write_atomic(
+            &settings.paths.slack_alarm_template,
             &settings.slack.alarm_message_template_body,
         )
         .is_err()
@@ -416,8 +2642,8 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
             ));
         }
 
-        if fs::write(
-            settings.paths.slack_restored_template,
+        if file_config::write_atomic(
+            &settings.paths.slack_restored_template,
             &settings.slack.restored_message_template_body,
         )
         .is_err()
@@ -428,8 +2654,8 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
             ));
         }
 
-        if fs::write(
-            settings.paths.batsign_alarm_template,
+        if file_config::write_atomic(
+            &settings.paths.batsign_alarm_template,
             &settings.batsign.alarm_message_template_body,
         )
         .is_err()
@@ -440,8 +2666,8 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
             ));
         }
 
-        if fs::write(
-            settings.paths.batsign_restored_template,
+        if file_config::write_atomic(
+            &settings.paths.batsign_restored_template,
             &settings.batsign.restored_message_template_body,
         )
         .is_err()
@@ -452,10 +2678,40 @@ fn init_settings(cli: &cli::Cli) -> Result<Settings, process::ExitCode> {
             ));
         }
 
+        if file_config::write_atomic(
+            &settings.paths.webhook_alarm_template,
+            &settings.webhook.alarm_payload_template_body,
+        )
+        .is_err()
+        {
+            eprintln!("[!] Failed to write webhook alarm template file.");
+            return Err(process::ExitCode::from(
+                defaults::exit_codes::FAILED_TO_WRITE_WEBHOOK_ALARM_TEMPLATE,
+            ));
+        }
+
+        if file_config::write_atomic(
+            &settings.paths.webhook_restored_template,
+            &settings.webhook.restored_payload_template_body,
+        )
+        .is_err()
+        {
+            eprintln!("[!] Failed to write webhook restored template file.");
+            return Err(process::ExitCode::from(
+                defaults::exit_codes::FAILED_TO_WRITE_WEBHOOK_RESTORED_TEMPLATE,
+            ));
+        }
+
         println!(
             "Configuration and resources written successfully to {}.",
             settings.paths.config_dir.display()
         );
+
+        // Carry on to the test send with the settings just saved.
+        if cli.test {
+            return Ok(settings);
+        }
+
         return Err(process::ExitCode::SUCCESS);
     }
 
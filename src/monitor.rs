@@ -0,0 +1,610 @@
+//! Qualification logic of the monitor loop.
+//!
+//! This module defines the `Monitor` struct, which keeps track of how long the
+//! GPIO pin has been at its current level and decides whether a reading has
+//! qualified as a valid change, based on the configured hold durations or,
+//! for noisy inputs, on how many of the most recent readings were HIGH.
+//!
+//! It is kept free of any actual GPIO access and sleeping, so that it can be
+//! driven by timestamps of our own choosing in tests.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::duration::{DurationFormat, format_duration};
+use crate::gpio::Level;
+
+/// State of the monitor loop, tracking since when the pin has been HIGH or LOW.
+pub struct Monitor {
+    /// Time the pin must be HIGH before qualifying as an alarm.
+    qualify_high_hold: Duration,
+
+    /// Time the pin must be LOW before qualifying as restored.
+    qualify_low_hold: Duration,
+
+    /// The timestamp of the first LOW reading since the last qualified HIGH.
+    low_since: Option<Instant>,
+
+    /// The timestamp of the first HIGH reading since the last qualified LOW.
+    high_since: Option<Instant>,
+
+    /// Whether an alarm notification has been sent, which is a prerequisite
+    /// for sending restored notifications.
+    seen_high: bool,
+
+    /// Whether a HIGH first reading qualifies immediately, without waiting out the hold.
+    startup_alarm: bool,
+
+    /// Whether the pin has been read yet.
+    polled: bool,
+
+    /// Window of recent readings, if qualifying M-of-N rather than by holds.
+    window: Option<Window>,
+}
+
+/// Sliding window of the most recent readings, for qualifying a level once
+/// it has been read at least `m` times out of the last `n`.
+struct Window {
+    /// Number of readings of a level needed to qualify it.
+    m: u32,
+
+    /// Size of the window, at most 64.
+    n: u32,
+
+    /// One bit per reading in the window, set if it was HIGH, newest lowest.
+    highs: u64,
+
+    /// Number of readings in the window so far.
+    len: u32,
+}
+
+impl Window {
+    /// Adds a reading, dropping the oldest one if the window is full.
+    fn push(&mut self, level: Level) {
+        let mask = u64::MAX >> (64 - self.n);
+        self.highs = ((self.highs << 1) | u64::from(level == Level::High)) & mask;
+        self.len = (self.len + 1).min(self.n);
+    }
+
+    /// Returns the level read at least `m` times in the window, if any,
+    /// preferring HIGH if both were.
+    fn qualified(&self) -> Option<Level> {
+        let highs = self.highs.count_ones();
+
+        if highs >= self.m {
+            Some(Level::High)
+        } else if self.len - highs >= self.m {
+            Some(Level::Low)
+        } else {
+            None
+        }
+    }
+}
+
+impl Monitor {
+    /// Creates a new `Monitor` with the passed hold durations.
+    ///
+    /// A hold of zero means a level qualifies on the very first reading of it.
+    pub fn new(qualify_high_hold: Duration, qualify_low_hold: Duration) -> Self {
+        Self {
+            qualify_high_hold,
+            qualify_low_hold,
+            low_since: None,
+            high_since: None,
+            seen_high: false,
+            startup_alarm: false,
+            polled: false,
+            window: None,
+        }
+    }
+
+    /// Qualifies levels once they have been read `m` times out of the last `n`,
+    /// if passed, instead of after the hold durations.
+    ///
+    /// `n` is clamped to between 1 and 64, and `m` to between 1 and `n`, so
+    /// that an invalid configuration can't make the window misbehave.
+    pub fn with_m_of_n(mut self, m_of_n: Option<(u32, u32)>) -> Self {
+        self.window = m_of_n.map(|(m, n)| {
+            let n = n.clamp(1, 64);
+
+            Window {
+                m: m.clamp(1, n),
+                n,
+                highs: 0,
+                len: 0,
+            }
+        });
+        self
+    }
+
+    /// Makes a HIGH first reading qualify immediately, for when the burner
+    /// was already in an error state before the monitor started.
+    pub fn with_startup_alarm(mut self, startup_alarm: bool) -> Self {
+        self.startup_alarm = startup_alarm;
+        self
+    }
+
+    /// Changes the holds, keeping the state of readings so far.
+    pub fn set_holds(&mut self, qualify_high_hold: Duration, qualify_low_hold: Duration) {
+        self.qualify_high_hold = qualify_high_hold;
+        self.qualify_low_hold = qualify_low_hold;
+    }
+
+    /// Registers a reading of the pin taken at `now`, returning the level
+    /// if it has qualified and notifications should be dispatched for it.
+    pub fn poll(&mut self, level: Level, now: Instant) -> Option<Level> {
+        let first = !self.polled;
+        self.polled = true;
+
+        if let Some(window) = &mut self.window {
+            window.push(level);
+
+            match level {
+                Level::Low => self.low_since.get_or_insert(now),
+                Level::High => self.high_since.get_or_insert(now),
+            };
+
+            let qualified = if first && self.startup_alarm && level == Level::High {
+                Some(Level::High)
+            } else {
+                window.qualified()
+            };
+
+            // As with holds, the other level stops being tracked once a
+            // reading of the qualified level confirms it; a glitch doesn't.
+            return match qualified {
+                Some(Level::Low) if self.seen_high => {
+                    if level == Level::Low {
+                        self.high_since = None;
+                    }
+                    Some(Level::Low)
+                }
+                Some(Level::High) => {
+                    if level == Level::High {
+                        self.low_since = None;
+                    }
+                    Some(Level::High)
+                }
+                _ => None,
+            };
+        }
+
+        match level {
+            Level::Low => {
+                let start = *self.low_since.get_or_insert(now);
+
+                if !has_held(start, now, self.qualify_low_hold) || !self.seen_high {
+                    return None;
+                }
+
+                self.high_since = None;
+            }
+            Level::High => {
+                let start = *self.high_since.get_or_insert(now);
+
+                let startup_alarm = first && self.startup_alarm;
+
+                if !startup_alarm && !has_held(start, now, self.qualify_high_hold) {
+                    return None;
+                }
+
+                self.low_since = None;
+            }
+        }
+
+        Some(level)
+    }
+
+    /// Returns the timestamp of when the pin was first read at the passed level,
+    /// if it is currently being tracked.
+    pub fn since(&self, level: Level) -> Option<Instant> {
+        match level {
+            Level::Low => self.low_since,
+            Level::High => self.high_since,
+        }
+    }
+
+    /// Returns how many of the readings in the M-of-N window were HIGH, and
+    /// how many readings it holds, if qualifying M-of-N.
+    pub fn window_highs(&self) -> Option<(u32, u32)> {
+        self.window
+            .as_ref()
+            .map(|window| (window.highs.count_ones(), window.len))
+    }
+
+    /// Records that an alarm notification has been sent, allowing
+    /// restored notifications to be sent once the pin goes LOW.
+    pub fn mark_alarm_notified(&mut self) {
+        self.seen_high = true;
+    }
+}
+
+/// Counters of what happened during a run of the monitor loop.
+#[derive(Default)]
+pub struct Summary {
+    /// Number of times the pin was read.
+    pub polls: u64,
+
+    /// Number of alarm notifications successfully sent.
+    pub alarms_sent: u64,
+
+    /// Number of restored notifications successfully sent.
+    pub restores_sent: u64,
+
+    /// Number of failed notification attempts.
+    pub failures: u64,
+
+    /// Number of failed pin reads.
+    pub read_errors: u64,
+}
+
+impl Summary {
+    /// Prints the summary in a human-readable format.
+    pub fn print(&self, elapsed: Duration, duration_format: DurationFormat) {
+        println!(
+            "Ran for {}.",
+            format_duration(Duration::from_secs(elapsed.as_secs()), duration_format)
+        );
+        println!("Polls                        {}", self.polls);
+        println!("Alarm notifications sent     {}", self.alarms_sent);
+        println!("Restored notifications sent  {}", self.restores_sent);
+        println!("Failed notification attempts {}", self.failures);
+        println!("Failed pin reads             {}", self.read_errors);
+    }
+}
+
+/// Tracking of a run of consecutive failed pin reads.
+#[derive(Default)]
+pub struct ReadErrors {
+    /// The timestamp of the first failed read of the current run.
+    since: Option<Instant>,
+
+    /// Number of failed reads in the current run.
+    count: u64,
+
+    /// The timestamp of when a failed read was last logged.
+    last_logged: Option<Instant>,
+
+    /// Whether an alert has been sent for the current run.
+    alerted: bool,
+}
+
+impl ReadErrors {
+    /// Registers a failed read at `now`, returning whether it should be logged,
+    /// which it should at most once per `log_interval`.
+    pub fn record(&mut self, now: Instant, log_interval: Duration) -> bool {
+        self.since.get_or_insert(now);
+        self.count += 1;
+
+        match self.last_logged {
+            Some(t) if now.saturating_duration_since(t) < log_interval => false,
+            _ => {
+                self.last_logged = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Returns whether reads have been failing for at least `timeout` at `now`
+    /// and no alert has been sent for it yet, in which case one is assumed to be.
+    pub fn should_alert(&mut self, now: Instant, timeout: Duration) -> bool {
+        let Some(since) = self.since else {
+            return false;
+        };
+
+        if self.alerted || !has_held(since, now, timeout) {
+            return false;
+        }
+
+        self.alerted = true;
+        true
+    }
+
+    /// Registers a successful read, ending the current run of failed reads.
+    ///
+    /// Returns the number of failed reads and whether an alert was sent for
+    /// them, if there were any.
+    pub fn clear(&mut self) -> Option<(u64, bool)> {
+        let ended = (self.count > 0).then_some((self.count, self.alerted));
+        *self = Self::default();
+        ended
+    }
+
+    /// Returns the timestamp of the first failed read of the current run, if any.
+    pub fn since(&self) -> Option<Instant> {
+        self.since
+    }
+
+    /// Returns the number of failed reads in the current run.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// A change in whether the pin is flapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlapEvent {
+    /// The pin changed level too often within the flap window.
+    Started,
+
+    /// The pin has held its level for a full flap window since it started flapping.
+    Settled,
+}
+
+/// Detects a pin flapping between HIGH and LOW, by counting its level changes
+/// within a sliding window.
+pub struct FlapDetector {
+    /// Number of level changes within the window above which the pin is flapping.
+    threshold: usize,
+
+    /// Window within which level changes are counted.
+    window: Duration,
+
+    /// Timestamps of the most recent level changes, at most `threshold + 1` of them.
+    transitions: VecDeque<Instant>,
+
+    /// The level last recorded.
+    last: Option<Level>,
+
+    /// Whether the pin is currently considered flapping.
+    flapping: bool,
+}
+
+impl FlapDetector {
+    /// Creates a new `FlapDetector` considering more than `threshold` level
+    /// changes within `window` to be flapping.
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            transitions: VecDeque::with_capacity(threshold + 1),
+            last: None,
+            flapping: false,
+        }
+    }
+
+    /// Returns whether the pin is currently considered flapping.
+    pub fn is_flapping(&self) -> bool {
+        self.flapping
+    }
+
+    /// Records a reading at `now`, returning whether the pin started flapping
+    /// or settled because of it.
+    pub fn record(&mut self, level: Level, now: Instant) -> Option<FlapEvent> {
+        let changed = self.last.is_some_and(|last| last != level);
+        self.last = Some(level);
+
+        if changed {
+            if self.transitions.len() > self.threshold {
+                self.transitions.pop_front();
+            }
+
+            self.transitions.push_back(now);
+        }
+
+        while let Some(&t) = self.transitions.front()
+            && now.saturating_duration_since(t) >= self.window
+        {
+            self.transitions.pop_front();
+        }
+
+        if !self.flapping && self.transitions.len() > self.threshold {
+            self.flapping = true;
+            return Some(FlapEvent::Started);
+        }
+
+        if self.flapping && self.transitions.is_empty() {
+            self.flapping = false;
+            return Some(FlapEvent::Settled);
+        }
+
+        None
+    }
+}
+
+/// Holds back a restore for a window after it qualifies, so that an alarm
+/// qualifying again within it can be told apart as a brief dropout, and
+/// neither the restore nor the renewed alarm notified.
+pub struct Coalescer {
+    /// Window after a restore within which a renewed alarm is coalesced.
+    window: Duration,
+
+    /// When the restore being held back qualified.
+    restored_at: Option<Instant>,
+}
+
+impl Coalescer {
+    /// Creates a new `Coalescer` holding back restores for `window`.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            restored_at: None,
+        }
+    }
+
+    /// Records that a restore qualified at `now`, holding it back.
+    pub fn restored(&mut self, now: Instant) {
+        self.restored_at = Some(now);
+    }
+
+    /// Returns whether the restore is still being held back at `now`.
+    pub fn holds_restore(&self, now: Instant) -> bool {
+        self.restored_at
+            .is_some_and(|t| now.saturating_duration_since(t) < self.window)
+    }
+
+    /// Records that an alarm qualified at `now`, returning how long the
+    /// restore before it lasted if it was within the window, in which case
+    /// the two are coalesced.
+    pub fn realarmed(&mut self, now: Instant) -> Option<Duration> {
+        let restored_at = self.restored_at.take()?;
+        let dropout = now.saturating_duration_since(restored_at);
+        (dropout < self.window).then_some(dropout)
+    }
+}
+
+/// Returns whether a level first read at `since` has been held for at least
+/// `hold` at `now`. A zero `hold` is always considered held.
+fn has_held(since: Instant, now: Instant, hold: Duration) -> bool {
+    now.saturating_duration_since(since) >= hold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coalescer() {
+        let window = Duration::from_secs(60);
+        let mut coalescer = Coalescer::new(window);
+        let t0 = Instant::now();
+
+        assert!(!coalescer.holds_restore(t0));
+        assert_eq!(coalescer.realarmed(t0), None);
+
+        // A renewed alarm within the window is a dropout.
+        coalescer.restored(t0);
+        assert!(coalescer.holds_restore(t0 + Duration::from_secs(30)));
+        assert_eq!(
+            coalescer.realarmed(t0 + Duration::from_secs(30)),
+            Some(Duration::from_secs(30))
+        );
+        assert!(!coalescer.holds_restore(t0 + Duration::from_secs(30)));
+
+        // One after it follows a restore that was let through.
+        coalescer.restored(t0);
+        assert!(!coalescer.holds_restore(t0 + window));
+        assert_eq!(coalescer.realarmed(t0 + window), None);
+    }
+
+    #[test]
+    fn test_zero_hold_qualifies_on_first_high_poll() {
+        let mut monitor = Monitor::new(Duration::ZERO, Duration::ZERO);
+        let now = Instant::now();
+
+        assert_eq!(monitor.poll(Level::High, now), Some(Level::High));
+    }
+
+    #[test]
+    fn test_hold_qualifies_after_duration() {
+        let hold = Duration::from_secs(10);
+        let mut monitor = Monitor::new(hold, hold);
+        let t0 = Instant::now();
+
+        assert_eq!(monitor.poll(Level::High, t0), None);
+        assert_eq!(monitor.poll(Level::High, t0 + Duration::from_secs(9)), None);
+        assert_eq!(monitor.poll(Level::High, t0 + hold), Some(Level::High));
+    }
+
+    #[test]
+    fn test_startup_alarm_only_skips_hold_on_first_poll() {
+        let hold = Duration::from_secs(10);
+        let t0 = Instant::now();
+
+        let mut monitor = Monitor::new(hold, hold).with_startup_alarm(true);
+        assert_eq!(monitor.poll(Level::High, t0), Some(Level::High));
+
+        let mut monitor = Monitor::new(hold, hold).with_startup_alarm(true);
+        assert_eq!(monitor.poll(Level::Low, t0), None);
+        assert_eq!(monitor.poll(Level::High, t0), None);
+        assert_eq!(monitor.poll(Level::High, t0 + hold), Some(Level::High));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let log_interval = Duration::from_secs(60);
+        let timeout = Duration::from_secs(300);
+        let t0 = Instant::now();
+        let mut errors = ReadErrors::default();
+
+        assert!(errors.record(t0, log_interval));
+        assert!(!errors.record(t0 + Duration::from_secs(30), log_interval));
+        assert!(errors.record(t0 + log_interval, log_interval));
+        assert!(!errors.should_alert(t0 + log_interval, timeout));
+
+        assert!(errors.should_alert(t0 + timeout, timeout));
+        assert!(!errors.should_alert(t0 + timeout * 2, timeout));
+
+        assert_eq!(errors.count(), 3);
+        assert_eq!(errors.clear(), Some((3, true)));
+        assert_eq!(errors.clear(), None);
+    }
+
+    #[test]
+    fn test_m_of_n_tolerates_noise() {
+        let mut monitor = Monitor::new(Duration::from_secs(10), Duration::from_secs(10))
+            .with_m_of_n(Some((3, 5)));
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        assert_eq!(monitor.poll(Level::High, at(0)), None);
+        assert_eq!(monitor.poll(Level::Low, at(1)), None);
+        assert_eq!(monitor.poll(Level::High, at(2)), None);
+        assert_eq!(monitor.poll(Level::High, at(3)), Some(Level::High));
+        assert_eq!(monitor.since(Level::High), Some(at(0)));
+
+        // A single LOW glitch doesn't end the alarm.
+        monitor.mark_alarm_notified();
+        assert_eq!(monitor.poll(Level::Low, at(4)), Some(Level::High));
+
+        // Three LOWs out of the last five restores it.
+        assert_eq!(monitor.poll(Level::Low, at(5)), Some(Level::Low));
+        assert_eq!(monitor.since(Level::Low), Some(at(4)));
+        assert_eq!(monitor.since(Level::High), None);
+    }
+
+    #[test]
+    fn test_m_of_n_out_of_range() {
+        let t0 = Instant::now();
+
+        // Clamped to 1-of-1 rather than panicking.
+        let mut monitor = Monitor::new(Duration::ZERO, Duration::ZERO).with_m_of_n(Some((0, 0)));
+        assert_eq!(monitor.poll(Level::High, t0), Some(Level::High));
+
+        for (m, n) in [(64, 64), (80, 100)] {
+            let mut monitor =
+                Monitor::new(Duration::ZERO, Duration::ZERO).with_m_of_n(Some((m, n)));
+
+            for _ in 0..63 {
+                assert_eq!(monitor.poll(Level::High, t0), None);
+            }
+
+            assert_eq!(monitor.poll(Level::High, t0), Some(Level::High));
+        }
+    }
+
+    #[test]
+    fn test_low_requires_seen_high() {
+        let mut monitor = Monitor::new(Duration::ZERO, Duration::ZERO);
+        let now = Instant::now();
+
+        assert_eq!(monitor.poll(Level::Low, now), None);
+
+        monitor.mark_alarm_notified();
+        assert_eq!(monitor.poll(Level::Low, now), Some(Level::Low));
+    }
+
+    #[test]
+    fn test_flap_detector_starts_and_settles() {
+        let window = Duration::from_secs(60);
+        let mut flap = FlapDetector::new(2, window);
+        let t0 = Instant::now();
+        let secs = |n| t0 + Duration::from_secs(n);
+
+        assert_eq!(flap.record(Level::Low, t0), None);
+        assert_eq!(flap.record(Level::High, secs(1)), None);
+        assert_eq!(flap.record(Level::Low, secs(2)), None);
+        assert_eq!(flap.record(Level::High, secs(3)), Some(FlapEvent::Started));
+        assert!(flap.is_flapping());
+
+        assert_eq!(flap.record(Level::Low, secs(4)), None);
+        assert_eq!(flap.record(Level::Low, secs(63)), None);
+        assert_eq!(flap.record(Level::Low, secs(64)), Some(FlapEvent::Settled));
+        assert!(!flap.is_flapping());
+
+        // Changes spread out over more than the window are not flapping.
+        assert_eq!(flap.record(Level::High, secs(100)), None);
+        assert_eq!(flap.record(Level::Low, secs(170)), None);
+        assert_eq!(flap.record(Level::High, secs(240)), None);
+    }
+}
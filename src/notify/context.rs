@@ -1,7 +1,13 @@
-use rppal::gpio::Level;
 use std::time::Instant;
 
+#[cfg(feature = "notifications")]
+use crate::defaults;
+use crate::duration::DurationFormat;
+use crate::gpio::Level;
+use crate::settings::{Settings, Severity};
+
 /// Context for sending notifications, containing the current GPIO level and timestamp.
+#[derive(Clone)]
 pub struct Context {
     /// The current GPIO level (High or Low) that triggered the notification.
     pub level: Level,
@@ -9,4 +15,87 @@ pub struct Context {
     /// The current timestamp when the notification is being processed,
     /// used for timing logic in the notifiers.
     pub now: Instant,
+
+    /// The timestamp of when the pin was first read at the current level,
+    /// which `{since}` counts from. It stays the same across repeats and
+    /// retries of the notification.
+    pub since: Instant,
+
+    /// The GPIO pin number being monitored.
+    pub pin_number: u8,
+
+    /// Friendly name of the GPIO pin, or `GPIO<n>` if it has none.
+    pub pin_name: String,
+
+    /// How durations are formatted in the message, e.g. `{since}`.
+    pub duration_format: DurationFormat,
+
+    /// Summary of the key settings for `{config_summary}`, or empty if it
+    /// should not be included.
+    pub config_summary: String,
+
+    /// Recent alarms and restores to append to the message, one per line,
+    /// or empty if none should be.
+    pub log_tail: String,
+
+    /// How urgent alarms of the pin are, for `{severity}`.
+    pub severity: Severity,
+}
+
+impl Context {
+    /// Creates a context for the monitored pin of the passed settings, at
+    /// the passed level since `since`. The config summary is included if
+    /// the settings ask for it, and there is no log tail.
+    pub fn new(settings: &Settings, level: Level, now: Instant, since: Instant) -> Self {
+        Self {
+            level,
+            now,
+            since,
+            pin_number: settings.gpio.pin_number,
+            pin_name: settings.pin_name(settings.gpio.pin_number),
+            duration_format: settings.duration_format,
+            config_summary: if settings.notifications.include_config_in_notification {
+                settings.config_summary()
+            } else {
+                String::new()
+            },
+            log_tail: String::new(),
+            severity: settings.gpio.severity,
+        }
+    }
+
+    /// Creates a context for the default pin at the passed level since
+    /// `now`, for rendering templates outside of a run, such as to check
+    /// that they render to valid JSON.
+    #[cfg(feature = "notifications")]
+    pub fn example(level: Level, now: Instant) -> Self {
+        Self {
+            level,
+            now,
+            since: now,
+            pin_number: defaults::gpio::PIN_NUMBER,
+            pin_name: format!("GPIO{}", defaults::gpio::PIN_NUMBER),
+            duration_format: DurationFormat::Compact,
+            config_summary: String::new(),
+            log_tail: String::new(),
+            severity: Severity::Critical,
+        }
+    }
+
+    /// Creates a context for tests, of pin 24 named `boiler` at the passed
+    /// level since `now`.
+    #[cfg(test)]
+    pub fn for_test(level: Level, now: Instant) -> Self {
+        Self {
+            level,
+            now,
+            since: now,
+            pin_number: 24,
+            pin_name: "boiler".to_string(),
+            duration_format: DurationFormat::Compact,
+            config_summary: String::new(),
+            log_tail: String::new(),
+            severity: Severity::Critical,
+        }
+    }
 }
@@ -0,0 +1,235 @@
+//! Ordered failover between notifiers, for primary and backup endpoints.
+//!
+//! Rather than sending every notification through every notifier, the
+//! notifiers are tried in the order given until one of them succeeds. A
+//! notifier that failed is skipped over for a while, so that a primary
+//! known to be down doesn't delay every send until its request times out.
+
+use std::time::{Duration, Instant};
+
+use crate::gpio::Level;
+use crate::history::Receipt;
+use crate::notify::{Context, NotificationResult, Notifier};
+
+/// Notifier trying its tiers in order, falling through to the next only when
+/// the one before it fails.
+pub struct FailoverNotifier {
+    /// Name of the group, used in log output and reports.
+    name: String,
+
+    /// The notifiers to try, in order of preference.
+    tiers: Vec<Box<dyn Notifier>>,
+
+    /// When each tier last failed, if it did so within the health window.
+    failed_at: Vec<Option<Instant>>,
+
+    /// How long a tier that failed is skipped over.
+    health_window: Duration,
+
+    /// The tier tried last, for its receipt.
+    last_tried: Option<usize>,
+}
+
+impl FailoverNotifier {
+    /// Creates a new `FailoverNotifier` trying the passed notifiers in order.
+    pub fn new(name: &str, tiers: Vec<Box<dyn Notifier>>, health_window: Duration) -> Self {
+        Self {
+            name: name.to_owned(),
+            failed_at: vec![None; tiers.len()],
+            tiers,
+            health_window,
+            last_tried: None,
+        }
+    }
+
+    /// Returns the indices of the tiers in the order they should be tried at
+    /// `now`: healthy tiers first, then those that failed recently, each in
+    /// the order given. Unhealthy tiers are still tried as a last resort.
+    fn order(&self, now: Instant) -> Vec<usize> {
+        let (healthy, unhealthy): (Vec<usize>, Vec<usize>) =
+            (0..self.tiers.len()).partition(|&i| match self.failed_at[i] {
+                Some(t) => now.saturating_duration_since(t) >= self.health_window,
+                None => true,
+            });
+
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+
+    /// Tries the tiers in order with the passed send, until one of them
+    /// doesn't fail, returning its index and result.
+    ///
+    /// If all of them fail, the failures are joined into one.
+    fn try_in_order(
+        &mut self,
+        now: Instant,
+        mut send: impl FnMut(&mut dyn Notifier) -> NotificationResult,
+    ) -> (Option<usize>, NotificationResult) {
+        let mut errors = Vec::new();
+
+        for i in self.order(now) {
+            let result = send(self.tiers[i].as_mut());
+
+            if matches!(
+                result,
+                NotificationResult::Success | NotificationResult::Failure(_)
+            ) {
+                self.last_tried = Some(i);
+            }
+
+            match result {
+                NotificationResult::Failure(e) => {
+                    self.failed_at[i] = Some(now);
+                    errors.push(format!("{}: {e}", self.tiers[i].name()));
+                }
+                result => {
+                    if matches!(result, NotificationResult::Success) {
+                        self.failed_at[i] = None;
+
+                        if i > 0 {
+                            println!(
+                                "[{}] Delivered by tier {} ({}).",
+                                self.name,
+                                i + 1,
+                                self.tiers[i].name()
+                            );
+                        }
+                    }
+
+                    return (Some(i), result);
+                }
+            }
+        }
+
+        (None, NotificationResult::Failure(errors.join("; ")))
+    }
+}
+
+impl Notifier for FailoverNotifier {
+    /// Returns the name of the group.
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Sends a notification through the first tier that succeeds, and records
+    /// it as sent in the others so they keep to the same schedule.
+    fn send_notification(&mut self, ctx: &Context) -> NotificationResult {
+        let (delivered_by, result) = self.try_in_order(ctx.now, |n| n.send_notification(ctx));
+
+        if let Some(i) = delivered_by
+            && matches!(result, NotificationResult::Success)
+        {
+            for (j, n) in self.tiers.iter_mut().enumerate() {
+                if j != i {
+                    n.mark_sent(ctx.level, ctx.now);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Sends a one-off notice through the first tier that succeeds.
+    fn send_oneshot(&mut self, ctx: &Context, subject: &str, body: &str) -> NotificationResult {
+        self.try_in_order(ctx.now, |n| n.send_oneshot(ctx, subject, body))
+            .1
+    }
+
+    /// Sends the alarm message as a test through the first tier that succeeds.
+    fn send_test(&mut self, ctx: &Context) -> NotificationResult {
+        self.try_in_order(ctx.now, |n| n.send_test(ctx)).1
+    }
+
+    /// Records a notification for the passed level as sent in every tier.
+    fn mark_sent(&mut self, level: Level, now: Instant) {
+        for n in self.tiers.iter_mut() {
+            n.mark_sent(level, now);
+        }
+    }
+
+    /// Returns the receipt of the last tier tried.
+    fn last_receipt(&self) -> Option<Receipt> {
+        self.tiers[self.last_tried?].last_receipt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use httpmock::prelude::*;
+    use reqwest::blocking::Client;
+
+    use super::*;
+    use crate::backend::TEST_HTTP_OPTIONS;
+    use crate::backend::batsign::BatsignBackend;
+    use crate::notify::TwoLevelNotifier;
+    use crate::settings::BatsignRequestStyle;
+
+    const WINDOW: Duration = Duration::from_secs(15 * 60);
+
+    /// Creates a Batsign notifier pointing at the passed mock server path.
+    fn tier(server: &MockServer, index: usize, path: &str) -> Box<dyn Notifier> {
+        let backend = BatsignBackend::new(
+            index,
+            Arc::new(Client::new()),
+            &server.url(path),
+            BatsignRequestStyle::RawSubject,
+            TEST_HTTP_OPTIONS,
+        );
+
+        Box::new(TwoLevelNotifier::new(
+            backend,
+            Some(Duration::from_secs(6 * 3600)),
+            Duration::from_secs(5 * 60),
+            "alarm",
+            "restored",
+            false,
+        ))
+    }
+
+    #[test]
+    fn test_falls_through_and_skips_failed_primary() {
+        let server = MockServer::start();
+        let primary = server.mock(|when, then| {
+            when.method(POST).path("/at/primary@example.com/token");
+            then.status(503);
+        });
+        let backup = server.mock(|when, then| {
+            when.method(POST).path("/at/backup@example.com/token");
+            then.status(200);
+        });
+
+        let tiers = vec![
+            tier(&server, 0, "/at/primary@example.com/token"),
+            tier(&server, 1, "/at/backup@example.com/token"),
+        ];
+        let mut notifier = FailoverNotifier::new("batsign", tiers, WINDOW);
+        let t0 = Instant::now();
+
+        let result = notifier.send_notification(&Context::for_test(Level::High, t0));
+        assert!(matches!(result, NotificationResult::Success));
+        primary.assert_calls(1);
+        backup.assert_calls(1);
+
+        // Both tiers keep to the schedule of the one that delivered.
+        let result = notifier.send_notification(&Context::for_test(Level::High, t0 + WINDOW));
+        assert!(matches!(result, NotificationResult::NotYetTime));
+
+        // The primary failed recently, so the backup is tried first.
+        let t1 = t0 + Duration::from_secs(60);
+        let result = notifier.send_notification(&Context::for_test(Level::Low, t1));
+        assert!(matches!(result, NotificationResult::Success));
+        primary.assert_calls(1);
+        backup.assert_calls(2);
+
+        // Once the window has passed, the primary is tried first again.
+        let result = notifier.send_oneshot(
+            &Context::for_test(Level::Low, t0 + WINDOW),
+            "subject",
+            "body",
+        );
+        assert!(matches!(result, NotificationResult::Success));
+        primary.assert_calls(2);
+        backup.assert_calls(3);
+    }
+}
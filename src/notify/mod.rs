@@ -1,12 +1,18 @@
 pub mod context;
+#[cfg(feature = "notifications")]
+pub mod failover;
 pub mod result;
 pub mod state;
 pub mod traits;
 pub mod two_level;
+pub mod worker;
 
 pub use context::Context;
+#[cfg(feature = "notifications")]
+pub use failover::FailoverNotifier;
 pub use result::NotificationResult;
 pub use traits::Notifier;
 pub use two_level::TwoLevelNotifier;
+pub use worker::SendWorker;
 
 use state::LevelNotifier;
@@ -13,4 +13,11 @@ pub enum NotificationResult {
 
     /// Indicates that the notification failed.
     Failure(String),
+
+    /// Indicates that the notifier does not send this kind of notification.
+    Skipped,
+
+    /// Indicates that the notification was identical to the last one sent
+    /// within the dedupe window, and so was not sent again.
+    Deduplicated,
 }
@@ -1,13 +1,8 @@
-use rppal::gpio::Level;
 use std::time::{Duration, Instant};
 
 /// Internal struct to track the state of notifications for a specific GPIO level,
 /// including timing for repeats and retries.
 pub struct LevelNotifier {
-    /// The GPIO level that this notifier is responsible for
-    /// (e.g., High for alarm, Low for restored).
-    pub level: Level,
-
     /// The message template to use when building the notification message for this level.
     pub message_template: String,
 
@@ -29,14 +24,8 @@ pub struct LevelNotifier {
 
 impl LevelNotifier {
     /// Creates a new `LevelNotifier`.
-    pub fn new(
-        level: Level,
-        message_template: &str,
-        repeat: Option<Duration>,
-        retry: Duration,
-    ) -> Self {
+    pub fn new(message_template: &str, repeat: Option<Duration>, retry: Duration) -> Self {
         Self {
-            level,
             message_template: message_template.to_string(),
             last_sent: None,
             last_failed: None,
@@ -48,9 +37,12 @@ impl LevelNotifier {
     /// Determines whether a notification should be sent at the current time,
     /// based on the last sent and failed timestamps, as well as the configured
     /// repeat and retry intervals.
+    ///
+    /// A `now` that is earlier than a recorded timestamp is treated as no time
+    /// having passed, rather than panicking or wrapping around.
     pub fn should_send_now(&self, now: Instant) -> bool {
         if let Some(t) = self.last_failed
-            && now.duration_since(t) < self.retry_interval
+            && now.saturating_duration_since(t) < self.retry_interval
         {
             return false;
         }
@@ -58,7 +50,7 @@ impl LevelNotifier {
         match (self.last_sent, self.repeat_interval) {
             (None, _) => true,
             (Some(_), None) => false,
-            (Some(t), Some(iv)) => now.duration_since(t) >= iv,
+            (Some(t), Some(iv)) => now.saturating_duration_since(t) >= iv,
         }
     }
 
@@ -80,3 +72,24 @@ impl LevelNotifier {
         self.last_failed = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps_in_the_future_do_not_qualify() {
+        let retry = Duration::from_secs(60);
+        let repeat = Duration::from_secs(3600);
+        let mut notifier = LevelNotifier::new("", Some(repeat), retry);
+        let t0 = Instant::now() + Duration::from_secs(10);
+
+        notifier.record_failure(t0);
+        assert!(!notifier.should_send_now(t0 - Duration::from_secs(5)));
+        assert!(notifier.should_send_now(t0 + retry));
+
+        notifier.record_success(t0);
+        assert!(!notifier.should_send_now(t0 - Duration::from_secs(5)));
+        assert!(notifier.should_send_now(t0 + repeat));
+    }
+}
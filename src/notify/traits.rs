@@ -1,12 +1,33 @@
+use std::time::Instant;
+
+use crate::gpio::Level;
+use crate::history::Receipt;
 use crate::notify::Context;
 use crate::notify::NotificationResult;
 
 /// Defines the `Notifier` trait.
-pub trait Notifier {
+///
+/// Notifiers are `Send`, so that they can be handed to the send worker.
+pub trait Notifier: Send {
     /// Returns the name of the notifier, which is typically the name of the backend
     /// it uses (e.g., "slack" or "batsign") plus potentially any other identifier.
     fn name(&self) -> String;
 
     /// Sends a notification.
     fn send_notification(&mut self, ctx: &Context) -> NotificationResult;
+
+    /// Sends a one-off notice with the passed subject and body, outside of the
+    /// alarm and restored state tracking, so it is neither repeated nor retried.
+    fn send_oneshot(&mut self, ctx: &Context, subject: &str, body: &str) -> NotificationResult;
+
+    /// Sends the alarm message once as a test, leaving the alarm and restored
+    /// state untouched.
+    fn send_test(&mut self, ctx: &Context) -> NotificationResult;
+
+    /// Records a notification for the passed level as sent at `now` without
+    /// sending it, for carrying the state over to a rebuilt notifier.
+    fn mark_sent(&mut self, level: Level, now: Instant);
+
+    /// Returns the receipt of the last delivery attempt, if one was made.
+    fn last_receipt(&self) -> Option<Receipt>;
 }
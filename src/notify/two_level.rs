@@ -1,11 +1,32 @@
-use rppal::gpio::Level;
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
 
 use crate::backend::Backend;
+use crate::gpio::Level;
+use crate::history::Receipt;
 use crate::notify::Context;
 use crate::notify::LevelNotifier;
 use crate::notify::NotificationResult;
 use crate::notify::Notifier;
+use crate::template;
+
+/// A message that was rendered but not yet delivered, kept so that it can be
+/// re-sent on retry with the level and trigger time it was rendered with.
+struct QueuedMessage {
+    /// The context the message was last rendered with.
+    ctx: Context,
+
+    /// The built message, as passed to the backend.
+    message: String,
+
+    /// Hash of the message body, for deduplication.
+    hash: u64,
+
+    /// Number of attempts at sending the message so far.
+    attempts: u32,
+}
 
 /// A notifier that manages two levels of notifications (alarm and restored)
 /// using a specified backend, handling the logic for when to send notifications
@@ -22,6 +43,23 @@ pub struct TwoLevelNotifier<B: Backend> {
 
     /// Indicates whether the notifier should operate in dry run mode.
     dry_run: bool,
+
+    /// Maximum size of a message body in bytes, if it should be limited.
+    max_message_bytes: Option<usize>,
+
+    /// Window within which a message identical to the last one sent is
+    /// skipped, if identical messages should be deduplicated.
+    dedupe_window: Option<Duration>,
+
+    /// Hash of the last message body sent and when it was sent.
+    last_sent: Option<(u64, Instant)>,
+
+    /// Messages that failed to send, oldest first, re-sent before anything
+    /// else and drained as they are delivered.
+    pending_sends: VecDeque<QueuedMessage>,
+
+    /// Receipt of the last delivery attempt, if one was made.
+    last_receipt: Option<Receipt>,
 }
 
 impl<B: Backend> Notifier for TwoLevelNotifier<B> {
@@ -35,6 +73,26 @@ impl<B: Backend> Notifier for TwoLevelNotifier<B> {
     fn send_notification(&mut self, ctx: &Context) -> NotificationResult {
         TwoLevelNotifier::send_notification(self, ctx)
     }
+
+    /// Sends a one-off notice, outside of the alarm and restored state tracking.
+    fn send_oneshot(&mut self, ctx: &Context, subject: &str, body: &str) -> NotificationResult {
+        TwoLevelNotifier::send_oneshot(self, ctx, subject, body)
+    }
+
+    /// Sends the alarm message once as a test, outside of the state tracking.
+    fn send_test(&mut self, ctx: &Context) -> NotificationResult {
+        TwoLevelNotifier::send_test(self, ctx)
+    }
+
+    /// Records a notification for the passed level as sent, without sending it.
+    fn mark_sent(&mut self, level: Level, now: Instant) {
+        TwoLevelNotifier::mark_sent(self, level, now)
+    }
+
+    /// Returns the receipt of the last delivery attempt.
+    fn last_receipt(&self) -> Option<Receipt> {
+        self.last_receipt.clone()
+    }
 }
 
 impl<B: Backend> TwoLevelNotifier<B> {
@@ -49,19 +107,108 @@ impl<B: Backend> TwoLevelNotifier<B> {
     ) -> Self {
         Self {
             backend,
-            alarm: LevelNotifier::new(Level::High, alarm_template, repeat_interval, retry_interval),
-            restored: LevelNotifier::new(Level::Low, restored_template, None, retry_interval),
+            alarm: LevelNotifier::new(alarm_template, repeat_interval, retry_interval),
+            restored: LevelNotifier::new(restored_template, None, retry_interval),
             dry_run,
+            max_message_bytes: None,
+            dedupe_window: None,
+            last_sent: None,
+            pending_sends: VecDeque::new(),
+            last_receipt: None,
         }
     }
 
+    /// Limits message bodies to the passed size in bytes, truncating longer ones.
+    pub fn with_max_message_bytes(mut self, max_message_bytes: usize) -> Self {
+        self.max_message_bytes = Some(max_message_bytes);
+        self
+    }
+
+    /// Skips messages identical to the last one sent within the passed window,
+    /// or never if `None`.
+    pub fn with_dedupe_window(mut self, dedupe_window: Option<Duration>) -> Self {
+        self.dedupe_window = dedupe_window;
+        self
+    }
+
     /// Returns the name of the backend used by this notifier.
     pub fn name(&self) -> String {
         self.backend.name()
     }
 
+    /// Sends a one-off notice with the passed subject and body, which may
+    /// contain the same placeholders as the templates.
+    ///
+    /// The alarm and restored state is left untouched, so the notice is
+    /// neither repeated nor retried.
+    pub fn send_oneshot(&mut self, ctx: &Context, subject: &str, body: &str) -> NotificationResult {
+        let subject = template::format_message(subject, ctx);
+        let body = template::format_message(body, ctx);
+
+        let Some(msg) = self.backend.build_oneshot(ctx, &subject, &body) else {
+            return NotificationResult::Skipped;
+        };
+
+        self.deliver(ctx, &msg)
+    }
+
+    /// Sends the alarm message once as a test, to confirm that the backend
+    /// can still be reached.
+    ///
+    /// As with one-off notices, the alarm and restored state is left
+    /// untouched, and the message is neither repeated nor retried.
+    pub fn send_test(&mut self, ctx: &Context) -> NotificationResult {
+        let body = template::format_message(&self.alarm.message_template, ctx);
+        let body = truncate(&self.backend, self.max_message_bytes, body);
+        let msg = self.backend.build_message(ctx, &body);
+        self.deliver(ctx, &msg)
+    }
+
+    /// Records a notification for the passed level as sent at `now`, without
+    /// sending it, so that it isn't sent again until it is due.
+    ///
+    /// Any messages still queued for a retry are dropped, as they have been
+    /// superseded by the one recorded as sent.
+    pub fn mark_sent(&mut self, level: Level, now: Instant) {
+        self.pending_sends.clear();
+
+        let (current, other) = match level {
+            Level::Low => (&mut self.restored, &mut self.alarm),
+            Level::High => (&mut self.alarm, &mut self.restored),
+        };
+
+        current.record_success(now);
+        other.reset();
+    }
+
+    /// Sends an already-built message outside of the state tracking,
+    /// or prints it if in dry run mode.
+    fn deliver(&mut self, ctx: &Context, msg: &str) -> NotificationResult {
+        if self.dry_run {
+            print_dry_run(&self.backend, msg);
+            return NotificationResult::DryRun;
+        }
+
+        let started = Instant::now();
+        let result = self.backend.send_message(ctx, msg);
+        self.last_receipt = Some(receipt(&self.backend, started, 1));
+
+        match result {
+            Ok(()) => NotificationResult::Success,
+            Err(e) => {
+                eprintln!("[!] {} failed: {e}", self.backend.name());
+                NotificationResult::Failure(e)
+            }
+        }
+    }
+
     /// Sends a notification based on the current GPIO level and the
     /// configured backend, while managing timing for repeats and retries.
+    ///
+    /// A message that fails to send is queued and re-sent on retry, rendered
+    /// anew so that `{since}` counts from when the pin changed level to the
+    /// retry, rather than to the first attempt. Queued messages are sent in
+    /// order, ahead of any new one.
     pub fn send_notification(&mut self, ctx: &Context) -> NotificationResult {
         let (current, other) = match ctx.level {
             Level::Low => (&mut self.restored, &mut self.alarm),
@@ -72,28 +219,752 @@ impl<B: Backend> TwoLevelNotifier<B> {
             return NotificationResult::NotYetTime;
         }
 
-        let msg = self
-            .backend
-            .build_message(current.level, &current.message_template);
+        if !self
+            .pending_sends
+            .iter()
+            .any(|queued| queued.ctx.level == ctx.level)
+        {
+            let (message, hash) = render(
+                &self.backend,
+                self.max_message_bytes,
+                &current.message_template,
+                ctx,
+            );
+
+            if self.pending_sends.is_empty()
+                && is_duplicate(self.dedupe_window, self.last_sent, hash, ctx.now)
+            {
+                println!(
+                    "[{}] Skipping message identical to the last one sent.",
+                    self.backend.name()
+                );
+                current.record_success(ctx.now);
+                other.reset();
+                return NotificationResult::Deduplicated;
+            }
+
+            self.pending_sends.push_back(QueuedMessage {
+                ctx: ctx.clone(),
+                message,
+                hash,
+                attempts: 0,
+            });
+        }
+
+        while let Some(queued) = self.pending_sends.front_mut() {
+            if queued.attempts > 0 {
+                let template = if queued.ctx.level == ctx.level {
+                    &current.message_template
+                } else {
+                    &other.message_template
+                };
+
+                queued.ctx.now = ctx.now;
+                (queued.message, queued.hash) =
+                    render(&self.backend, self.max_message_bytes, template, &queued.ctx);
+            }
+
+            if self.dry_run {
+                print_dry_run(&self.backend, &queued.message);
+            } else {
+                queued.attempts += 1;
+
+                let started = Instant::now();
+                let result = self.backend.send_message(&queued.ctx, &queued.message);
+                self.last_receipt = Some(receipt(&self.backend, started, queued.attempts));
+
+                if let Err(e) = result {
+                    eprintln!("[!] {} failed: {e}", self.backend.name());
+                    current.record_failure(ctx.now);
+                    return NotificationResult::Failure(e);
+                }
+            }
+
+            self.last_sent = Some((queued.hash, ctx.now));
+            self.pending_sends.pop_front();
+        }
+
+        current.record_success(ctx.now);
+        other.reset();
 
         if self.dry_run {
-            println!("[{}] DRY RUN:\n{}\n", self.backend.name(), msg);
-            current.record_success(ctx.now);
-            other.reset();
-            return NotificationResult::DryRun;
+            NotificationResult::DryRun
+        } else {
+            NotificationResult::Success
         }
+    }
+}
 
-        match self.backend.send_message(&msg) {
-            Ok(()) => {
-                current.record_success(ctx.now);
-                other.reset();
-                NotificationResult::Success
+/// Renders the passed template into a message for the backend, returning it
+/// along with the hash of its body, for deduplication.
+fn render<B: Backend>(
+    backend: &B,
+    max_message_bytes: Option<usize>,
+    template: &str,
+    ctx: &Context,
+) -> (String, u64) {
+    let body = template::format_message(template, ctx);
+    let body = if backend.appends_log_tail() {
+        template::append_log_tail(body, &ctx.log_tail)
+    } else {
+        body
+    };
+    let body = truncate(backend, max_message_bytes, body);
+    let hash = body_hash(&body);
+    (backend.build_message(ctx, &body), hash)
+}
+
+/// Prints the message that would have been sent through the passed backend.
+/// An empty message, such as a command left unset for a level, is skipped,
+/// as it is when actually sending.
+fn print_dry_run<B: Backend>(backend: &B, msg: &str) {
+    if !msg.trim().is_empty() {
+        println!("[{}] DRY RUN:\n{}\n", backend.name(), msg);
+    }
+}
+
+/// Returns the receipt of a delivery attempt through the passed backend,
+/// started at `started`.
+fn receipt<B: Backend>(backend: &B, started: Instant, attempts: u32) -> Receipt {
+    Receipt {
+        target: backend.target(),
+        status: backend.last_status(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        attempts,
+    }
+}
+
+/// Truncates a message body to the passed size in bytes, if it is limited
+/// and the body is longer.
+fn truncate<B: Backend>(backend: &B, max_message_bytes: Option<usize>, body: String) -> String {
+    let Some(max) = max_message_bytes else {
+        return body;
+    };
+
+    match template::truncate_message(&body, max) {
+        Some(truncated) => {
+            eprintln!(
+                "[!] {}: message is {} bytes; truncating to {max}.",
+                backend.name(),
+                body.len()
+            );
+            truncated
+        }
+        None => body,
+    }
+}
+
+/// Hashes a message body, for comparing it with the last one sent.
+fn body_hash(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns whether the passed body hash matches the last message sent
+/// within the dedupe window, if there is one.
+fn is_duplicate(
+    dedupe_window: Option<Duration>,
+    last_sent: Option<(u64, Instant)>,
+    hash: u64,
+    now: Instant,
+) -> bool {
+    match (dedupe_window, last_sent) {
+        (Some(window), Some((last_hash, at))) => {
+            last_hash == hash && now.saturating_duration_since(at) < window
+        }
+        _ => false,
+    }
+}
+
+#[cfg(all(test, feature = "notifications"))]
+mod tests {
+    use httpmock::prelude::*;
+    use reqwest::blocking::Client;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::backend::batsign::BatsignBackend;
+    use crate::backend::command::CommandBackend;
+    use crate::backend::webhook::WebhookBackend;
+    use crate::backend::{HmacSigner, HttpOptions, TEST_HTTP_OPTIONS};
+    use crate::monitor::Monitor;
+    use crate::settings::{BatsignRequestStyle, HmacAlgorithm};
+
+    const ALARM: &str = "Subject: PellX Alarm\nPellets burner is in an error state.";
+    const RESTORED: &str = "Subject: PellX Restored\nPellets burner has been restored.";
+    const REPEAT: Duration = Duration::from_secs(6 * 3600);
+    const RETRY: Duration = Duration::from_secs(5 * 60);
+
+    /// Creates a Batsign notifier pointing at the passed mock server path.
+    fn batsign_notifier(server: &MockServer, path: &str) -> TwoLevelNotifier<BatsignBackend> {
+        let backend = BatsignBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url(path),
+            BatsignRequestStyle::RawSubject,
+            TEST_HTTP_OPTIONS,
+        );
+        TwoLevelNotifier::new(backend, Some(REPEAT), RETRY, ALARM, RESTORED, false)
+    }
+
+    #[test]
+    fn test_qualified_alarm_sends_exactly_one_post() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body(ALARM);
+            then.status(200);
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+        let mut monitor = Monitor::new(Duration::from_secs(10), Duration::from_secs(10));
+        let t0 = Instant::now();
+
+        // Poll every second for a minute; only one notification should go out
+        // once the level qualifies, since the repeat interval is much longer.
+        for secs in 0..60 {
+            let now = t0 + Duration::from_secs(secs);
+
+            if let Some(level) = monitor.poll(Level::High, now) {
+                notifier.send_notification(&Context::for_test(level, now));
             }
-            Err(e) => {
-                eprintln!("[!] {} failed: {e}", self.backend.name());
-                current.record_failure(ctx.now);
-                NotificationResult::Failure(e)
+        }
+
+        mock.assert_calls(1);
+    }
+
+    #[test]
+    fn test_log_tail_is_left_out_of_commands_and_payloads() {
+        let server = MockServer::start();
+        let payload = r#"{"text": "alarm"}"#;
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/hook").body(payload);
+            then.status(200);
+        });
+
+        let now = Instant::now();
+        let ctx = Context {
+            log_tail: "first\nsecond".to_string(),
+            ..Context::for_test(Level::High, now)
+        };
+
+        let backend = WebhookBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url("/hook"),
+            TEST_HTTP_OPTIONS,
+        );
+        let mut notifier =
+            TwoLevelNotifier::new(backend, Some(REPEAT), RETRY, payload, payload, false);
+        assert!(matches!(
+            notifier.send_notification(&ctx),
+            NotificationResult::Success
+        ));
+        mock.assert_calls(1);
+
+        let mut notifier = TwoLevelNotifier::new(
+            CommandBackend::new(0),
+            Some(REPEAT),
+            RETRY,
+            "true",
+            "true",
+            false,
+        );
+        assert!(matches!(
+            notifier.send_notification(&ctx),
+            NotificationResult::Success
+        ));
+
+        let (message, _) = render(&batsign_notifier(&server, "/").backend, None, ALARM, &ctx);
+        assert!(message.ends_with("\nfirst\nsecond"));
+    }
+
+    #[test]
+    fn test_signed_request_carries_hmac_header() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .header_exists("X-Signature")
+                .body(ALARM);
+            then.status(200);
+        });
+
+        let backend = BatsignBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url("/at/test@example.com/token"),
+            BatsignRequestStyle::RawSubject,
+            TEST_HTTP_OPTIONS,
+        )
+        .with_signer(HmacSigner::new(
+            "It's a Secret to Everybody",
+            HmacAlgorithm::Sha256,
+            "X-Signature",
+        ));
+
+        let mut notifier =
+            TwoLevelNotifier::new(backend, Some(REPEAT), RETRY, ALARM, RESTORED, false);
+
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(Level::High, Instant::now())),
+            NotificationResult::Success
+        ));
+        mock.assert_calls(1);
+    }
+
+    #[test]
+    fn test_server_error_is_retried_after_retry_interval() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(500);
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+        let t0 = Instant::now();
+        let ctx = |now| Context::for_test(Level::High, now);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0)),
+            NotificationResult::Failure(_)
+        ));
+        mock.assert_calls(1);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY - Duration::from_secs(1))),
+            NotificationResult::NotYetTime
+        ));
+        mock.assert_calls(1);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY)),
+            NotificationResult::Failure(_)
+        ));
+        mock.assert_calls(2);
+    }
+
+    #[test]
+    fn test_failure_includes_response_body() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(403).body("Invalid token.\nCheck your URL.\n");
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+
+        let NotificationResult::Failure(e) =
+            notifier.send_notification(&Context::for_test(Level::High, Instant::now()))
+        else {
+            panic!("expected a failure");
+        };
+
+        assert!(e.starts_with("HTTP 403 Forbidden in "));
+        assert!(e.ends_with("ms: Invalid token. Check your URL."));
+    }
+
+    #[test]
+    fn test_retry_timeout_escalates() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(200).delay(Duration::from_millis(500));
+        });
+
+        let options = HttpOptions {
+            retry_timeout_schedule: Some(vec![Duration::from_millis(100), Duration::from_secs(5)]),
+            ..TEST_HTTP_OPTIONS
+        };
+        let backend = BatsignBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url("/at/test@example.com/token"),
+            BatsignRequestStyle::RawSubject,
+            options,
+        );
+        let mut notifier =
+            TwoLevelNotifier::new(backend, Some(REPEAT), RETRY, ALARM, RESTORED, false);
+        let t0 = Instant::now();
+
+        // The first attempt is cut short, and the retry waits long enough.
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(Level::High, t0)),
+            NotificationResult::Failure(_)
+        ));
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(Level::High, t0 + RETRY)),
+            NotificationResult::Success
+        ));
+        mock.assert_calls(2);
+    }
+
+    #[test]
+    fn test_success_statuses_override_2xx() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(302);
+        });
+
+        let options = HttpOptions {
+            success_statuses: Some(vec![302]),
+            ..TEST_HTTP_OPTIONS
+        };
+        let backend = BatsignBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url("/at/test@example.com/token"),
+            BatsignRequestStyle::RawSubject,
+            options,
+        );
+        let mut notifier =
+            TwoLevelNotifier::new(backend, Some(REPEAT), RETRY, ALARM, RESTORED, false);
+
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(Level::High, Instant::now())),
+            NotificationResult::Success
+        ));
+    }
+
+    #[test]
+    fn test_connection_refused_is_retried_after_retry_interval() {
+        // Bind to a free port and close it again, so nothing is listening there.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let backend = BatsignBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &format!("http://127.0.0.1:{port}/at/test@example.com/token"),
+            BatsignRequestStyle::RawSubject,
+            TEST_HTTP_OPTIONS,
+        );
+        let mut notifier =
+            TwoLevelNotifier::new(backend, Some(REPEAT), RETRY, ALARM, RESTORED, false);
+        let t0 = Instant::now();
+        let ctx = |now| Context::for_test(Level::High, now);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0)),
+            NotificationResult::Failure(_)
+        ));
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY - Duration::from_secs(1))),
+            NotificationResult::NotYetTime
+        ));
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY)),
+            NotificationResult::Failure(_)
+        ));
+    }
+
+    #[test]
+    fn test_retry_counts_since_from_trigger() {
+        let server = MockServer::start();
+        let mut failing = server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(500);
+        });
+
+        let backend = BatsignBackend::new(
+            0,
+            Arc::new(Client::new()),
+            &server.url("/at/test@example.com/token"),
+            BatsignRequestStyle::RawSubject,
+            TEST_HTTP_OPTIONS,
+        );
+        let mut notifier = TwoLevelNotifier::new(
+            backend,
+            Some(REPEAT),
+            RETRY,
+            "Subject: PellX Alarm\nDown for {since}.",
+            RESTORED,
+            false,
+        );
+        let t0 = Instant::now();
+        let ctx = |now| Context {
+            since: t0,
+            ..Context::for_test(Level::High, now)
+        };
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + Duration::from_secs(10))),
+            NotificationResult::Failure(_)
+        ));
+        failing.delete();
+
+        // The retry reports the time from the pin changing to the retry, not
+        // to the failed attempt.
+        let ok = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body("Subject: PellX Alarm\nDown for 5m 10s.");
+            then.status(200);
+        });
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + Duration::from_secs(10) + RETRY)),
+            NotificationResult::Success
+        ));
+        ok.assert_calls(1);
+    }
+
+    #[test]
+    fn test_success_after_failure_waits_for_repeat_interval() {
+        let server = MockServer::start();
+        let mut failing = server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(500);
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+        let t0 = Instant::now();
+        let ctx = |now| Context::for_test(Level::High, now);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0)),
+            NotificationResult::Failure(_)
+        ));
+        failing.delete();
+
+        let ok = server.mock(|when, then| {
+            when.method(POST).path("/at/test@example.com/token");
+            then.status(200);
+        });
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY)),
+            NotificationResult::Success
+        ));
+
+        // Once sent, the next one is due after the repeat interval, not the retry interval.
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY * 2)),
+            NotificationResult::NotYetTime
+        ));
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + RETRY + REPEAT)),
+            NotificationResult::Success
+        ));
+        ok.assert_calls(2);
+    }
+
+    #[test]
+    fn test_test_notification_leaves_state_untouched() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body(ALARM);
+            then.status(200);
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+        let now = Instant::now();
+
+        assert!(matches!(
+            notifier.send_test(&Context::for_test(Level::High, now)),
+            NotificationResult::Success
+        ));
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(Level::High, now)),
+            NotificationResult::Success
+        ));
+        mock.assert_calls(2);
+    }
+
+    #[test]
+    fn test_identical_repeat_is_deduplicated_within_window() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body(ALARM);
+            then.status(200);
+        });
+
+        let window = REPEAT * 3;
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token")
+            .with_dedupe_window(Some(window));
+        let t0 = Instant::now();
+        let ctx = |now| Context::for_test(Level::High, now);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0)),
+            NotificationResult::Success
+        ));
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + REPEAT)),
+            NotificationResult::Deduplicated
+        ));
+
+        // A deduplicated message still counts towards the repeat interval.
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + REPEAT + RETRY)),
+            NotificationResult::NotYetTime
+        ));
+        mock.assert_calls(1);
+
+        assert!(matches!(
+            notifier.send_notification(&ctx(t0 + window)),
+            NotificationResult::Success
+        ));
+        mock.assert_calls(2);
+    }
+
+    #[test]
+    fn test_restored_is_sent_once_and_rearms_the_alarm() {
+        let server = MockServer::start();
+        let alarm = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body(ALARM);
+            then.status(200);
+        });
+        let restored = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body(RESTORED);
+            then.status(200);
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+        let t0 = Instant::now();
+
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(Level::High, t0)),
+            NotificationResult::Success
+        ));
+
+        // Restored notifications are not repeated.
+        for secs in [60, 120, 3600] {
+            let now = t0 + Duration::from_secs(secs);
+            notifier.send_notification(&Context::for_test(Level::Low, now));
+        }
+
+        restored.assert_calls(1);
+
+        // The restore reset the alarm state, so a new alarm goes out right away
+        // rather than waiting out the repeat interval.
+        assert!(matches!(
+            notifier.send_notification(&Context::for_test(
+                Level::High,
+                t0 + Duration::from_secs(3660)
+            )),
+            NotificationResult::Success
+        ));
+        alarm.assert_calls(2);
+    }
+
+    #[test]
+    fn test_oneshot_leaves_alarm_state_untouched() {
+        let server = MockServer::start();
+        let notice = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body("Subject: Notice\nboiler is fine.");
+            then.status(200);
+        });
+        let alarm = server.mock(|when, then| {
+            when.method(POST)
+                .path("/at/test@example.com/token")
+                .body(ALARM);
+            then.status(200);
+        });
+
+        let mut notifier = batsign_notifier(&server, "/at/test@example.com/token");
+        let ctx = Context::for_test(Level::High, Instant::now());
+
+        assert!(matches!(
+            notifier.send_oneshot(&ctx, "Notice", "{pin_name} is fine."),
+            NotificationResult::Success
+        ));
+        assert!(matches!(
+            notifier.send_notification(&ctx),
+            NotificationResult::Success
+        ));
+
+        notice.assert_calls(1);
+        alarm.assert_calls(1);
+    }
+
+    #[test]
+    fn test_failing_url_does_not_affect_timing_of_healthy_one() {
+        let server = MockServer::start();
+        let healthy = server.mock(|when, then| {
+            when.method(POST).path("/at/healthy@example.com/token");
+            then.status(200);
+        });
+        let down = server.mock(|when, then| {
+            when.method(POST).path("/at/down@example.com/token");
+            then.status(503);
+        });
+
+        let mut notifiers = [
+            batsign_notifier(&server, "/at/healthy@example.com/token"),
+            batsign_notifier(&server, "/at/down@example.com/token"),
+        ];
+        let t0 = Instant::now();
+
+        // Poll every retry interval for a little over one repeat interval.
+        let mut now = t0;
+
+        while now <= t0 + REPEAT {
+            for n in notifiers.iter_mut() {
+                n.send_notification(&Context::for_test(Level::High, now));
             }
+
+            now += RETRY;
+        }
+
+        // The healthy URL is only notified again once the repeat interval has
+        // passed, while the one that is down is retried at every retry interval.
+        healthy.assert_calls(2);
+        down.assert_calls((REPEAT.as_secs() / RETRY.as_secs() + 1) as usize);
+    }
+
+    #[test]
+    fn test_multiple_urls_each_receive_the_message() {
+        let server = MockServer::start();
+        let paths = [
+            "/at/first@example.com/token",
+            "/at/second@example.com/token",
+            "/at/third@example.com/token",
+        ];
+
+        let mocks: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                server.mock(|when, then| {
+                    when.method(POST).path(*path).body(ALARM);
+                    then.status(200);
+                })
+            })
+            .collect();
+
+        let mut notifiers: Vec<_> = paths
+            .iter()
+            .map(|path| batsign_notifier(&server, path))
+            .collect();
+
+        let ctx = Context::for_test(Level::High, Instant::now());
+
+        for n in notifiers.iter_mut() {
+            assert!(matches!(
+                n.send_notification(&ctx),
+                NotificationResult::Success
+            ));
+        }
+
+        for mock in mocks {
+            mock.assert_calls(1);
         }
     }
 }
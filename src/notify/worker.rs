@@ -0,0 +1,548 @@
+//! Sending notifications on a worker thread, so that a slow or unreachable
+//! endpoint never delays the next read of the pin.
+//!
+//! The monitor loop hands sends to the worker over a bounded channel and
+//! carries on polling. The worker owns the notifiers, and with them the
+//! timing of repeats and retries, and reports the outcome of each send back
+//! over another channel for the loop to log and record.
+//!
+//! At most one alarm or restored notification is queued at a time. As the
+//! loop asks for one on every poll while a level is qualified, the next one
+//! is only queued once the last has been reported on, which is also when the
+//! notifiers get to retry a failed send.
+//!
+//! Each notifier in turn runs on a thread of its own, so that the notifiers
+//! of a job are tried concurrently, and one that is slow, hangs or panics
+//! doesn't hold up or take down the others. A notifier that hasn't finished
+//! within the send timeout is reported as failed, and jobs for it are then
+//! reported as failed while it is still busy.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::duration::format_duration;
+use crate::gpio::Level;
+use crate::history::Receipt;
+use crate::notify::{Context, NotificationResult, Notifier};
+
+/// What kind of send a job or report is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    /// An alarm or restored notification, tracking the alarm state.
+    Notification,
+
+    /// A one-off notice, outside of the alarm state tracking.
+    Oneshot,
+
+    /// A test notification, as requested by signal.
+    Test,
+}
+
+/// A send for the worker thread to carry out through all notifiers.
+#[derive(Clone)]
+enum Job {
+    /// Send an alarm or restored notification.
+    Notification(Context),
+
+    /// Send a one-off notice with the passed subject and body.
+    Oneshot {
+        ctx: Context,
+        subject: String,
+        body: String,
+    },
+
+    /// Send the alarm message as a test.
+    Test(Context),
+
+    /// Record a notification for the passed level as sent, without sending it.
+    MarkSent(Level, Instant),
+}
+
+/// The outcome of a job, with the result of each notifier.
+pub struct Report {
+    /// What kind of send this was.
+    pub kind: JobKind,
+
+    /// The context the send was made with.
+    pub ctx: Context,
+
+    /// The name, result and delivery receipt of each notifier, in order.
+    /// There is a receipt only if a delivery was attempted.
+    pub results: Vec<(String, NotificationResult, Option<Receipt>)>,
+}
+
+/// Handle to the thread sending notifications.
+pub struct SendWorker {
+    /// Queue of jobs for the current worker thread, or `None` once finished.
+    jobs: Option<SyncSender<Job>>,
+
+    /// Sender of reports, cloned into each worker thread.
+    reports_tx: Sender<Report>,
+
+    /// Reports of finished jobs.
+    reports: Receiver<Report>,
+
+    /// Maximum number of jobs queued.
+    capacity: usize,
+
+    /// How long each notifier is given to carry out a job before it is
+    /// reported as failed.
+    timeout: Duration,
+
+    /// Number of notifiers owned by the current worker thread.
+    len: usize,
+
+    /// Number of notification jobs queued or being sent.
+    notifications_in_flight: usize,
+
+    /// Whether notifications and notices are dropped rather than queued,
+    /// as during maintenance. Tests are still sent.
+    silenced: bool,
+
+    /// Worker threads started, including ones still draining the jobs queued
+    /// before the notifiers were replaced.
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl SendWorker {
+    /// Starts a worker thread owning the passed notifiers, with room for
+    /// `capacity` queued jobs, giving each notifier `timeout` to send.
+    pub fn spawn(notifiers: Vec<Box<dyn Notifier>>, capacity: usize, timeout: Duration) -> Self {
+        let (reports_tx, reports) = mpsc::channel();
+
+        let mut worker = Self {
+            jobs: None,
+            reports_tx,
+            reports,
+            capacity,
+            timeout,
+            len: 0,
+            notifications_in_flight: 0,
+            silenced: false,
+            threads: Vec::new(),
+        };
+
+        worker.replace(notifiers);
+        worker
+    }
+
+    /// Hands the passed notifiers to a new worker thread, for jobs queued from
+    /// now on. Jobs already queued are still sent by the old notifiers.
+    pub fn replace(&mut self, notifiers: Vec<Box<dyn Notifier>>) {
+        let (jobs, queue) = mpsc::sync_channel(self.capacity);
+        let reports = self.reports_tx.clone();
+        let (capacity, timeout) = (self.capacity, self.timeout);
+
+        self.len = notifiers.len();
+        self.jobs = Some(jobs);
+        self.threads.push(thread::spawn(move || {
+            run(notifiers, queue, reports, capacity, timeout)
+        }));
+        self.threads.retain(|handle| !handle.is_finished());
+    }
+
+    /// Drops notifications and notices rather than queueing them while
+    /// `silenced` is set, leaving the alarm state untouched so that they are
+    /// asked for again once it is cleared.
+    pub fn set_silenced(&mut self, silenced: bool) {
+        self.silenced = silenced;
+    }
+
+    /// Returns whether the worker has no notifiers.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Queues an alarm or restored notification, unless one is already queued
+    /// or being sent, or the worker is silenced, in which case it is left for
+    /// a later poll.
+    pub fn send_notification(&mut self, ctx: &Context) {
+        if self.silenced || self.notifications_in_flight > 0 {
+            return;
+        }
+
+        if self.enqueue(Job::Notification(ctx.clone())) {
+            self.notifications_in_flight += 1;
+        }
+    }
+
+    /// Queues a one-off notice with the passed subject and body, unless silenced.
+    pub fn send_oneshot(&mut self, ctx: &Context, subject: &str, body: &str) {
+        if self.silenced {
+            return;
+        }
+
+        self.enqueue(Job::Oneshot {
+            ctx: ctx.clone(),
+            subject: subject.to_owned(),
+            body: body.to_owned(),
+        });
+    }
+
+    /// Queues a test notification.
+    pub fn send_test(&mut self, ctx: &Context) {
+        self.enqueue(Job::Test(ctx.clone()));
+    }
+
+    /// Queues recording a notification for the passed level as sent at `now`,
+    /// without sending it, so that the notifiers move on to the next level as
+    /// if it had been. Nothing is reported back.
+    pub fn mark_sent(&mut self, level: Level, now: Instant) {
+        self.enqueue(Job::MarkSent(level, now));
+    }
+
+    /// Returns the reports of the jobs finished since the last call.
+    pub fn take_reports(&mut self) -> Vec<Report> {
+        let reports: Vec<Report> = self.reports.try_iter().collect();
+
+        for report in &reports {
+            if report.kind == JobKind::Notification {
+                self.notifications_in_flight = self.notifications_in_flight.saturating_sub(1);
+            }
+        }
+
+        reports
+    }
+
+    /// Stops taking jobs and waits for the ones already queued to be sent,
+    /// returning the reports not yet taken.
+    pub fn finish(&mut self) -> Vec<Report> {
+        self.jobs = None;
+
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
+
+        self.take_reports()
+    }
+
+    /// Queues a job, returning whether there was room for it.
+    fn enqueue(&mut self, job: Job) -> bool {
+        let Some(jobs) = &self.jobs else {
+            return false;
+        };
+
+        match jobs.try_send(job) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                eprintln!(
+                    "[!] Send queue is full ({} jobs); dropping a notification.",
+                    self.capacity
+                );
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                eprintln!("[!] Send worker has stopped; dropping a notification.");
+                false
+            }
+        }
+    }
+}
+
+/// A notifier running on a thread of its own.
+struct Lane {
+    /// Name of the notifier.
+    name: String,
+
+    /// Queue of jobs for the notifier, numbered in order.
+    jobs: SyncSender<(usize, Job)>,
+
+    /// Results and receipts of the jobs carried out, with the number of each.
+    results: Receiver<(usize, NotificationResult, Option<Receipt>)>,
+
+    /// Number of jobs that timed out and are still being carried out.
+    overdue: usize,
+}
+
+impl Lane {
+    /// Starts a thread owning the passed notifier, with room for `capacity`
+    /// queued jobs.
+    fn spawn(mut notifier: Box<dyn Notifier>, capacity: usize) -> Self {
+        let (jobs, queue) = mpsc::sync_channel::<(usize, Job)>(capacity);
+        let (results_tx, results) = mpsc::channel();
+        let name = notifier.name();
+
+        thread::spawn(move || {
+            for (number, job) in queue {
+                let result = match &job {
+                    Job::Notification(ctx) => notifier.send_notification(ctx),
+                    Job::Oneshot { ctx, subject, body } => {
+                        notifier.send_oneshot(ctx, subject, body)
+                    }
+                    Job::Test(ctx) => notifier.send_test(ctx),
+                    Job::MarkSent(level, now) => {
+                        notifier.mark_sent(*level, *now);
+                        continue;
+                    }
+                };
+
+                let receipt = match result {
+                    NotificationResult::Success | NotificationResult::Failure(_) => {
+                        notifier.last_receipt()
+                    }
+                    _ => None,
+                };
+
+                if results_tx.send((number, result, receipt)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            name,
+            jobs,
+            results,
+            overdue: 0,
+        }
+    }
+
+    /// Returns whether the notifier is still carrying out a job that timed
+    /// out, discarding the results of any that have since finished.
+    fn is_busy(&mut self) -> bool {
+        while self.overdue > 0 && self.results.try_recv().is_ok() {
+            self.overdue -= 1;
+        }
+
+        self.overdue > 0
+    }
+
+    /// Hands a job to the notifier, returning an error result if it can't
+    /// take it.
+    fn submit(&self, number: usize, job: Job) -> Result<(), NotificationResult> {
+        match self.jobs.try_send((number, job)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(NotificationResult::Failure(
+                "still busy with earlier sends".to_string(),
+            )),
+            Err(TrySendError::Disconnected(_)) => Err(NotificationResult::Failure(
+                "notifier has stopped".to_string(),
+            )),
+        }
+    }
+
+    /// Waits until `deadline` for the result and receipt of the job with the
+    /// passed number, discarding results of earlier jobs that timed out.
+    fn wait(
+        &mut self,
+        number: usize,
+        deadline: Instant,
+        timed_out: &str,
+    ) -> (NotificationResult, Option<Receipt>) {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            match self.results.recv_timeout(remaining) {
+                Ok((n, result, receipt)) if n == number => return (result, receipt),
+                Ok(_) => self.overdue = self.overdue.saturating_sub(1),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.overdue += 1;
+                    return (NotificationResult::Failure(timed_out.to_string()), None);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return (
+                        NotificationResult::Failure("notifier has stopped".to_string()),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Carries out jobs through the passed notifiers until the queue is closed,
+/// each notifier on a thread of its own and given `timeout` per job.
+fn run(
+    notifiers: Vec<Box<dyn Notifier>>,
+    queue: Receiver<Job>,
+    reports: Sender<Report>,
+    capacity: usize,
+    timeout: Duration,
+) {
+    let mut lanes: Vec<Lane> = notifiers
+        .into_iter()
+        .map(|n| Lane::spawn(n, capacity))
+        .collect();
+
+    for (number, job) in queue.into_iter().enumerate() {
+        let (kind, ctx) = match &job {
+            Job::Notification(ctx) => (JobKind::Notification, ctx.clone()),
+            Job::Oneshot { ctx, .. } => (JobKind::Oneshot, ctx.clone()),
+            Job::Test(ctx) => (JobKind::Test, ctx.clone()),
+            Job::MarkSent(..) => {
+                for lane in &lanes {
+                    if lane.submit(number, job.clone()).is_err() {
+                        eprintln!("[!] {}: busy; state not carried over.", lane.name);
+                    }
+                }
+
+                continue;
+            }
+        };
+
+        let submitted: Vec<Result<(), NotificationResult>> = lanes
+            .iter_mut()
+            .map(|lane| {
+                if !lane.is_busy() {
+                    lane.submit(number, job.clone())
+                } else if kind == JobKind::Notification {
+                    // The send that timed out is still under way, and it
+                    // keeps track of its own retries.
+                    Err(NotificationResult::NotYetTime)
+                } else {
+                    Err(NotificationResult::Failure(
+                        "still busy with an earlier send".to_string(),
+                    ))
+                }
+            })
+            .collect();
+
+        let deadline = Instant::now() + timeout;
+        let timed_out = format!(
+            "timed out after {}",
+            format_duration(timeout, ctx.duration_format)
+        );
+
+        let results = lanes
+            .iter_mut()
+            .zip(submitted)
+            .map(|(lane, submitted)| {
+                let (result, receipt) = match submitted {
+                    Ok(()) => lane.wait(number, deadline, &timed_out),
+                    Err(result) => (result, None),
+                };
+
+                (lane.name.clone(), result, receipt)
+            })
+            .collect();
+
+        let report = Report { kind, ctx, results };
+
+        if reports.send(report).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::backend::Backend;
+    use crate::notify::TwoLevelNotifier;
+
+    /// Backend that takes the passed time to deliver each message.
+    struct SlowBackend(Duration);
+
+    impl Backend for SlowBackend {
+        /// Returns the name of the backend.
+        fn name(&self) -> String {
+            format!("slow {}ms", self.0.as_millis())
+        }
+
+        /// Returns the template verbatim.
+        fn build_message(&self, _ctx: &Context, template: &str) -> String {
+            template.to_owned()
+        }
+
+        /// Pretends to deliver the message, slowly.
+        fn send_message(&mut self, _ctx: &Context, _message: &str) -> Result<(), String> {
+            thread::sleep(self.0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_send_does_not_block_and_is_reported() {
+        let notifier = TwoLevelNotifier::new(
+            SlowBackend(Duration::from_millis(200)),
+            None,
+            Duration::from_secs(60),
+            "alarm",
+            "restored",
+            false,
+        );
+        let mut worker = SendWorker::spawn(vec![Box::new(notifier)], 1, Duration::from_secs(5));
+        let now = Instant::now();
+        let ctx = Context::for_test(Level::High, now);
+
+        let start = Instant::now();
+        worker.send_notification(&ctx);
+        worker.send_notification(&ctx);
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        let reports = worker.finish();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].kind, JobKind::Notification);
+        assert!(matches!(
+            reports[0].results[..],
+            [(_, NotificationResult::Success, _)]
+        ));
+    }
+
+    #[test]
+    fn test_mark_sent_moves_on_without_sending() {
+        let notifier = TwoLevelNotifier::new(
+            SlowBackend(Duration::from_millis(200)),
+            None,
+            Duration::from_secs(60),
+            "alarm",
+            "restored",
+            false,
+        );
+        let mut worker = SendWorker::spawn(vec![Box::new(notifier)], 4, Duration::from_secs(5));
+        let now = Instant::now();
+        let ctx = Context::for_test(Level::High, now);
+
+        worker.send_notification(&ctx);
+
+        while worker.take_reports().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Without the restore recorded, the alarm wouldn't be due again for a minute
+        worker.mark_sent(Level::Low, now);
+        worker.send_notification(&ctx);
+
+        let reports = worker.finish();
+        assert_eq!(reports.len(), 1);
+        assert!(matches!(
+            reports[0].results[..],
+            [(_, NotificationResult::Success, _)]
+        ));
+    }
+
+    #[test]
+    fn test_slow_notifier_times_out_without_holding_up_others() {
+        let notifiers: Vec<Box<dyn Notifier>> = [2000, 10]
+            .into_iter()
+            .map(|millis| {
+                Box::new(TwoLevelNotifier::new(
+                    SlowBackend(Duration::from_millis(millis)),
+                    None,
+                    Duration::from_secs(60),
+                    "alarm",
+                    "restored",
+                    false,
+                )) as Box<dyn Notifier>
+            })
+            .collect();
+        let mut worker = SendWorker::spawn(notifiers, 4, Duration::from_millis(200));
+        let now = Instant::now();
+        let ctx = Context::for_test(Level::High, now);
+
+        let start = Instant::now();
+        worker.send_test(&ctx);
+        let reports = worker.finish();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert_eq!(reports.len(), 1);
+        assert!(matches!(
+            &reports[0].results[..],
+            [(_, NotificationResult::Failure(e), None), (_, NotificationResult::Success, Some(_))]
+                if e.starts_with("timed out")
+        ));
+    }
+}
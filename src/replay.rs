@@ -0,0 +1,335 @@
+//! Replaying a recorded history file through the decision logic, for `--replay`.
+//!
+//! Each entry in the history file says when an alarm or restore was notified
+//! and how long the pin had been at its level by then, which tells when the
+//! pin changed. Those changes are polled at the configured intervals through
+//! the same `Monitor` and `TwoLevelNotifier` logic as the real loop, with the
+//! simulated notifiers of `--explain`, and the notifications that would have
+//! been sent are compared with the recorded ones. Nothing is actually sent.
+//!
+//! This makes for a regression check of configuration changes: replaying the
+//! history of the old configuration with the new one shows whether it would
+//! have notified the same.
+
+use chrono::{NaiveDateTime, TimeDelta};
+use std::time::{Duration, Instant};
+
+use crate::explain;
+use crate::gpio::Level;
+use crate::history::Entry;
+use crate::monitor::{Coalescer, Monitor};
+use crate::notify::{Context, NotificationResult};
+use crate::settings::Settings;
+
+/// Format of the timestamps in the history file.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// An alarm or restore, as recorded or as replayed.
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    /// When it was notified.
+    pub time: NaiveDateTime,
+
+    /// `High` for an alarm, `Low` for a restore.
+    pub level: Level,
+}
+
+impl Event {
+    /// Returns the name of the event as it is written in the history file.
+    fn name(&self) -> &'static str {
+        match self.level {
+            Level::High => "alarm",
+            Level::Low => "restored",
+        }
+    }
+}
+
+/// Returns the recorded events of the passed history entries, along with the
+/// changes of the pin they imply, both in order of time.
+///
+/// Entries of other events, or with timestamps that can't be parsed, are
+/// reported as errors.
+pub fn recorded(entries: &[Entry]) -> Result<(Vec<Event>, Vec<Event>), String> {
+    let mut events: Vec<Event> = Vec::new();
+    let mut changes: Vec<Event> = Vec::new();
+
+    for (i, entry) in entries.iter().enumerate() {
+        let time = NaiveDateTime::parse_from_str(&entry.timestamp, TIMESTAMP_FORMAT)
+            .map_err(|e| format!("entry {}: invalid timestamp: {e}", i + 1))?;
+
+        let level = match entry.event.as_str() {
+            "alarm" => Level::High,
+            "restored" => Level::Low,
+            other => return Err(format!("entry {}: unknown event \"{other}\"", i + 1)),
+        };
+
+        let changed = time - TimeDelta::seconds(entry.since_secs as i64);
+
+        // Two alarms in a row mean the pin was restored in between without a
+        // restore being recorded, e.g. with `send_restored` off. When isn't
+        // known, so take it to be halfway.
+        if let Some(last) = events.last()
+            && last.level == level
+        {
+            let gap = changed.max(last.time).signed_duration_since(last.time);
+            let between = last.time + gap / 2;
+            changes.push(Event {
+                time: between,
+                level: !level,
+            });
+        }
+
+        changes.push(Event {
+            time: changed,
+            level,
+        });
+        events.push(Event { time, level });
+    }
+
+    changes.sort_by_key(|change| change.time);
+    events.sort_by_key(|event| event.time);
+    Ok((events, changes))
+}
+
+/// Polls the passed changes of the pin with the passed settings from the time
+/// of the first change until the pin has had time to qualify after the last
+/// event, returning the alarms and restores that would have been notified.
+pub fn replay(settings: &Settings, changes: &[Event], until: NaiveDateTime) -> Vec<Event> {
+    let Some(first) = changes.first() else {
+        return Vec::new();
+    };
+
+    let mut monitor = Monitor::new(
+        settings.gpio.qualify_high_hold,
+        settings.gpio.qualify_low_hold,
+    )
+    .with_startup_alarm(settings.gpio.notify_on_startup_alarm)
+    .with_m_of_n(settings.gpio.m_of_n());
+    let mut notifiers = explain::build_trace_notifiers(settings);
+    let mut coalesce = settings.gpio.coalesce_window.map(Coalescer::new);
+    let mut alarm_active = false;
+
+    let start = first.time;
+    let t0 = Instant::now();
+    let slack = settings
+        .gpio
+        .qualify_latency(Level::High)
+        .1
+        .max(settings.gpio.qualify_latency(Level::Low).1);
+    let end = (until - start).to_std().unwrap_or_default() + slack;
+
+    let mut events = Vec::new();
+    let mut offset = Duration::ZERO;
+
+    while offset <= end {
+        let time = start + TimeDelta::from_std(offset).unwrap_or_default();
+        let level = changes
+            .iter()
+            .take_while(|change| change.time <= time)
+            .last()
+            .map_or(Level::Low, |change| change.level);
+        let now = t0 + offset;
+
+        let qualified = monitor.poll(level, now);
+
+        if let Some(level) = qualified
+            && (level == Level::High) != alarm_active
+        {
+            alarm_active = level == Level::High;
+
+            if let Some(coalesce) = coalesce.as_mut() {
+                if alarm_active {
+                    coalesce.realarmed(now);
+                } else {
+                    coalesce.restored(now);
+                }
+            }
+        }
+
+        let held = coalesce
+            .as_ref()
+            .is_some_and(|coalesce| coalesce.holds_restore(now));
+
+        // Restores are passed on even when `send_restored` is off, as the
+        // notifiers are told of them in the real loop too, but not recorded.
+        if let Some(level) = qualified
+            && !(level == Level::Low && held)
+            && dispatch(settings, &mut notifiers, &mut monitor, level, now)
+            && (level == Level::High || settings.notifications.send_restored)
+        {
+            events.push(Event { time, level });
+        }
+
+        offset += settings.gpio.poll_interval_for(level);
+    }
+
+    events
+}
+
+/// Passes a qualified level to the simulated notifiers, returning whether any
+/// of them would have sent a notification.
+fn dispatch(
+    settings: &Settings,
+    notifiers: &mut [Box<dyn crate::notify::Notifier>],
+    monitor: &mut Monitor,
+    level: Level,
+    now: Instant,
+) -> bool {
+    let ctx = Context::new(settings, level, now, monitor.since(level).unwrap_or(now));
+
+    let mut sent = notifiers.is_empty();
+
+    for n in notifiers.iter_mut() {
+        if matches!(
+            n.send_notification(&ctx),
+            NotificationResult::Success | NotificationResult::DryRun
+        ) {
+            sent = true;
+        }
+    }
+
+    if sent && level == Level::High {
+        monitor.mark_alarm_notified();
+    }
+
+    sent
+}
+
+/// Prints the recorded events side by side with the replayed ones, in order,
+/// and returns whether they are the same alarms and restores at the same
+/// times. Times are the same if they are no further apart than the passed
+/// tolerance, or a second, as timestamps are recorded to the second.
+pub fn compare(recorded: &[Event], replayed: &[Event], tolerance: Duration) -> bool {
+    println!("{:<4} {:<30} {:<30}", "#", "Recorded", "Replayed");
+
+    let tolerance =
+        TimeDelta::from_std(tolerance.max(Duration::from_secs(1))).unwrap_or(TimeDelta::MAX);
+    let mut same = recorded.len() == replayed.len();
+
+    for i in 0..recorded.len().max(replayed.len()) {
+        let describe = |event: Option<&Event>| {
+            event.map_or("-".to_string(), |event| {
+                format!("{} {}", event.time.format(TIMESTAMP_FORMAT), event.name())
+            })
+        };
+
+        let (a, b) = (recorded.get(i), replayed.get(i));
+        let differs = match (a, b) {
+            (Some(a), Some(b)) => a.level != b.level || (a.time - b.time).abs() > tolerance,
+            _ => true,
+        };
+
+        same &= !differs;
+
+        println!(
+            "{:<4} {:<30} {:<30}{}",
+            i + 1,
+            describe(a),
+            describe(b),
+            if differs { "  <- differs" } else { "" }
+        );
+    }
+
+    same
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::GpioSettings;
+
+    fn entry(timestamp: &str, event: &str, since_secs: u64) -> Entry {
+        Entry {
+            timestamp: timestamp.to_string(),
+            event: event.to_string(),
+            pin_number: 24,
+            pin_name: "GPIO24".to_string(),
+            since_secs,
+            outcomes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let entries = [
+            entry("2026-01-01 10:00:10", "alarm", 10),
+            entry("2026-01-01 10:05:10", "restored", 10),
+            entry("2026-01-01 11:00:10", "alarm", 10),
+        ];
+
+        let (events, changes) = recorded(&entries).unwrap();
+        let levels: Vec<Level> = changes.iter().map(|change| change.level).collect();
+        assert_eq!(levels, [Level::High, Level::Low, Level::High]);
+        assert_eq!(changes[1].time.to_string(), "2026-01-01 10:05:00");
+
+        let mut settings = Settings::default();
+        settings.slack.enabled = false;
+
+        let replayed = replay(&settings, &changes, events.last().unwrap().time);
+        assert!(compare(&events, &replayed, settings.gpio.poll_interval));
+        assert_eq!(replayed[0].time, events[0].time);
+
+        // Without restores being sent, the recorded one wouldn't have been.
+        settings.notifications.send_restored = false;
+        let replayed = replay(&settings, &changes, events.last().unwrap().time);
+        assert_eq!(replayed.len(), 2);
+        assert!(replayed.iter().all(|event| event.level == Level::High));
+        assert!(!compare(&events, &replayed, settings.gpio.poll_interval));
+
+        // Coalescing dropouts shorter than an hour, the restore and the alarm
+        // after it would have been taken for a dropout.
+        let mut settings = Settings::default();
+        settings.gpio.coalesce_window = Some(Duration::from_secs(3600));
+        let replayed = replay(&settings, &changes, events.last().unwrap().time);
+        assert_eq!(
+            replayed,
+            [Event {
+                time: events[0].time,
+                level: Level::High
+            }]
+        );
+
+        // With a hold longer than the first alarm lasted, the first alarm
+        // wouldn't have been sent, and as a restore only qualifies after an
+        // alarm, the hold would have counted on from it. A single alarm would
+        // then have been sent, as the pin went HIGH again an hour later.
+        let settings = Settings {
+            gpio: GpioSettings {
+                qualify_high_hold: Duration::from_secs(3600),
+                ..GpioSettings::default()
+            },
+            ..Settings::default()
+        };
+        let replayed = replay(&settings, &changes, events.last().unwrap().time);
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].level, Level::High);
+        assert_eq!(replayed[0].time, changes[2].time);
+
+        // The same alarms and restores at other times also differ.
+        let mut settings = Settings::default();
+        settings.gpio.qualify_high_hold = Duration::from_secs(70);
+        let replayed = replay(&settings, &changes, events.last().unwrap().time);
+        let levels: Vec<Level> = replayed.iter().map(|event| event.level).collect();
+        assert_eq!(levels, [Level::High, Level::Low, Level::High]);
+        assert!(!compare(&events, &replayed, settings.gpio.poll_interval));
+        assert!(compare(&events, &replayed, Duration::from_secs(60)));
+
+        // Alarms without restores in between, as recorded with `send_restored`
+        // off, imply a restore halfway.
+        let entries = [
+            entry("2026-01-01 10:00:10", "alarm", 10),
+            entry("2026-01-01 11:00:10", "alarm", 10),
+        ];
+        let (events, changes) = recorded(&entries).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[1].time.to_string(), "2026-01-01 10:30:05");
+
+        let mut settings = Settings::default();
+        settings.notifications.send_restored = false;
+        let replayed = replay(&settings, &changes, events.last().unwrap().time);
+        assert!(compare(&events, &replayed, settings.gpio.poll_interval));
+
+        assert!(recorded(&[entry("yesterday", "alarm", 0)]).is_err());
+        assert!(recorded(&[entry("2026-01-01 10:00:10", "test", 0)]).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+//! Errors found when sanity checking settings.
+//!
+//! This module defines the `SanityError` enum, with one variant per kind of
+//! problem that `Settings::sanity_check` can find, and the `SanityWarning` enum
+//! for likely mistakes found by `Settings::warnings` that don't prevent running.
+//! The `Display` implementations provide the user-facing text.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::duration::{DurationFormat, format_duration};
+use crate::settings::Signal;
+
+/// A single problem found when sanity checking settings.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanityError {
+    /// The GPIO pin number is out of range.
+    InvalidPin { pin_number: u8, max: u8 },
+
+    /// The GPIO poll interval is zero.
+    ZeroPollInterval,
+
+    /// The GPIO alarm poll interval is set but zero.
+    ZeroAlarmPollInterval,
+
+    /// Holds are set along with the `mofn` qualify mode, which replaces them.
+    HoldWithMOfN,
+
+    /// The M and N of the `mofn` qualify mode are out of range.
+    InvalidMOfN { m: u32, n: u32 },
+
+    /// The pin is pulled down, but has a fixed pull-up resistor on the board.
+    PullDownOnFixedPullUp { pin_number: u8 },
+
+    /// A sysfs GPIO directory is set but doesn't exist.
+    SysfsBaseNotFound { path: String },
+
+    /// A sysfs GPIO directory is set but this build can't read pins through sysfs.
+    SysfsUnsupported,
+
+    /// The input source is an ADC but this build can't read one.
+    AdcUnsupported,
+
+    /// An ADC setting is above the highest value it can have.
+    AdcOutOfRange {
+        setting: &'static str,
+        value: u16,
+        max: u16,
+    },
+
+    /// The ADC hysteresis is larger than the threshold it is below.
+    AdcHysteresisAboveThreshold { hysteresis: u16, threshold: u16 },
+
+    /// Flap detection is enabled with a threshold of zero.
+    ZeroFlapThreshold,
+
+    /// Flap detection is enabled but the flap window is zero.
+    ZeroFlapWindow,
+
+    /// The coalesce window is set to zero.
+    ZeroCoalesceWindow,
+
+    /// A duration is outside its sensible range, and extreme durations are errors.
+    ExtremeDuration {
+        setting: &'static str,
+        value: Duration,
+        limit: Duration,
+    },
+
+    /// A backend has a notification interval of zero.
+    ZeroNotificationInterval { backend: &'static str },
+
+    /// A backend has a retry interval of zero.
+    ZeroRetryInterval { backend: &'static str },
+
+    /// Slack notifications are enabled but no webhook URLs are configured.
+    #[cfg(feature = "notifications")]
+    MissingSlackUrl,
+
+    /// Batsign notifications are enabled but no URLs are configured.
+    #[cfg(feature = "notifications")]
+    MissingBatsignUrl,
+
+    /// Webhook notifications are enabled but no URLs are configured.
+    #[cfg(feature = "notifications")]
+    MissingWebhookUrl,
+
+    /// A URL to compress payloads to isn't among the webhook URLs.
+    #[cfg(feature = "notifications")]
+    UnknownGzipUrl { url: String },
+
+    /// A configured URL does not look like a URL.
+    #[cfg(feature = "notifications")]
+    InvalidUrl { kind: &'static str, url: String },
+
+    /// The HMAC signature header name is not a valid HTTP header name.
+    #[cfg(feature = "notifications")]
+    InvalidHmacHeader { header: String },
+
+    /// A payload template does not produce valid JSON.
+    #[cfg(feature = "notifications")]
+    InvalidPayloadTemplate { name: &'static str, error: String },
+
+    /// Commands are enabled but none are configured.
+    MissingCommand,
+
+    /// Piping is enabled but no command is configured.
+    MissingPipeCommand,
+
+    /// A key in the pin names table is not a valid GPIO pin number.
+    InvalidPinNameKey { key: String },
+
+    /// The maximum message size is zero.
+    ZeroMaxMessageBytes,
+
+    /// The status report interval is set but zero.
+    ZeroStatusReportInterval,
+
+    /// Deduplication is enabled but the dedupe window is zero.
+    ZeroDedupeWindow,
+
+    /// The restart dedupe window is set to zero.
+    ZeroRestartDedupeWindow,
+
+    /// The send queue size is zero.
+    ZeroSendQueueSize,
+
+    /// The send timeout is zero.
+    ZeroSendTimeout,
+
+    /// The maintenance end time can't be parsed.
+    InvalidMaintenanceUntil { until: String },
+
+    /// The maintenance duration is zero.
+    ZeroMaintenanceDuration,
+
+    /// More recent events are to be appended to alarms than allowed.
+    LogTailTooLong { lines: usize, max: usize },
+
+    /// History is enabled but capped to zero entries.
+    ZeroHistoryMaxEntries,
+
+    /// The same signal is mapped to more than one action.
+    DuplicateSignal { signal: Signal },
+
+    /// The HTTP timeout is zero.
+    ZeroHttpTimeout,
+
+    /// The list of HTTP statuses that count as delivered is set but empty.
+    EmptySuccessStatuses,
+
+    /// An HTTP status that counts as delivered is not a valid status code.
+    InvalidSuccessStatus { status: u16 },
+
+    /// The retry timeout schedule is set but empty.
+    EmptyRetryTimeoutSchedule,
+
+    /// A step of the retry timeout schedule is zero.
+    ZeroRetryTimeout,
+
+    /// No notifier backends are enabled.
+    NoBackendEnabled,
+}
+
+impl fmt::Display for SanityError {
+    /// Formats the error as user-facing text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPin { pin_number, max } => write!(
+                f,
+                "Invalid GPIO pin number: {pin_number}. Must be between 0 and {max}."
+            ),
+            Self::ZeroPollInterval => write!(f, "GPIO poll interval must be greater than zero."),
+            Self::ZeroAlarmPollInterval => {
+                write!(f, "GPIO alarm poll interval must be greater than zero.")
+            }
+            Self::HoldWithMOfN => write!(
+                f,
+                "GPIO qualify holds can't be combined with the \"mofn\" qualify mode."
+            ),
+            Self::InvalidMOfN { m, n } => write!(
+                f,
+                "GPIO qualify M-of-N ({m} of {n}) is invalid. \
+                M must be between 1 and N, and N at most 64."
+            ),
+            Self::PullDownOnFixedPullUp { pin_number } => write!(
+                f,
+                "GPIO{pin_number} has a fixed pull-up resistor and can't be pulled down."
+            ),
+            Self::SysfsBaseNotFound { path } => {
+                write!(f, "GPIO sysfs directory {path} does not exist.")
+            }
+            Self::SysfsUnsupported => write!(
+                f,
+                "GPIO sysfs directory is set, but this build lacks the `sysfs-gpio` feature."
+            ),
+            Self::AdcUnsupported => write!(
+                f,
+                "Input source is `adc`, but this build lacks the `real-gpio` feature."
+            ),
+            Self::AdcOutOfRange {
+                setting,
+                value,
+                max,
+            } => write!(f, "ADC {setting} {value} is out of range (0-{max})."),
+            Self::AdcHysteresisAboveThreshold {
+                hysteresis,
+                threshold,
+            } => write!(
+                f,
+                "ADC hysteresis {hysteresis} must not be above the threshold {threshold}."
+            ),
+            Self::ZeroFlapThreshold => write!(f, "GPIO flap threshold must be non-zero."),
+            Self::ZeroFlapWindow => write!(f, "GPIO flap window must be non-zero."),
+            Self::ZeroCoalesceWindow => {
+                write!(f, "GPIO coalesce window must be non-zero if set.")
+            }
+            Self::ExtremeDuration {
+                setting,
+                value,
+                limit,
+            } => write_extreme_duration(f, setting, *value, *limit),
+            Self::ZeroNotificationInterval { backend } => {
+                write!(f, "{backend} notifications interval must be non-zero.")
+            }
+            // Worded as it always was for commands, which aren't notifications.
+            Self::ZeroRetryInterval { backend: "Command" } => {
+                write!(f, "Command retry interval must be non-zero.")
+            }
+            Self::ZeroRetryInterval { backend } => {
+                write!(f, "{backend} notification retry interval must be non-zero.")
+            }
+            #[cfg(feature = "notifications")]
+            Self::MissingSlackUrl => write!(
+                f,
+                "Slack notifications are enabled but no webhook URLs are configured."
+            ),
+            #[cfg(feature = "notifications")]
+            Self::MissingBatsignUrl => write!(
+                f,
+                "Batsign notifications are enabled but no URLs are configured."
+            ),
+            #[cfg(feature = "notifications")]
+            Self::MissingWebhookUrl => write!(
+                f,
+                "Webhook notifications are enabled but no URLs are configured."
+            ),
+            #[cfg(feature = "notifications")]
+            Self::UnknownGzipUrl { url } => write!(
+                f,
+                "Webhook gzip URL \"{url}\" is not among the webhook URLs."
+            ),
+            #[cfg(feature = "notifications")]
+            Self::InvalidUrl { kind, url } => {
+                write!(f, "{kind} \"{url}\" does not seem to be a valid URL.")
+            }
+            #[cfg(feature = "notifications")]
+            Self::InvalidHmacHeader { header } => {
+                write!(
+                    f,
+                    "HMAC header \"{header}\" is not a valid HTTP header name."
+                )
+            }
+            #[cfg(feature = "notifications")]
+            Self::InvalidPayloadTemplate { name, error } => {
+                write!(f, "Webhook {name} template is invalid: {error}")
+            }
+            Self::MissingPipeCommand => {
+                write!(
+                    f,
+                    "Piping is enabled but no command to pipe to is configured."
+                )
+            }
+            Self::MissingCommand => write!(
+                f,
+                "Commands are enabled but neither an alarm nor a restore command is configured."
+            ),
+            Self::InvalidPinNameKey { key } => {
+                write!(f, "Pin name key \"{key}\" is not a valid GPIO pin number.")
+            }
+            Self::ZeroMaxMessageBytes => write!(f, "Max message size must be non-zero."),
+            Self::ZeroStatusReportInterval => {
+                write!(f, "Status report interval must be non-zero.")
+            }
+            Self::ZeroDedupeWindow => write!(f, "Dedupe window must be non-zero."),
+            Self::ZeroRestartDedupeWindow => {
+                write!(f, "Restart dedupe window must be non-zero if set.")
+            }
+            Self::ZeroSendQueueSize => write!(f, "Send queue size must be non-zero."),
+            Self::ZeroSendTimeout => write!(f, "Send timeout must be non-zero."),
+            Self::InvalidMaintenanceUntil { until } => write!(
+                f,
+                "Maintenance end time \"{until}\" is not a valid local time \
+                (expected YYYY-MM-DD HH:MM:SS)."
+            ),
+            Self::ZeroMaintenanceDuration => write!(f, "Maintenance duration must be non-zero."),
+            Self::LogTailTooLong { lines, max } => {
+                write!(f, "Log tail of {lines} events is too long (at most {max}).")
+            }
+            Self::ZeroHistoryMaxEntries => {
+                write!(f, "History max entries must be non-zero.")
+            }
+            Self::DuplicateSignal { signal } => {
+                write!(f, "Signal {signal} is mapped to more than one action.")
+            }
+            Self::ZeroHttpTimeout => write!(f, "HTTP timeout must be non-zero."),
+            Self::EmptySuccessStatuses => write!(
+                f,
+                "HTTP success statuses must list at least one status code."
+            ),
+            Self::InvalidSuccessStatus { status } => write!(
+                f,
+                "HTTP success status {status} is invalid. Must be between 100 and 599."
+            ),
+            Self::EmptyRetryTimeoutSchedule => write!(
+                f,
+                "HTTP retry timeout schedule must list at least one timeout."
+            ),
+            Self::ZeroRetryTimeout => {
+                write!(f, "HTTP retry timeout schedule steps must be non-zero.")
+            }
+            Self::NoBackendEnabled => write!(f, "At least one notifier backend must be enabled."),
+        }
+    }
+}
+
+/// A likely mistake found when checking settings, which doesn't prevent running.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanityWarning {
+    /// The qualify holds were bypassed with `--immediate`.
+    HoldsBypassed,
+
+    /// The pin is configured without a pull resistor.
+    FloatingInput,
+
+    /// A duration is outside its sensible range.
+    ExtremeDuration {
+        setting: &'static str,
+        value: Duration,
+        limit: Duration,
+    },
+
+    /// A non-zero hold is shorter than the poll interval.
+    HoldShorterThanPollInterval {
+        level: &'static str,
+        hold: Duration,
+        poll_interval: Duration,
+    },
+
+    /// A Batsign template doesn't start with a non-empty `Subject:` line.
+    MissingSubjectLine { name: &'static str },
+}
+
+impl fmt::Display for SanityWarning {
+    /// Formats the warning as user-facing text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HoldsBypassed => write!(
+                f,
+                "Qualify holds are bypassed by --immediate; \
+                alarms and restores qualify on the first reading."
+            ),
+            Self::FloatingInput => write!(
+                f,
+                "GPIO pull is \"none\"; an external pull resistor is required, \
+                or the pin will float and read at random."
+            ),
+            Self::ExtremeDuration {
+                setting,
+                value,
+                limit,
+            } => write_extreme_duration(f, setting, *value, *limit),
+            Self::HoldShorterThanPollInterval {
+                level,
+                hold,
+                poll_interval,
+            } => write!(
+                f,
+                "GPIO qualify-{level} hold ({}) is shorter than the poll interval ({}).",
+                humantime::format_duration(*hold),
+                humantime::format_duration(*poll_interval)
+            ),
+            Self::MissingSubjectLine { name } => write!(
+                f,
+                "Batsign {name} template doesn't start with a \"Subject:\" line; \
+                mails will be sent without a subject."
+            ),
+        }
+    }
+}
+
+/// Formats a duration outside its sensible range, shared by the error and
+/// the warning.
+fn write_extreme_duration(
+    f: &mut fmt::Formatter<'_>,
+    setting: &str,
+    value: Duration,
+    limit: Duration,
+) -> fmt::Result {
+    write!(
+        f,
+        "GPIO {setting} of {} is {} the sensible {} of {}; \
+        pass --allow-extreme if this is intended.",
+        format_duration(value, DurationFormat::Compact),
+        if value > limit { "above" } else { "below" },
+        if value > limit { "maximum" } else { "minimum" },
+        format_duration(limit, DurationFormat::Compact)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_unchanged() {
+        assert_eq!(
+            SanityError::ZeroRetryInterval { backend: "Command" }.to_string(),
+            "Command retry interval must be non-zero."
+        );
+        assert_eq!(
+            SanityError::ZeroNotificationInterval { backend: "Command" }.to_string(),
+            "Command notifications interval must be non-zero."
+        );
+        assert_eq!(
+            SanityError::ZeroRetryInterval { backend: "Webhook" }.to_string(),
+            "Webhook notification retry interval must be non-zero."
+        );
+        assert_eq!(
+            SanityError::InvalidPin {
+                pin_number: 40,
+                max: 27
+            }
+            .to_string(),
+            "Invalid GPIO pin number: 40. Must be between 0 and 27."
+        );
+        assert_eq!(
+            SanityWarning::HoldShorterThanPollInterval {
+                level: "HIGH",
+                hold: Duration::from_millis(1500),
+                poll_interval: Duration::from_secs(2),
+            }
+            .to_string(),
+            "GPIO qualify-HIGH hold (1s 500ms) is shorter than the poll interval (2s)."
+        );
+    }
+}
@@ -8,26 +8,524 @@
 //! overrides from the configuration file, and finally applying what was read
 //! on the command-line.
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
+#[cfg(feature = "notifications")]
+use std::time::Instant;
 use std::{fs, io};
 
+#[cfg(feature = "notifications")]
+use crate::backend;
 use crate::cli::Cli;
 use crate::defaults;
+use crate::duration::{DurationFormat, format_duration};
 use crate::file_config;
+use crate::gpio::Level;
+#[cfg(feature = "notifications")]
+use crate::notify;
+use crate::sanity::{SanityError, SanityWarning};
+#[cfg(feature = "notifications")]
+use crate::template;
 
-/// GPIO settings, including pin number, poll interval, and hold time.
+/// Highest valid BCM GPIO pin number.
+const MAX_GPIO_PIN: u8 = 27;
+
+/// BCM GPIO pins with fixed pull-up resistors on the board.
+const FIXED_PULL_UP_PINS: [u8; 2] = [2, 3];
+
+/// Verbosity of terminal output.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors are printed.
+    Quiet,
+
+    /// Normal output, including notification outcomes.
+    Info,
+
+    /// Additional debug information.
+    Debug,
+
+    /// Debug information plus every single pin reading.
+    Trace,
+}
+
+impl Verbosity {
+    /// Resolves the verbosity from the command-line flags.
+    ///
+    /// `--debug` is kept as an alias for `-v`, and `--quiet` trumps everything.
+    fn from_cli(cli: &Cli) -> Self {
+        if cli.quiet {
+            return Self::Quiet;
+        }
+
+        match cli.verbose.max(u8::from(cli.debug)) {
+            0 => Self::Info,
+            1 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+}
+
+/// How a Batsign message is packaged into the HTTP request.
+///
+/// Batsign itself expects the raw message, with the subject on a leading
+/// `Subject:` line. Other, self-hosted relays may want the subject and body
+/// as separate fields.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatsignRequestStyle {
+    /// The message as-is, as the raw request body.
+    RawSubject,
+
+    /// `subject=...&body=...`, form-encoded.
+    FormEncoded,
+
+    /// `{"subject": "...", "body": "..."}`.
+    Json,
+}
+
+impl fmt::Display for BatsignRequestStyle {
+    /// Formats the request style as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RawSubject => write!(f, "raw_subject"),
+            Self::FormEncoded => write!(f, "form_encoded"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Line ending used in Batsign messages.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    /// `\n`, as the templates are written.
+    Lf,
+
+    /// `\r\n`, as RFC 822 mail headers expect.
+    Crlf,
+}
+
+impl fmt::Display for LineEnding {
+    /// Formats the line ending as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lf => write!(f, "lf"),
+            Self::Crlf => write!(f, "crlf"),
+        }
+    }
+}
+
+/// How notifications are spread over the Batsign URLs.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatsignMode {
+    /// Every URL is sent every notification.
+    All,
+
+    /// URLs are tried in the order listed until one succeeds, skipping ones
+    /// that failed recently.
+    Failover,
+}
+
+impl fmt::Display for BatsignMode {
+    /// Formats the mode as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "all"),
+            Self::Failover => write!(f, "failover"),
+        }
+    }
+}
+
+/// How urgent the alarms of a pin are, deciding which channels they are sent to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth knowing about, but no action is needed.
+    Info,
+
+    /// Should be looked into soon.
+    Warning,
+
+    /// Needs attention right away.
+    Critical,
+}
+
+impl Severity {
+    /// All severities, from least to most urgent.
+    pub const ALL: [Severity; 3] = [Self::Info, Self::Warning, Self::Critical];
+}
+
+impl fmt::Display for Severity {
+    /// Formats the severity as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Hash function used to compute the HMAC signature of Batsign requests.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HmacAlgorithm {
+    /// HMAC-SHA256, as used by GitHub webhooks.
+    Sha256,
+
+    /// HMAC-SHA384.
+    Sha384,
+
+    /// HMAC-SHA512.
+    Sha512,
+}
+
+impl fmt::Display for HmacAlgorithm {
+    /// Formats the algorithm as it is written in the configuration file,
+    /// which is also the prefix of the signature header value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sha256 => write!(f, "sha256"),
+            Self::Sha384 => write!(f, "sha384"),
+            Self::Sha512 => write!(f, "sha512"),
+        }
+    }
+}
+
+/// How a level of the monitored pin qualifies as an alarm or restore.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QualifyMode {
+    /// Once the pin has read the level for the qualify hold duration.
+    #[serde(rename = "hold")]
+    Hold,
+
+    /// Once the pin has read the level at least M times out of the last N polls.
+    #[serde(rename = "mofn")]
+    MOfN,
+}
+
+impl fmt::Display for QualifyMode {
+    /// Formats the qualify mode as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hold => write!(f, "hold"),
+            Self::MOfN => write!(f, "mofn"),
+        }
+    }
+}
+
+/// How durations outside their sensible ranges are reported.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtremeDurations {
+    /// As warnings at startup, running anyway.
+    Warn,
+
+    /// As configuration errors, refusing to run.
+    Error,
+}
+
+impl fmt::Display for ExtremeDurations {
+    /// Formats the setting as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The level the monitored pin reads when the burner is operating normally.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BaselineLevel {
+    /// The pin idles LOW and HIGH is an alarm.
+    Low,
+
+    /// The pin idles HIGH and LOW is an alarm.
+    High,
+
+    /// The pin is read once at startup, and whatever it reads is taken as normal.
+    Auto,
+}
+
+impl fmt::Display for BaselineLevel {
+    /// Formats the baseline level as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::High => write!(f, "high"),
+            Self::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// The internal resistor the monitored pin is configured with.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PullMode {
+    /// The pin is pulled up, reading HIGH when nothing drives it.
+    Up,
+
+    /// The pin is pulled down, reading LOW when nothing drives it.
+    Down,
+
+    /// The pin is left floating, relying on an external resistor.
+    None,
+}
+
+impl fmt::Display for PullMode {
+    /// Formats the pull mode as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Up => write!(f, "up"),
+            Self::Down => write!(f, "down"),
+            Self::None => write!(f, "none"),
+        }
+    }
+}
+
+/// Where the monitored input is read from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    /// The level of a GPIO pin.
+    Gpio,
+
+    /// A channel of an MCP3008 ADC over SPI, compared against a threshold.
+    Adc,
+}
+
+impl fmt::Display for InputSource {
+    /// Formats the input source as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gpio => write!(f, "gpio"),
+            Self::Adc => write!(f, "adc"),
+        }
+    }
+}
+
+/// A preset of the baseline level and pull resistor of the monitored pin,
+/// named for the level that means an alarm and how the pin is pulled.
+///
+/// The variants are documented with /// so that `--help` describes each preset.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InputMode {
+    /// HIGH is an alarm, with the pin pulled up (the default)
+    #[serde(rename = "alarm-active-high-pullup")]
+    #[value(name = "alarm-active-high-pullup")]
+    HighPullup,
+
+    /// LOW is an alarm, with the pin pulled up, e.g. by a contact closing to ground
+    #[serde(rename = "alarm-active-low-pullup")]
+    #[value(name = "alarm-active-low-pullup")]
+    LowPullup,
+
+    /// HIGH is an alarm, with the pin pulled down
+    #[serde(rename = "alarm-active-high-pulldown")]
+    #[value(name = "alarm-active-high-pulldown")]
+    HighPulldown,
+
+    /// LOW is an alarm, with the pin pulled down
+    #[serde(rename = "alarm-active-low-pulldown")]
+    #[value(name = "alarm-active-low-pulldown")]
+    LowPulldown,
+
+    /// HIGH is an alarm, with the pin floating on an external resistor
+    #[serde(rename = "alarm-active-high-floating")]
+    #[value(name = "alarm-active-high-floating")]
+    HighFloating,
+
+    /// LOW is an alarm, with the pin floating on an external resistor
+    #[serde(rename = "alarm-active-low-floating")]
+    #[value(name = "alarm-active-low-floating")]
+    LowFloating,
+}
+
+impl InputMode {
+    /// All presets, in order.
+    const ALL: [Self; 6] = [
+        Self::HighPullup,
+        Self::LowPullup,
+        Self::HighPulldown,
+        Self::LowPulldown,
+        Self::HighFloating,
+        Self::LowFloating,
+    ];
+
+    /// Returns the level the pin reads normally with this preset, which is
+    /// the opposite of the one that means an alarm.
+    pub fn baseline_level(self) -> BaselineLevel {
+        match self {
+            Self::HighPullup | Self::HighPulldown | Self::HighFloating => BaselineLevel::Low,
+            Self::LowPullup | Self::LowPulldown | Self::LowFloating => BaselineLevel::High,
+        }
+    }
+
+    /// Returns the pull resistor of this preset.
+    pub fn pull(self) -> PullMode {
+        match self {
+            Self::HighPullup | Self::LowPullup => PullMode::Up,
+            Self::HighPulldown | Self::LowPulldown => PullMode::Down,
+            Self::HighFloating | Self::LowFloating => PullMode::None,
+        }
+    }
+
+    /// Returns the preset with the passed baseline level and pull resistor,
+    /// if there is one.
+    pub fn of(baseline_level: BaselineLevel, pull: PullMode) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.baseline_level() == baseline_level && mode.pull() == pull)
+    }
+}
+
+impl fmt::Display for InputMode {
+    /// Formats the input mode as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HighPullup => write!(f, "alarm-active-high-pullup"),
+            Self::LowPullup => write!(f, "alarm-active-low-pullup"),
+            Self::HighPulldown => write!(f, "alarm-active-high-pulldown"),
+            Self::LowPulldown => write!(f, "alarm-active-low-pulldown"),
+            Self::HighFloating => write!(f, "alarm-active-high-floating"),
+            Self::LowFloating => write!(f, "alarm-active-low-floating"),
+        }
+    }
+}
+
+/// A signal that can be mapped to a runtime action in the `[signals]` section.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// SIGHUP.
+    #[serde(rename = "SIGHUP")]
+    Hup,
+
+    /// SIGUSR1.
+    #[serde(rename = "SIGUSR1")]
+    Usr1,
+
+    /// SIGUSR2.
+    #[serde(rename = "SIGUSR2")]
+    Usr2,
+
+    /// No signal, leaving the action unmapped.
+    #[serde(rename = "none")]
+    None,
+}
+
+impl fmt::Display for Signal {
+    /// Formats the signal as it is written in the configuration file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hup => write!(f, "SIGHUP"),
+            Self::Usr1 => write!(f, "SIGUSR1"),
+            Self::Usr2 => write!(f, "SIGUSR2"),
+            Self::None => write!(f, "none"),
+        }
+    }
+}
+
+/// GPIO settings, including pin number, poll interval, and hold times.
 #[derive(Debug, Serialize)]
 pub struct GpioSettings {
     /// GPIO pin number to monitor.
     pub pin_number: u8,
 
+    /// Human-readable label of the monitored pin, taking precedence over
+    /// any name given to it in `pin_names`.
+    pub pin_label: Option<String>,
+
+    /// How urgent alarms of the pin are, deciding which channels they are sent to.
+    pub severity: Severity,
+
     /// Poll interval for checking the GPIO pin.
     pub poll_interval: Duration,
 
-    /// Time the GPIO pin must be HIGH or LOW before qualifying as a valid change.
-    pub hold: Duration,
+    /// Optional poll interval to use instead of `poll_interval` while the pin reads HIGH,
+    /// to react faster during an alarm.
+    pub alarm_poll_interval: Option<Duration>,
+
+    /// Time the GPIO pin must be HIGH before qualifying as an alarm.
+    /// Zero means an alarm qualifies on the first HIGH reading.
+    pub qualify_high_hold: Duration,
+
+    /// Time the GPIO pin must be LOW before qualifying as restored.
+    /// Zero means the restore qualifies on the first LOW reading.
+    pub qualify_low_hold: Duration,
+
+    /// Whether levels qualify by holds or M-of-N.
+    pub qualify_mode: QualifyMode,
+
+    /// Number of readings of a level out of the last `qualify_n` needed to
+    /// qualify it, in the `mofn` qualify mode.
+    pub qualify_m: u32,
+
+    /// Number of most recent readings considered in the `mofn` qualify mode.
+    pub qualify_n: u32,
+
+    /// The level the pin reads normally. If it is HIGH, the pin is read
+    /// inverted, so that HIGH means an alarm throughout the rest of the program.
+    pub baseline_level: BaselineLevel,
+
+    /// The internal resistor the pin is configured with.
+    pub pull: PullMode,
+
+    /// Sysfs GPIO directory, e.g. `/sys/class/gpio`, to read the pin through
+    /// instead of `rppal`, for kernels where the latter doesn't work.
+    pub sysfs_base: Option<PathBuf>,
+
+    /// Whether the input is a GPIO pin or an ADC channel, as set up in `[adc]`.
+    pub input_source: InputSource,
+
+    /// Whether a HIGH reading at startup is notified right away, rather than
+    /// after the qualify-HIGH hold.
+    pub notify_on_startup_alarm: bool,
+
+    /// Time reading the pin must keep failing before an alert is sent.
+    pub read_error_timeout: Duration,
+
+    /// Time after sending a notification during which readings are logged but
+    /// otherwise ignored, to ride out glitches induced by whatever the
+    /// notification set off.
+    pub post_notify_deadtime: Duration,
+
+    /// Time to wait before the first poll, to let GPIO and the network settle
+    /// when started at boot.
+    pub startup_delay: Duration,
+
+    /// Upper bound of a random extra wait added to `startup_delay`, so that
+    /// several monitors started at once don't all poll and send in lockstep.
+    pub startup_jitter: Duration,
+
+    /// Time the pin is sampled for with `--calibrate`, to infer its baseline level.
+    pub calibration_duration: Duration,
+
+    /// Number of level changes within `flap_window` beyond which the signal
+    /// is considered unstable and alarms and restores are held, or `None` if
+    /// flapping isn't detected.
+    pub flap_threshold: Option<usize>,
+
+    /// Window within which level changes are counted for flap detection, and
+    /// how long the signal must stay unchanged to be considered settled.
+    pub flap_window: Duration,
+
+    /// Window for which a restore is held back, so that an alarm qualifying
+    /// again within it is taken as a brief dropout and neither is notified,
+    /// or `None` if restores are notified right away.
+    pub coalesce_window: Option<Duration>,
+
+    /// Whether durations outside their sensible ranges are warned about or
+    /// refused, unless allowed with `--allow-extreme`.
+    pub extreme_durations: ExtremeDurations,
 }
 
 impl Default for GpioSettings {
@@ -35,8 +533,29 @@ impl Default for GpioSettings {
     fn default() -> Self {
         Self {
             pin_number: defaults::gpio::PIN_NUMBER,
+            pin_label: None,
+            severity: Severity::Critical,
             poll_interval: defaults::gpio::POLL_INTERVAL,
-            hold: defaults::gpio::HOLD,
+            alarm_poll_interval: None,
+            qualify_high_hold: defaults::gpio::QUALIFY_HIGH_HOLD,
+            qualify_low_hold: defaults::gpio::QUALIFY_LOW_HOLD,
+            qualify_mode: QualifyMode::Hold,
+            qualify_m: defaults::gpio::QUALIFY_M,
+            qualify_n: defaults::gpio::QUALIFY_N,
+            baseline_level: BaselineLevel::Low,
+            pull: PullMode::Up,
+            sysfs_base: None,
+            input_source: InputSource::Gpio,
+            notify_on_startup_alarm: false,
+            read_error_timeout: defaults::gpio::READ_ERROR_TIMEOUT,
+            post_notify_deadtime: defaults::gpio::POST_NOTIFY_DEADTIME,
+            startup_delay: defaults::gpio::STARTUP_DELAY,
+            startup_jitter: defaults::gpio::STARTUP_JITTER,
+            calibration_duration: defaults::gpio::CALIBRATION_DURATION,
+            flap_threshold: None,
+            flap_window: defaults::gpio::FLAP_WINDOW,
+            coalesce_window: None,
+            extreme_durations: ExtremeDurations::Warn,
         }
     }
 }
@@ -48,30 +567,326 @@ impl GpioSettings {
             self.pin_number = pin_number;
         }
 
+        if let Some(pin_label) = &gpio_config.pin_label {
+            let pin_label = pin_label.trim();
+            self.pin_label = (!pin_label.is_empty()).then(|| pin_label.to_string());
+        }
+
+        if let Some(severity) = gpio_config.severity {
+            self.severity = severity;
+        }
+
         if let Some(poll_interval) = gpio_config.poll_interval {
             self.poll_interval = poll_interval;
         }
 
+        if let Some(alarm_poll_interval) = gpio_config.alarm_poll_interval {
+            self.alarm_poll_interval = Some(alarm_poll_interval);
+        }
+
+        if let Some(qualify_mode) = gpio_config.qualify_mode {
+            self.qualify_mode = qualify_mode;
+        }
+
+        if let Some(qualify_m) = gpio_config.qualify_m {
+            self.qualify_m = qualify_m;
+        }
+
+        if let Some(qualify_n) = gpio_config.qualify_n {
+            self.qualify_n = qualify_n;
+        }
+
+        // M-of-N replaces the holds, so drop the default ones. Any set
+        // explicitly below are then caught as a conflict by `sanity_check`.
+        if self.qualify_mode == QualifyMode::MOfN {
+            self.qualify_high_hold = Duration::ZERO;
+            self.qualify_low_hold = Duration::ZERO;
+        }
+
+        // `hold` is a deprecated alias for setting both holds at once,
+        // so apply it first and let the specific settings override it.
         if let Some(hold) = gpio_config.hold {
-            self.hold = hold;
+            self.qualify_high_hold = hold;
+            self.qualify_low_hold = hold;
+        }
+
+        if let Some(qualify_high_hold) = gpio_config.qualify_high_hold {
+            self.qualify_high_hold = qualify_high_hold;
+        }
+
+        if let Some(qualify_low_hold) = gpio_config.qualify_low_hold {
+            self.qualify_low_hold = qualify_low_hold;
+        }
+
+        // The preset goes first, so that the granular settings override it.
+        if let Some(input_mode) = gpio_config.input_mode {
+            self.baseline_level = input_mode.baseline_level();
+            self.pull = input_mode.pull();
+        }
+
+        if let Some(baseline_level) = gpio_config.baseline_level {
+            self.baseline_level = baseline_level;
+        }
+
+        if let Some(pull) = gpio_config.pull {
+            self.pull = pull;
+        }
+
+        if let Some(sysfs_base) = &gpio_config.sysfs_base {
+            self.sysfs_base = (!sysfs_base.as_os_str().is_empty()).then(|| sysfs_base.clone());
+        }
+
+        if let Some(input_source) = gpio_config.input_source {
+            self.input_source = input_source;
+        }
+
+        if let Some(notify_on_startup_alarm) = gpio_config.notify_on_startup_alarm {
+            self.notify_on_startup_alarm = notify_on_startup_alarm;
+        }
+
+        if let Some(read_error_timeout) = gpio_config.read_error_timeout {
+            self.read_error_timeout = read_error_timeout;
+        }
+
+        if let Some(post_notify_deadtime) = gpio_config.post_notify_deadtime {
+            self.post_notify_deadtime = post_notify_deadtime;
+        }
+
+        if let Some(startup_delay) = gpio_config.startup_delay {
+            self.startup_delay = startup_delay;
+        }
+
+        if let Some(startup_jitter) = gpio_config.startup_jitter {
+            self.startup_jitter = startup_jitter;
+        }
+
+        if let Some(calibration_duration) = gpio_config.calibration_duration {
+            self.calibration_duration = calibration_duration;
+        }
+
+        if let Some(flap_threshold) = gpio_config.flap_threshold {
+            self.flap_threshold = Some(flap_threshold);
+        }
+
+        if let Some(flap_window) = gpio_config.flap_window {
+            self.flap_window = flap_window;
+        }
+
+        if let Some(coalesce_window) = gpio_config.coalesce_window {
+            self.coalesce_window = Some(coalesce_window);
+        }
+
+        if let Some(extreme_durations) = gpio_config.extreme_durations {
+            self.extreme_durations = extreme_durations;
         }
     }
 
-    /// Sanity check settings, returning a list of errors if any are found.
-    fn sanity_check(&self, vec: &mut Vec<String>) {
-        const MAX_GPIO_PIN: u8 = 27;
+    /// Checks the GPIO settings for mistakes that don't prevent running,
+    /// appending a warning for each one found.
+    ///
+    /// A non-zero hold shorter than the poll interval is effectively one poll
+    /// long, as the level is only read again after it has passed.
+    fn warnings(&self, vec: &mut Vec<SanityWarning>) {
+        let holds = [
+            ("HIGH", self.qualify_high_hold),
+            ("LOW", self.qualify_low_hold),
+        ];
+
+        for (level, hold) in holds {
+            if hold != Duration::ZERO && hold < self.poll_interval {
+                vec.push(SanityWarning::HoldShorterThanPollInterval {
+                    level,
+                    hold,
+                    poll_interval: self.poll_interval,
+                });
+            }
+        }
+    }
+
+    /// Returns the durations that are outside their sensible ranges, as the
+    /// name of the setting, its value and the limit it is past, to catch
+    /// mistaken units such as `10h` for `10s`.
+    pub fn extreme_durations(&self) -> Vec<(&'static str, Duration, Duration)> {
+        let mut vec = Vec::new();
+        let mut check = |name, value: Duration, min: Duration, max: Duration| {
+            if value > max {
+                vec.push((name, value, max));
+            } else if !value.is_zero() && value < min {
+                vec.push((name, value, min));
+            }
+        };
+
+        let poll_interval = (
+            defaults::gpio::MIN_SENSIBLE_POLL_INTERVAL,
+            defaults::gpio::MAX_SENSIBLE_POLL_INTERVAL,
+        );
+
+        check(
+            "poll_interval",
+            self.poll_interval,
+            poll_interval.0,
+            poll_interval.1,
+        );
+
+        if let Some(alarm_poll_interval) = self.alarm_poll_interval {
+            check(
+                "alarm_poll_interval",
+                alarm_poll_interval,
+                poll_interval.0,
+                poll_interval.1,
+            );
+        }
+
+        for (name, hold) in [
+            ("qualify_high_hold", self.qualify_high_hold),
+            ("qualify_low_hold", self.qualify_low_hold),
+            ("post_notify_deadtime", self.post_notify_deadtime),
+        ] {
+            check(
+                name,
+                hold,
+                Duration::ZERO,
+                defaults::gpio::MAX_SENSIBLE_HOLD,
+            );
+        }
+
+        vec
+    }
+
+    /// Returns how long to wait before the first poll: the startup delay plus
+    /// a share of the startup jitter picked by the passed random number.
+    pub fn startup_wait(&self, random: u64) -> Duration {
+        let jitter_millis = self.startup_jitter.as_millis() as u64;
+
+        if jitter_millis == 0 {
+            return self.startup_delay;
+        }
+
+        self.startup_delay + Duration::from_millis(random % (jitter_millis + 1))
+    }
+
+    /// Returns M and N if levels qualify M-of-N rather than by holds.
+    pub fn m_of_n(&self) -> Option<(u32, u32)> {
+        (self.qualify_mode == QualifyMode::MOfN).then_some((self.qualify_m, self.qualify_n))
+    }
+
+    /// Returns an error if levels qualify M-of-N with an M or N out of range.
+    pub fn invalid_m_of_n(&self) -> Option<SanityError> {
+        let (m, n) = self.m_of_n()?;
+        (m == 0 || m > n || n > 64).then_some(SanityError::InvalidMOfN { m, n })
+    }
+
+    /// Returns the poll interval to sleep for after reading the passed level.
+    pub fn poll_interval_for(&self, level: Level) -> Duration {
+        match (level, self.alarm_poll_interval) {
+            (Level::High, Some(alarm_poll_interval)) => alarm_poll_interval,
+            _ => self.poll_interval,
+        }
+    }
 
+    /// Returns the shortest and longest time from the pin changing to the
+    /// passed level until the level qualifies, given the poll intervals and
+    /// qualify settings, assuming the pin stays at it.
+    ///
+    /// The change is first read up to one poll interval of the other level
+    /// late, after which it takes a whole number of poll intervals of this
+    /// level to cover the hold, or to read it M times.
+    pub fn qualify_latency(&self, level: Level) -> (Duration, Duration) {
+        let interval = self.poll_interval_for(level);
+        let polls = match self.m_of_n() {
+            Some((m, _)) => m.saturating_sub(1),
+            None => {
+                let hold = match level {
+                    Level::High => self.qualify_high_hold,
+                    Level::Low => self.qualify_low_hold,
+                };
+
+                hold.as_nanos().div_ceil(interval.as_nanos().max(1)) as u32
+            }
+        };
+
+        let best = interval.saturating_mul(polls);
+        (best, best.saturating_add(self.poll_interval_for(!level)))
+    }
+
+    /// Sanity check settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
         if self.pin_number > MAX_GPIO_PIN {
-            vec.push(format!(
-                "Invalid GPIO pin number: {}. Must be between 0 and {}.",
-                self.pin_number, MAX_GPIO_PIN
-            ));
+            vec.push(SanityError::InvalidPin {
+                pin_number: self.pin_number,
+                max: MAX_GPIO_PIN,
+            });
         }
 
         if self.poll_interval == Duration::ZERO {
-            vec.push("GPIO poll interval must be greater than zero.".to_string());
+            vec.push(SanityError::ZeroPollInterval);
+        }
+
+        if self.alarm_poll_interval == Some(Duration::ZERO) {
+            vec.push(SanityError::ZeroAlarmPollInterval);
+        }
+
+        if self.qualify_mode == QualifyMode::MOfN {
+            if self.qualify_high_hold != Duration::ZERO || self.qualify_low_hold != Duration::ZERO {
+                vec.push(SanityError::HoldWithMOfN);
+            }
+
+            vec.extend(self.invalid_m_of_n());
+        }
+
+        // GPIO2 and GPIO3 have fixed pull-up resistors on the board, for I2C,
+        // which an internal pull-down can't overcome.
+        if self.pull == PullMode::Down && FIXED_PULL_UP_PINS.contains(&self.pin_number) {
+            vec.push(SanityError::PullDownOnFixedPullUp {
+                pin_number: self.pin_number,
+            });
+        }
+
+        if let Some(sysfs_base) = &self.sysfs_base {
+            if !cfg!(feature = "sysfs-gpio") {
+                vec.push(SanityError::SysfsUnsupported);
+            } else if !sysfs_base.is_dir() {
+                vec.push(SanityError::SysfsBaseNotFound {
+                    path: sysfs_base.display().to_string(),
+                });
+            }
+        }
+
+        if self.flap_threshold == Some(0) {
+            vec.push(SanityError::ZeroFlapThreshold);
+        }
+
+        if self.flap_threshold.is_some() && self.flap_window.is_zero() {
+            vec.push(SanityError::ZeroFlapWindow);
+        }
+
+        if self.coalesce_window == Some(Duration::ZERO) {
+            vec.push(SanityError::ZeroCoalesceWindow);
         }
     }
+
+    /// Keeps the settings of the passed current GPIO settings that can't
+    /// change while the pin is open and being monitored, returning whether
+    /// any of them differed.
+    pub fn retain_fixed(&mut self, current: &GpioSettings) -> bool {
+        let differed = self.pin_number != current.pin_number
+            || self.pull != current.pull
+            || self.sysfs_base != current.sysfs_base
+            || self.input_source != current.input_source
+            || self.baseline_level != current.baseline_level
+            || self.qualify_mode != current.qualify_mode
+            || self.m_of_n() != current.m_of_n();
+
+        self.pin_number = current.pin_number;
+        self.pull = current.pull;
+        self.sysfs_base = current.sysfs_base.clone();
+        self.input_source = current.input_source;
+        self.baseline_level = current.baseline_level;
+        self.qualify_mode = current.qualify_mode;
+        self.qualify_m = current.qualify_m;
+        self.qualify_n = current.qualify_n;
+        differed
+    }
 }
 
 /// Slack settings.
@@ -94,6 +909,9 @@ pub struct SlackSettings {
 
     /// Time to wait before retrying to send a Slack notification after a failure.
     pub retry_interval: Duration,
+
+    /// Severities of alarm the channel accepts.
+    pub severities: Vec<Severity>,
 }
 
 impl Default for SlackSettings {
@@ -108,6 +926,7 @@ impl Default for SlackSettings {
             ),
             notification_interval: defaults::slack::NOTIFICATION_INTERVAL,
             retry_interval: defaults::slack::RETRY_INTERVAL,
+            severities: Severity::ALL.to_vec(),
         }
     }
 }
@@ -130,6 +949,10 @@ impl SlackSettings {
         if let Some(retry_interval) = slack_config.retry_interval {
             self.retry_interval = retry_interval;
         }
+
+        if let Some(severities) = &slack_config.severities {
+            self.severities = severities.clone();
+        }
     }
 
     /// Trims whitespace from the Slack webhook URLs and removes any empty URLs.
@@ -140,13 +963,14 @@ impl SlackSettings {
     /// Sanity check the Slack settings, returning true if they are valid
     /// and false if any issues are found. This is used to validate the settings
     /// before starting the monitoring loop.
-    fn sanity_check(&self, vec: &mut Vec<String>) {
+    #[cfg(feature = "notifications")]
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
         if self.notification_interval == Duration::ZERO {
-            vec.push("Slack notifications interval must be non-zero.".to_string());
+            vec.push(SanityError::ZeroNotificationInterval { backend: "Slack" });
         }
 
         if self.retry_interval == Duration::ZERO {
-            vec.push("Slack notification retry interval must be non-zero.".to_string());
+            vec.push(SanityError::ZeroRetryInterval { backend: "Slack" });
         }
 
         if !self.enabled {
@@ -154,18 +978,17 @@ impl SlackSettings {
         }
 
         if self.urls.is_empty() {
-            vec.push(
-                "Slack notifications are enabled but no webhook URLs are configured.".to_string(),
-            );
+            vec.push(SanityError::MissingSlackUrl);
             return;
         }
 
         for url in self.urls.iter() {
             match url.trim() {
                 url if !url.starts_with("https://") => {
-                    vec.push(format!(
-                        "Slack webhook URL \"{url}\" does not seem to be a valid URL."
-                    ));
+                    vec.push(SanityError::InvalidUrl {
+                        kind: "Slack webhook URL",
+                        url: url.to_string(),
+                    });
                 }
                 _ => {}
             }
@@ -182,6 +1005,32 @@ pub struct BatsignSettings {
     /// List of Batsign URLs to send notifications to.
     pub urls: Vec<String>,
 
+    /// Optional `http(s)://` URL from which to fetch additional Batsign URLs at startup.
+    pub urls_source: Option<String>,
+
+    /// How messages are packaged into the HTTP request.
+    pub request_style: BatsignRequestStyle,
+
+    /// Line ending used between the `Subject:` line and the lines of the body.
+    pub line_ending: LineEnding,
+
+    /// Whether to send to every URL or fail over between them in order.
+    pub mode: BatsignMode,
+
+    /// How long a URL that failed is skipped over in failover mode.
+    pub failover_health_window: Duration,
+
+    /// Optional shared secret with which to sign request bodies. Requests are
+    /// left unsigned if it is not set.
+    #[serde(skip)]
+    pub hmac_secret: Option<String>,
+
+    /// Name of the header carrying the signature.
+    pub hmac_header: String,
+
+    /// Hash function used for the signature.
+    pub hmac_algorithm: HmacAlgorithm,
+
     /// Path to the Batsign alarm message template file.
     pub alarm_message_template_body: String,
 
@@ -193,14 +1042,36 @@ pub struct BatsignSettings {
 
     /// Time to wait before retrying to send a notification after a failure.
     pub retry_interval: Duration,
-}
 
-impl Default for BatsignSettings {
+    /// Severities of alarm the channel accepts.
+    pub severities: Vec<Severity>,
+
+    /// Whether a Batsign host must be reachable before starting to monitor.
+    pub require_reachable_on_start: bool,
+
+    /// How long to keep retrying at startup for a Batsign host to become
+    /// reachable before giving up.
+    pub reachable_wait: Duration,
+
+    /// Whether to pin the addresses the Batsign hosts resolve to, so that
+    /// sends don't fail on a transient DNS failure.
+    pub pin_dns: bool,
+}
+
+impl Default for BatsignSettings {
     /// Default values for the Batsign settings.
     fn default() -> Self {
         Self {
             enabled: true,
             urls: Vec::new(),
+            urls_source: None,
+            request_style: BatsignRequestStyle::RawSubject,
+            line_ending: LineEnding::Lf,
+            mode: BatsignMode::All,
+            failover_health_window: defaults::batsign::FAILOVER_HEALTH_WINDOW,
+            hmac_secret: None,
+            hmac_header: String::from(defaults::batsign::HMAC_HEADER),
+            hmac_algorithm: HmacAlgorithm::Sha256,
             alarm_message_template_body: String::from(
                 defaults::batsign::ALARM_MESSAGE_TEMPLATE_BODY,
             ),
@@ -209,6 +1080,10 @@ impl Default for BatsignSettings {
             ),
             notification_interval: defaults::batsign::NOTIFICATION_INTERVAL,
             retry_interval: defaults::batsign::RETRY_INTERVAL,
+            severities: Severity::ALL.to_vec(),
+            require_reachable_on_start: false,
+            reachable_wait: defaults::batsign::REACHABLE_WAIT,
+            pin_dns: false,
         }
     }
 }
@@ -224,6 +1099,38 @@ impl BatsignSettings {
             self.urls = urls;
         }
 
+        if let Some(urls_source) = batsign_config.urls_source.clone() {
+            self.urls_source = Some(urls_source);
+        }
+
+        if let Some(request_style) = batsign_config.request_style {
+            self.request_style = request_style;
+        }
+
+        if let Some(line_ending) = batsign_config.line_ending {
+            self.line_ending = line_ending;
+        }
+
+        if let Some(mode) = batsign_config.mode {
+            self.mode = mode;
+        }
+
+        if let Some(failover_health_window) = batsign_config.failover_health_window {
+            self.failover_health_window = failover_health_window;
+        }
+
+        if let Some(hmac_secret) = &batsign_config.hmac_secret {
+            self.hmac_secret = (!hmac_secret.is_empty()).then(|| hmac_secret.clone());
+        }
+
+        if let Some(hmac_header) = &batsign_config.hmac_header {
+            self.hmac_header = hmac_header.trim().to_string();
+        }
+
+        if let Some(hmac_algorithm) = batsign_config.hmac_algorithm {
+            self.hmac_algorithm = hmac_algorithm;
+        }
+
         if let Some(notification_interval) = batsign_config.notification_interval {
             self.notification_interval = notification_interval;
         }
@@ -231,279 +1138,2159 @@ impl BatsignSettings {
         if let Some(retry_interval) = batsign_config.retry_interval {
             self.retry_interval = retry_interval;
         }
+
+        if let Some(severities) = &batsign_config.severities {
+            self.severities = severities.clone();
+        }
+
+        if let Some(require_reachable_on_start) = batsign_config.require_reachable_on_start {
+            self.require_reachable_on_start = require_reachable_on_start;
+        }
+
+        if let Some(reachable_wait) = batsign_config.reachable_wait {
+            self.reachable_wait = reachable_wait;
+        }
+
+        if let Some(pin_dns) = batsign_config.pin_dns {
+            self.pin_dns = pin_dns;
+        }
     }
 
     /// Trims whitespace from the Batsign URLs and removes any empty URLs.
     fn trim_urls(&mut self) {
         self.urls = trim_vec_of_strings(&self.urls);
+
+        if let Some(source) = &self.urls_source {
+            let source = source.trim();
+            self.urls_source = (!source.is_empty()).then(|| source.to_string());
+        }
+    }
+
+    /// Appends URLs fetched from the remote source to the list of Batsign URLs,
+    /// skipping any that are already present.
+    #[cfg(feature = "notifications")]
+    fn extend_urls(&mut self, urls: Vec<String>) {
+        for url in urls {
+            if !self.urls.contains(&url) {
+                self.urls.push(url);
+            }
+        }
     }
 
     /// Sanity check the Batsign settings, returning true if they are valid
     /// and false if any issues are found. This is used to validate the settings
     /// before starting the monitoring loop.
-    fn sanity_check(&self, vec: &mut Vec<String>) {
+    #[cfg(feature = "notifications")]
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
         if self.notification_interval == Duration::ZERO {
-            vec.push("Batsign notifications interval must be non-zero.".to_string());
+            vec.push(SanityError::ZeroNotificationInterval { backend: "Batsign" });
         }
 
         if self.retry_interval == Duration::ZERO {
-            vec.push("Batsign notification retry interval must be non-zero.".to_string());
+            vec.push(SanityError::ZeroRetryInterval { backend: "Batsign" });
         }
 
         if !self.enabled {
             return;
         }
 
+        if let Some(source) = &self.urls_source
+            && !source.starts_with("http://")
+            && !source.starts_with("https://")
+        {
+            vec.push(SanityError::InvalidUrl {
+                kind: "Batsign URL source",
+                url: source.clone(),
+            });
+        }
+
+        if self.hmac_secret.is_some()
+            && reqwest::header::HeaderName::from_bytes(self.hmac_header.as_bytes()).is_err()
+        {
+            vec.push(SanityError::InvalidHmacHeader {
+                header: self.hmac_header.clone(),
+            });
+        }
+
         if self.urls.is_empty() {
-            vec.push("Batsign notifications are enabled but no URLs are configured.".to_string());
+            vec.push(SanityError::MissingBatsignUrl);
             return;
         }
 
         for url in self.urls.iter() {
             match url.trim() {
                 url if !url.starts_with("https://") => {
-                    vec.push(format!(
-                        "Batsign URL \"{url}\" does not seem to be a valid URL."
-                    ));
+                    vec.push(SanityError::InvalidUrl {
+                        kind: "Batsign URL",
+                        url: url.to_string(),
+                    });
                 }
                 _ => {}
             }
         }
     }
-}
-
-/// Paths to resources, resolved at runtime.
-#[derive(Debug, Serialize)]
-pub struct PathBufs {
-    /// Path to the configuration directory, which contains the configuration file and other resources.
-    pub config_dir: PathBuf,
-
-    /// Path to the configuration file, resolved at runtime.
-    pub config_file: PathBuf,
-
-    /// Path to the Slack alarm message template file.
-    pub slack_alarm_template: PathBuf,
 
-    /// Path to the Slack restored message template file.
-    pub slack_restored_template: PathBuf,
+    /// Checks the Batsign settings for mistakes that don't prevent running,
+    /// appending a warning for each one found.
+    ///
+    /// Every request style takes the subject from a leading `Subject:` line,
+    /// so a template without one results in mails without a subject.
+    fn warnings(&self, vec: &mut Vec<SanityWarning>) {
+        // Batsign doesn't send anything if it wasn't compiled in.
+        if !cfg!(feature = "notifications") || !self.enabled {
+            return;
+        }
 
-    /// Path to the alarm message template file, resolved at runtime.
-    pub batsign_alarm_template: PathBuf,
+        let templates = [
+            ("alarm", &self.alarm_message_template_body),
+            ("restored", &self.restored_message_template_body),
+        ];
 
-    /// Path to the restored message template file, resolved at runtime.
-    pub batsign_restored_template: PathBuf,
-}
+        for (name, body) in templates {
+            let first_line = body.lines().next().unwrap_or_default();
 
-impl Default for PathBufs {
-    /// Default values for the resource paths.
-    fn default() -> Self {
-        Self {
-            config_dir: PathBuf::new(),
-            config_file: PathBuf::new(),
-            slack_alarm_template: PathBuf::new(),
-            slack_restored_template: PathBuf::new(),
-            batsign_alarm_template: PathBuf::new(),
-            batsign_restored_template: PathBuf::new(),
+            if first_line
+                .strip_prefix("Subject:")
+                .is_none_or(|subject| subject.trim().is_empty())
+            {
+                vec.push(SanityWarning::MissingSubjectLine { name });
+            }
         }
     }
 }
 
-/// Application settings, including defaults and sanity checks.
+/// Webhook settings, for POSTing templated JSON payloads to generic receivers.
 #[derive(Debug, Serialize)]
-pub struct Settings {
-    /// GPIO settings.
-    pub gpio: GpioSettings,
+pub struct WebhookSettings {
+    /// Whether webhook notifications are enabled.
+    pub enabled: bool,
 
-    /// Slack settings.
-    pub slack: SlackSettings,
+    /// List of webhook URLs to send the payloads to.
+    pub urls: Vec<String>,
 
-    /// Batsign settings.
-    pub batsign: BatsignSettings,
+    /// Text body of the webhook alarm payload template.
+    pub alarm_payload_template_body: String,
 
-    /// Paths to resources, resolved at runtime.
-    pub paths: PathBufs,
+    /// Text body of the webhook restored payload template.
+    pub restored_payload_template_body: String,
 
-    /// If true, the program will not send any Batsign notifications and will only print what it would do.
-    pub dry_run: bool,
+    /// Minimum time between sending notifications, to avoid spamming.
+    pub notification_interval: Duration,
+
+    /// Time to wait before retrying to send a notification after a failure.
+    pub retry_interval: Duration,
+
+    /// Severities of alarm the channel accepts.
+    pub severities: Vec<Severity>,
 
-    /// If true, the program will print additional debug information.
-    pub debug: bool,
+    /// Webhook URLs whose receivers accept gzip-compressed payloads.
+    pub gzip_urls: Vec<String>,
+
+    /// Size in bytes above which payloads to the `gzip_urls` are compressed.
+    pub gzip_min_bytes: usize,
 }
 
-impl Default for Settings {
-    /// Default values for settings, used as a base for applying config file and CLI overrides.
+impl Default for WebhookSettings {
+    /// Default values for the webhook settings.
     fn default() -> Self {
         Self {
-            gpio: GpioSettings::default(),
-            slack: SlackSettings::default(),
-            batsign: BatsignSettings::default(),
-            paths: PathBufs::default(),
-            dry_run: false,
-            debug: false,
+            enabled: false,
+            urls: Vec::new(),
+            alarm_payload_template_body: String::from(
+                defaults::webhook::ALARM_PAYLOAD_TEMPLATE_BODY,
+            ),
+            restored_payload_template_body: String::from(
+                defaults::webhook::RESTORED_PAYLOAD_TEMPLATE_BODY,
+            ),
+            notification_interval: defaults::webhook::NOTIFICATION_INTERVAL,
+            retry_interval: defaults::webhook::RETRY_INTERVAL,
+            severities: Severity::ALL.to_vec(),
+            gzip_urls: Vec::new(),
+            gzip_min_bytes: defaults::webhook::GZIP_MIN_BYTES,
         }
     }
 }
 
-impl Settings {
-    /// Applies the configuration directory setting, resolving the resource paths
-    /// based on the provided directory or the default. This is used to set up
-    /// the resource paths before loading resources from disk.
-    pub fn inherit_config_dir(&mut self, config_dir: &Option<String>) -> Result<(), String> {
-        if let Some(dir) = config_dir {
-            self.paths.config_dir = PathBuf::from(dir);
-            return Ok(());
+impl WebhookSettings {
+    /// Applies webhook settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, webhook_config: &file_config::WebhookConfig) {
+        if let Some(enabled) = webhook_config.enabled {
+            self.enabled = enabled;
         }
 
-        match file_config::resolve_default_config_directory_from_env() {
-            Ok(path) => {
-                self.paths.config_dir = path;
-                Ok(())
-            }
-            Err(e) => Err(e),
+        if let Some(urls) = webhook_config.urls.clone() {
+            self.urls = urls;
         }
-    }
 
-    /// Clean up settings by trimming whitespace from URLs and removing empty URLs.
-    pub fn clean_up(&mut self) {
-        self.slack.trim_urls();
-        self.batsign.trim_urls();
-    }
+        if let Some(notification_interval) = webhook_config.notification_interval {
+            self.notification_interval = notification_interval;
+        }
 
-    /// Sanity check settings, returning a list of errors if any are found.
-    pub fn sanity_check(&self) -> Result<(), Vec<String>> {
-        let mut vec = Vec::new();
+        if let Some(retry_interval) = webhook_config.retry_interval {
+            self.retry_interval = retry_interval;
+        }
 
-        self.gpio.sanity_check(&mut vec);
-        self.slack.sanity_check(&mut vec);
-        self.batsign.sanity_check(&mut vec);
+        if let Some(severities) = &webhook_config.severities {
+            self.severities = severities.clone();
+        }
 
-        if !self.slack.enabled && !self.batsign.enabled {
-            vec.push("At least one notifier backend must be enabled.".to_string());
+        if let Some(gzip_urls) = &webhook_config.gzip_urls {
+            self.gzip_urls = gzip_urls.clone();
         }
 
-        if vec.is_empty() { Ok(()) } else { Err(vec) }
+        if let Some(gzip_min_bytes) = webhook_config.gzip_min_bytes {
+            self.gzip_min_bytes = gzip_min_bytes;
+        }
     }
 
-    /// Print the settings in a human-readable format.
-    pub fn print(&self) {
-        if self.debug {
-            // If debug is enabled, print the entire settings struct with all details.
-            println!("{:#?}", self);
+    /// Trims whitespace from the webhook URLs and removes any empty URLs.
+    fn trim_urls(&mut self) {
+        self.urls = trim_vec_of_strings(&self.urls);
+        self.gzip_urls = trim_vec_of_strings(&self.gzip_urls);
+    }
+
+    /// Returns the size above which payloads to the passed URL are
+    /// compressed, or `None` if its receiver doesn't accept compressed ones.
+    #[cfg(feature = "notifications")]
+    pub fn gzip_min_bytes_for(&self, url: &str) -> Option<usize> {
+        self.gzip_urls
+            .iter()
+            .any(|gzip_url| gzip_url == url)
+            .then_some(self.gzip_min_bytes)
+    }
+
+    /// Sanity check the webhook settings, returning a list of errors if any are found.
+    ///
+    /// The payload templates are formatted with sample values and verified to be valid JSON,
+    /// so that a broken template is caught at startup rather than when an alarm is raised.
+    #[cfg(feature = "notifications")]
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if self.notification_interval == Duration::ZERO {
+            vec.push(SanityError::ZeroNotificationInterval { backend: "Webhook" });
+        }
+
+        if self.retry_interval == Duration::ZERO {
+            vec.push(SanityError::ZeroRetryInterval { backend: "Webhook" });
+        }
+
+        if !self.enabled {
             return;
         }
 
-        println!(
-            "Using configuration directory {}",
-            self.paths.config_dir.display()
-        );
+        let now = Instant::now();
 
-        println!();
-        println!("-- GPIO --");
-        println!("Pin number                   {}", self.gpio.pin_number);
-        println!(
-            "Poll interval                {}",
-            humantime::format_duration(self.gpio.poll_interval)
-        );
-        println!(
-            "Hold                         {}",
-            humantime::format_duration(self.gpio.hold)
-        );
+        for (name, template, level) in [
+            ("alarm", &self.alarm_payload_template_body, Level::High),
+            ("restored", &self.restored_payload_template_body, Level::Low),
+        ] {
+            let ctx = notify::Context::example(level, now);
 
-        println!();
-        println!("-- Slack --");
-        println!("Enabled                      {}", self.slack.enabled);
-        println!("Webhook URLs                 {:?}", self.slack.urls);
-        println!(
-            "Notification interval        {}",
-            humantime::format_duration(self.slack.notification_interval)
-        );
+            let payload = template::format_message(template, &ctx);
 
-        println!(
-            "Notification retry interval  {}",
-            humantime::format_duration(self.slack.retry_interval)
-        );
+            if let Err(e) = backend::webhook::validate_json_payload(&payload) {
+                vec.push(SanityError::InvalidPayloadTemplate { name, error: e });
+            }
+        }
 
-        println!();
-        println!("-- Batsign --");
-        println!("Enabled                      {}", self.batsign.enabled);
-        println!("URLs                         {:?}", self.batsign.urls);
-        println!(
-            "Notification interval        {}",
-            humantime::format_duration(self.batsign.notification_interval)
-        );
-        println!(
-            "Notification retry interval  {}",
-            humantime::format_duration(self.batsign.retry_interval)
-        );
+        if self.urls.is_empty() {
+            vec.push(SanityError::MissingWebhookUrl);
+            return;
+        }
 
-        if self.dry_run {
-            println!();
-            println!("(DRY RUN)");
+        for url in self.urls.iter() {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                vec.push(SanityError::InvalidUrl {
+                    kind: "Webhook URL",
+                    url: url.clone(),
+                });
+            }
+        }
+
+        for url in self.gzip_urls.iter() {
+            if !self.urls.contains(url) {
+                vec.push(SanityError::UnknownGzipUrl { url: url.clone() });
+            }
         }
     }
+}
 
-    /// Resolves the resource paths based on the config directory.
-    pub fn resolve_resource_paths(&mut self) {
-        self.paths.config_file = self.paths.config_dir.join(defaults::CONFIG_FILENAME);
+/// Command settings, for running local shell commands on alarm and restore.
+#[derive(Debug, Serialize)]
+pub struct CommandSettings {
+    /// Whether commands are run on alarm and restore.
+    pub enabled: bool,
 
-        self.paths.slack_alarm_template = self
-            .paths
-            .config_dir
-            .join(defaults::slack::ALARM_MESSAGE_TEMPLATE_FILENAME);
+    /// Shell command to run when an alarm is raised.
+    pub alarm_command: String,
 
-        self.paths.slack_restored_template = self
-            .paths
-            .config_dir
-            .join(defaults::slack::RESTORED_MESSAGE_TEMPLATE_FILENAME);
+    /// Shell command to run when the alarm is restored.
+    pub restored_command: String,
 
-        self.paths.batsign_alarm_template = self
-            .paths
-            .config_dir
-            .join(defaults::batsign::ALARM_MESSAGE_TEMPLATE_FILENAME);
+    /// Minimum time between running the alarm command.
+    pub notification_interval: Duration,
 
-        self.paths.batsign_restored_template = self
-            .paths
-            .config_dir
-            .join(defaults::batsign::RESTORED_MESSAGE_TEMPLATE_FILENAME);
-    }
+    /// Time to wait before retrying a command after a failure.
+    pub retry_interval: Duration,
 
-    /// Loads the Batsign URLs and message templates from disk,
-    /// returning an error if any of the files cannot be read.
-    pub fn load_resources_from_disk(&mut self) -> Vec<(PathBuf, io::Error)> {
-        let mut vec = Vec::new();
+    /// Severities of alarm the channel accepts.
+    pub severities: Vec<Severity>,
+}
 
-        match read_to_trimmed_string(&self.paths.slack_alarm_template) {
-            Ok(s) => self.slack.alarm_message_template_body = s,
-            Err(e) => vec.push((self.paths.slack_alarm_template.clone(), e)),
-        };
+impl Default for CommandSettings {
+    /// Default values for the command settings.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            alarm_command: String::new(),
+            restored_command: String::new(),
+            notification_interval: defaults::command::NOTIFICATION_INTERVAL,
+            retry_interval: defaults::command::RETRY_INTERVAL,
+            severities: Severity::ALL.to_vec(),
+        }
+    }
+}
 
-        match read_to_trimmed_string(&self.paths.slack_restored_template) {
-            Ok(s) => self.slack.restored_message_template_body = s,
-            Err(e) => vec.push((self.paths.slack_restored_template.clone(), e)),
-        };
+impl CommandSettings {
+    /// Applies command settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, command_config: &file_config::CommandConfig) {
+        if let Some(enabled) = command_config.enabled {
+            self.enabled = enabled;
+        }
 
-        match read_to_trimmed_string(&self.paths.batsign_alarm_template) {
-            Ok(s) => self.batsign.alarm_message_template_body = s,
-            Err(e) => vec.push((self.paths.batsign_alarm_template.clone(), e)),
-        };
+        if let Some(alarm_command) = command_config.on_alarm_command.clone() {
+            self.alarm_command = alarm_command;
+        }
 
-        match read_to_trimmed_string(&self.paths.batsign_restored_template) {
-            Ok(s) => self.batsign.restored_message_template_body = s,
-            Err(e) => vec.push((self.paths.batsign_restored_template.clone(), e)),
-        };
+        if let Some(restored_command) = command_config.on_restore_command.clone() {
+            self.restored_command = restored_command;
+        }
 
-        vec
-    }
+        if let Some(notification_interval) = command_config.notification_interval {
+            self.notification_interval = notification_interval;
+        }
 
-    /// Applies config file settings to the default settings, returning the resulting settings.
-    pub fn apply_file(&mut self, file_config: &Option<file_config::FileConfig>) {
-        let Some(file_config) = file_config else {
-            return;
-        };
+        if let Some(retry_interval) = command_config.retry_interval {
+            self.retry_interval = retry_interval;
+        }
 
-        self.gpio.apply_file(&file_config.gpio);
-        self.slack.apply_file(&file_config.slack);
-        self.batsign.apply_file(&file_config.batsign);
+        if let Some(severities) = &command_config.severities {
+            self.severities = severities.clone();
+        }
+    }
+
+    /// Sanity check the command settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if self.notification_interval == Duration::ZERO {
+            vec.push(SanityError::ZeroNotificationInterval { backend: "Command" });
+        }
+
+        if self.retry_interval == Duration::ZERO {
+            vec.push(SanityError::ZeroRetryInterval { backend: "Command" });
+        }
+
+        if !self.enabled {
+            return;
+        }
+
+        if self.alarm_command.trim().is_empty() && self.restored_command.trim().is_empty() {
+            vec.push(SanityError::MissingCommand);
+        }
+    }
+}
+
+/// Settings of the pipe notifier, which feeds messages to a shell command's stdin.
+#[derive(Debug, Serialize)]
+pub struct PipeSettings {
+    /// Whether messages are piped to the command on alarm and restore.
+    pub enabled: bool,
+
+    /// Shell command to pipe the messages to.
+    pub command: String,
+
+    /// Template of the alarm message.
+    pub alarm_message: String,
+
+    /// Template of the restored message.
+    pub restored_message: String,
+
+    /// Minimum time between piping the alarm message.
+    pub notification_interval: Duration,
+
+    /// Time to wait before retrying to pipe a message after a failure.
+    pub retry_interval: Duration,
+
+    /// Severities of alarm the channel accepts.
+    pub severities: Vec<Severity>,
+}
+
+impl Default for PipeSettings {
+    /// Default values for the pipe settings.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            command: String::new(),
+            alarm_message: String::from(defaults::pipe::ALARM_MESSAGE),
+            restored_message: String::from(defaults::pipe::RESTORED_MESSAGE),
+            notification_interval: defaults::pipe::NOTIFICATION_INTERVAL,
+            retry_interval: defaults::pipe::RETRY_INTERVAL,
+            severities: Severity::ALL.to_vec(),
+        }
+    }
+}
+
+impl PipeSettings {
+    /// Applies pipe settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, pipe_config: &file_config::PipeConfig) {
+        if let Some(enabled) = pipe_config.enabled {
+            self.enabled = enabled;
+        }
+
+        if let Some(command) = &pipe_config.command {
+            self.command = command.clone();
+        }
+
+        if let Some(message) = &pipe_config.alarm_message {
+            self.alarm_message = message.clone();
+        }
+
+        if let Some(message) = &pipe_config.restored_message {
+            self.restored_message = message.clone();
+        }
+
+        if let Some(notification_interval) = pipe_config.notification_interval {
+            self.notification_interval = notification_interval;
+        }
+
+        if let Some(retry_interval) = pipe_config.retry_interval {
+            self.retry_interval = retry_interval;
+        }
+
+        if let Some(severities) = &pipe_config.severities {
+            self.severities = severities.clone();
+        }
+    }
+
+    /// Sanity check the pipe settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if self.notification_interval == Duration::ZERO {
+            vec.push(SanityError::ZeroNotificationInterval { backend: "Pipe" });
+        }
+
+        if self.retry_interval == Duration::ZERO {
+            vec.push(SanityError::ZeroRetryInterval { backend: "Pipe" });
+        }
+
+        if self.enabled && self.command.trim().is_empty() {
+            vec.push(SanityError::MissingPipeCommand);
+        }
+    }
+}
+
+/// Notification settings that apply across backends.
+#[derive(Debug, Serialize)]
+pub struct NotificationsSettings {
+    /// Maximum size of a Slack, Batsign or pipe message body, in bytes.
+    /// Longer messages are truncated.
+    pub max_message_bytes: usize,
+
+    /// Whether `{config_summary}` is substituted with a summary of the key
+    /// settings, for troubleshooting. If not, it is substituted with nothing.
+    pub include_config_in_notification: bool,
+
+    /// Whether restored notifications are sent. If not, restores are still
+    /// tracked and logged, but only alarms are sent.
+    pub send_restored: bool,
+
+    /// Whether to send a notice through all notifiers when only some of them
+    /// succeeded in sending an alarm or restore.
+    pub notify_on_partial_failure: bool,
+
+    /// Whether to send a notice through all notifiers when the GPIO pin can't
+    /// be opened at startup, before exiting.
+    pub notify_on_startup_failure: bool,
+
+    /// Template of the partial failure notice, which may additionally contain
+    /// `{succeeded}`, `{total}` and `{failed}`.
+    pub partial_failure_message: String,
+
+    /// Optional interval at which to send a report that all is nominal, while
+    /// there is no alarm, so that silence can't be mistaken for a dead monitor.
+    pub status_report_interval: Option<Duration>,
+
+    /// Template of the status report.
+    pub status_report_message: String,
+
+    /// Whether to skip sending a message identical to the last one sent by
+    /// the same notifier, within the dedupe window.
+    pub dedupe_identical: bool,
+
+    /// Window within which identical messages are skipped, if deduplicating.
+    pub dedupe_window: Duration,
+
+    /// Optional window within which an alarm already sent for the same pin
+    /// and alarm episode is not sent again after a restart. Sent alarms are
+    /// recorded on disk only if it is set.
+    pub restart_dedupe_window: Option<Duration>,
+
+    /// Maximum number of sends queued for the send worker. Sends beyond it
+    /// are dropped, and the ones that track the alarm state tried again.
+    pub send_queue_size: usize,
+
+    /// Time each notifier is given to send, concurrently with the others,
+    /// before it is reported as failed.
+    pub send_timeout: Duration,
+
+    /// Local time until which notifications are silenced for maintenance, as
+    /// `YYYY-MM-DD HH:MM:SS`, if set.
+    pub maintenance_until: Option<String>,
+
+    /// Number of recent alarms and restores to append to alarm messages,
+    /// for triage at a glance. Zero disables it.
+    pub include_log_tail: usize,
+
+    /// How long maintenance mode lasts when entered by signal or through the
+    /// status socket without a duration.
+    pub maintenance_duration: Duration,
+}
+
+impl Default for NotificationsSettings {
+    /// Default values for the cross-backend notification settings.
+    fn default() -> Self {
+        Self {
+            max_message_bytes: defaults::notifications::MAX_MESSAGE_BYTES,
+            include_config_in_notification: false,
+            send_restored: true,
+            notify_on_partial_failure: false,
+            notify_on_startup_failure: false,
+            partial_failure_message: String::from(defaults::notifications::PARTIAL_FAILURE_MESSAGE),
+            status_report_interval: None,
+            status_report_message: String::from(defaults::notifications::STATUS_REPORT_MESSAGE),
+            dedupe_identical: false,
+            dedupe_window: defaults::notifications::DEDUPE_WINDOW,
+            restart_dedupe_window: None,
+            send_queue_size: defaults::notifications::SEND_QUEUE_SIZE,
+            send_timeout: defaults::notifications::SEND_TIMEOUT,
+            maintenance_until: None,
+            maintenance_duration: defaults::notifications::MAINTENANCE_DURATION,
+            include_log_tail: 0,
+        }
+    }
+}
+
+impl NotificationsSettings {
+    /// Applies notification settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, notifications_config: &file_config::NotificationsConfig) {
+        if let Some(max_message_bytes) = notifications_config.max_message_bytes {
+            self.max_message_bytes = max_message_bytes;
+        }
+
+        if let Some(include) = notifications_config.include_config_in_notification {
+            self.include_config_in_notification = include;
+        }
+
+        if let Some(send_restored) = notifications_config.send_restored {
+            self.send_restored = send_restored;
+        }
+
+        if let Some(notify) = notifications_config.notify_on_partial_failure {
+            self.notify_on_partial_failure = notify;
+        }
+
+        if let Some(notify) = notifications_config.notify_on_startup_failure {
+            self.notify_on_startup_failure = notify;
+        }
+
+        if let Some(message) = &notifications_config.partial_failure_message {
+            self.partial_failure_message = message.clone();
+        }
+
+        if let Some(interval) = notifications_config.status_report_interval {
+            self.status_report_interval = Some(interval);
+        }
+
+        if let Some(message) = &notifications_config.status_report_message {
+            self.status_report_message = message.clone();
+        }
+
+        if let Some(dedupe) = notifications_config.dedupe_identical {
+            self.dedupe_identical = dedupe;
+        }
+
+        if let Some(window) = notifications_config.dedupe_window {
+            self.dedupe_window = window;
+        }
+
+        if let Some(window) = notifications_config.restart_dedupe_window {
+            self.restart_dedupe_window = Some(window);
+        }
+
+        if let Some(size) = notifications_config.send_queue_size {
+            self.send_queue_size = size;
+        }
+
+        if let Some(timeout) = notifications_config.send_timeout {
+            self.send_timeout = timeout;
+        }
+
+        if let Some(until) = &notifications_config.maintenance_until {
+            let until = until.trim();
+            self.maintenance_until = (!until.is_empty()).then(|| until.to_string());
+        }
+
+        if let Some(duration) = notifications_config.maintenance_duration {
+            self.maintenance_duration = duration;
+        }
+
+        if let Some(lines) = notifications_config.include_log_tail {
+            self.include_log_tail = lines;
+        }
+    }
+
+    /// Returns the local time until which notifications are silenced for
+    /// maintenance, if it is set and valid.
+    pub fn maintenance_until(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        chrono::NaiveDateTime::parse_from_str(
+            self.maintenance_until.as_deref()?,
+            defaults::notifications::MAINTENANCE_UNTIL_FORMAT,
+        )
+        .ok()?
+        .and_local_timezone(chrono::Local)
+        .earliest()
+    }
+
+    /// Returns the window within which identical messages are skipped,
+    /// or `None` if they shouldn't be.
+    pub fn dedupe_window(&self) -> Option<Duration> {
+        self.dedupe_identical.then_some(self.dedupe_window)
+    }
+
+    /// Sanity check the notification settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if self.max_message_bytes == 0 {
+            vec.push(SanityError::ZeroMaxMessageBytes);
+        }
+
+        if self.status_report_interval == Some(Duration::ZERO) {
+            vec.push(SanityError::ZeroStatusReportInterval);
+        }
+
+        if self.dedupe_identical && self.dedupe_window.is_zero() {
+            vec.push(SanityError::ZeroDedupeWindow);
+        }
+
+        if self.restart_dedupe_window == Some(Duration::ZERO) {
+            vec.push(SanityError::ZeroRestartDedupeWindow);
+        }
+
+        if self.send_queue_size == 0 {
+            vec.push(SanityError::ZeroSendQueueSize);
+        }
+
+        if self.send_timeout.is_zero() {
+            vec.push(SanityError::ZeroSendTimeout);
+        }
+
+        if let Some(until) = &self.maintenance_until
+            && self.maintenance_until().is_none()
+        {
+            vec.push(SanityError::InvalidMaintenanceUntil {
+                until: until.clone(),
+            });
+        }
+
+        if self.maintenance_duration.is_zero() {
+            vec.push(SanityError::ZeroMaintenanceDuration);
+        }
+
+        if self.include_log_tail > defaults::notifications::MAX_LOG_TAIL {
+            vec.push(SanityError::LogTailTooLong {
+                lines: self.include_log_tail,
+                max: defaults::notifications::MAX_LOG_TAIL,
+            });
+        }
+    }
+}
+
+/// Settings of the alarm history file.
+#[derive(Debug, Serialize)]
+pub struct HistorySettings {
+    /// Whether to record qualified alarms and restores in the history file.
+    pub enabled: bool,
+
+    /// Maximum number of entries to keep in the history file.
+    pub max_entries: usize,
+
+    /// Whether to rotate a full history file out and gzip it, rather than
+    /// dropping its oldest entries.
+    pub compress_rotated_logs: bool,
+}
+
+impl Default for HistorySettings {
+    /// Default values for the history settings.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: defaults::history::MAX_ENTRIES,
+            compress_rotated_logs: false,
+        }
+    }
+}
+
+impl HistorySettings {
+    /// Applies history settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, history_config: &file_config::HistoryConfig) {
+        if let Some(enabled) = history_config.enabled {
+            self.enabled = enabled;
+        }
+
+        if let Some(max_entries) = history_config.max_entries {
+            self.max_entries = max_entries;
+        }
+
+        if let Some(compress_rotated_logs) = history_config.compress_rotated_logs {
+            self.compress_rotated_logs = compress_rotated_logs;
+        }
+    }
+
+    /// Sanity check the history settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if self.enabled && self.max_entries == 0 {
+            vec.push(SanityError::ZeroHistoryMaxEntries);
+        }
+    }
+}
+
+/// Settings of the MCP3008 ADC read when `input_source` is `adc`.
+#[derive(Debug, Serialize)]
+pub struct AdcSettings {
+    /// SPI bus the ADC is connected to.
+    pub spi_bus: u8,
+
+    /// Chip select line of the ADC on the bus.
+    pub chip_select: u8,
+
+    /// ADC channel the sensor is connected to.
+    pub channel: u8,
+
+    /// Reading at or above which the input is HIGH.
+    pub threshold: u16,
+
+    /// Margin below the threshold a reading must fall to turn the input LOW
+    /// again, so that a reading hovering around the threshold doesn't flap.
+    pub hysteresis: u16,
+}
+
+impl Default for AdcSettings {
+    /// Default values for the ADC settings.
+    fn default() -> Self {
+        Self {
+            spi_bus: defaults::adc::SPI_BUS,
+            chip_select: defaults::adc::CHIP_SELECT,
+            channel: defaults::adc::CHANNEL,
+            threshold: defaults::adc::THRESHOLD,
+            hysteresis: defaults::adc::HYSTERESIS,
+        }
+    }
+}
+
+impl AdcSettings {
+    /// Applies ADC settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, adc_config: &file_config::AdcConfig) {
+        if let Some(spi_bus) = adc_config.spi_bus {
+            self.spi_bus = spi_bus;
+        }
+
+        if let Some(chip_select) = adc_config.chip_select {
+            self.chip_select = chip_select;
+        }
+
+        if let Some(channel) = adc_config.channel {
+            self.channel = channel;
+        }
+
+        if let Some(threshold) = adc_config.threshold {
+            self.threshold = threshold;
+        }
+
+        if let Some(hysteresis) = adc_config.hysteresis {
+            self.hysteresis = hysteresis;
+        }
+    }
+
+    /// Sanity check the ADC settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if !cfg!(all(
+            target_os = "linux",
+            feature = "real-gpio",
+            not(feature = "mock-gpio")
+        )) {
+            vec.push(SanityError::AdcUnsupported);
+        }
+
+        for (setting, value, max) in [
+            (
+                "SPI bus",
+                self.spi_bus.into(),
+                defaults::adc::MAX_SPI_BUS.into(),
+            ),
+            (
+                "chip select",
+                self.chip_select.into(),
+                defaults::adc::MAX_CHIP_SELECT.into(),
+            ),
+            (
+                "channel",
+                self.channel.into(),
+                defaults::adc::MAX_CHANNEL.into(),
+            ),
+            ("threshold", self.threshold, defaults::adc::MAX_VALUE),
+        ] {
+            if value > max {
+                vec.push(SanityError::AdcOutOfRange {
+                    setting,
+                    value,
+                    max,
+                });
+            }
+        }
+
+        if self.hysteresis > self.threshold {
+            vec.push(SanityError::AdcHysteresisAboveThreshold {
+                hysteresis: self.hysteresis,
+                threshold: self.threshold,
+            });
+        }
+    }
+
+    /// Keeps the settings of the passed current ADC settings that can't
+    /// change while the ADC is open, returning whether any of them differed.
+    pub fn retain_fixed(&mut self, current: &AdcSettings) -> bool {
+        let differed = self.spi_bus != current.spi_bus
+            || self.chip_select != current.chip_select
+            || self.channel != current.channel
+            || self.threshold != current.threshold
+            || self.hysteresis != current.hysteresis;
+
+        self.spi_bus = current.spi_bus;
+        self.chip_select = current.chip_select;
+        self.channel = current.channel;
+        self.threshold = current.threshold;
+        self.hysteresis = current.hysteresis;
+        differed
+    }
+}
+
+/// Settings of the status socket.
+#[derive(Debug, Serialize)]
+pub struct StatusSettings {
+    /// Whether to listen on the status socket.
+    pub enabled: bool,
+
+    /// Number of recent alarms and restores to keep in memory.
+    pub history_size: usize,
+}
+
+impl Default for StatusSettings {
+    /// Default values for the status socket settings.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            history_size: defaults::status::HISTORY_SIZE,
+        }
+    }
+}
+
+impl StatusSettings {
+    /// Applies status socket settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, status_config: &file_config::StatusConfig) {
+        if let Some(enabled) = status_config.enabled {
+            self.enabled = enabled;
+        }
+
+        if let Some(history_size) = status_config.history_size {
+            self.history_size = history_size;
+        }
+    }
+}
+
+/// Settings of which signals trigger which runtime actions.
+#[derive(Debug, Serialize)]
+pub struct SignalsSettings {
+    /// Signal that reloads the configuration.
+    pub reload: Signal,
+
+    /// Signal that sends the alarm message through all notifiers as a test.
+    pub test: Signal,
+
+    /// Signal that prints the current runtime state.
+    pub dump_state: Signal,
+
+    /// Signal that enters maintenance mode, or leaves it if already in it.
+    pub maintenance: Signal,
+}
+
+impl Default for SignalsSettings {
+    /// Default values for the signal settings.
+    fn default() -> Self {
+        Self {
+            reload: Signal::Hup,
+            test: Signal::Usr1,
+            dump_state: Signal::Usr2,
+            maintenance: Signal::None,
+        }
+    }
+}
+
+impl SignalsSettings {
+    /// Applies signal settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, signals_config: &file_config::SignalsConfig) {
+        if let Some(reload) = signals_config.reload {
+            self.reload = reload;
+        }
+
+        if let Some(test) = signals_config.test {
+            self.test = test;
+        }
+
+        if let Some(dump_state) = signals_config.dump_state {
+            self.dump_state = dump_state;
+        }
+
+        if let Some(maintenance) = signals_config.maintenance {
+            self.maintenance = maintenance;
+        }
+    }
+
+    /// Sanity check the signal settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        let mapped = [self.reload, self.test, self.dump_state, self.maintenance];
+
+        for (i, signal) in mapped.iter().enumerate() {
+            if *signal != Signal::None && mapped[..i].contains(signal) {
+                vec.push(SanityError::DuplicateSignal { signal: *signal });
+            }
+        }
+    }
+}
+
+/// HTTP settings shared by all HTTP-based backends.
+#[derive(Debug, Serialize)]
+pub struct HttpSettings {
+    /// Timeout for HTTP requests.
+    pub timeout: Duration,
+
+    /// How much of a failed response's body to include in error messages, in bytes.
+    /// Zero leaves it out.
+    pub max_error_body_bytes: usize,
+
+    /// The HTTP status codes that count as delivered, overriding the default
+    /// of any 2xx status, if set.
+    pub success_statuses: Option<Vec<u16>>,
+
+    /// Timeouts of consecutive attempts to send to the same URL, the last one
+    /// repeating, overriding the fixed timeout if set.
+    pub retry_timeout_schedule: Option<Vec<Duration>>,
+}
+
+impl Default for HttpSettings {
+    /// Default values for the HTTP settings.
+    fn default() -> Self {
+        Self {
+            timeout: defaults::http::TIMEOUT,
+            max_error_body_bytes: defaults::http::MAX_ERROR_BODY_BYTES,
+            success_statuses: None,
+            retry_timeout_schedule: None,
+        }
+    }
+}
+
+impl HttpSettings {
+    /// Applies HTTP settings from the config file, overriding the default settings where specified.
+    fn apply_file(&mut self, http_config: &file_config::HttpConfig) {
+        if let Some(timeout) = http_config.timeout {
+            self.timeout = timeout;
+        }
+
+        if let Some(max_error_body_bytes) = http_config.max_error_body_bytes {
+            self.max_error_body_bytes = max_error_body_bytes;
+        }
+
+        if let Some(success_statuses) = &http_config.success_statuses {
+            self.success_statuses = Some(success_statuses.clone());
+        }
+
+        if let Some(schedule) = &http_config.retry_timeout_schedule {
+            self.retry_timeout_schedule = Some(schedule.iter().map(|timeout| **timeout).collect());
+        }
+    }
+
+    /// Returns whether redirects should be followed, which they shouldn't be
+    /// if a redirect status itself counts as delivered.
+    #[cfg(feature = "notifications")]
+    pub fn follow_redirects(&self) -> bool {
+        self.success_statuses
+            .as_ref()
+            .is_none_or(|statuses| !statuses.iter().any(|status| (300..400).contains(status)))
+    }
+
+    /// Sanity check the HTTP settings, returning a list of errors if any are found.
+    fn sanity_check(&self, vec: &mut Vec<SanityError>) {
+        if self.timeout == Duration::ZERO {
+            vec.push(SanityError::ZeroHttpTimeout);
+        }
+
+        if let Some(statuses) = &self.success_statuses {
+            if statuses.is_empty() {
+                vec.push(SanityError::EmptySuccessStatuses);
+            }
+
+            for &status in statuses {
+                if !(100..=599).contains(&status) {
+                    vec.push(SanityError::InvalidSuccessStatus { status });
+                }
+            }
+        }
+
+        if let Some(schedule) = &self.retry_timeout_schedule {
+            if schedule.is_empty() {
+                vec.push(SanityError::EmptyRetryTimeoutSchedule);
+            }
+
+            if schedule.contains(&Duration::ZERO) {
+                vec.push(SanityError::ZeroRetryTimeout);
+            }
+        }
+    }
+}
+
+/// Paths to resources, resolved at runtime.
+#[derive(Debug, Serialize)]
+pub struct PathBufs {
+    /// Path to the configuration directory, which contains the configuration file and other resources.
+    pub config_dir: PathBuf,
+
+    /// Path to the configuration file, resolved at runtime.
+    pub config_file: PathBuf,
+
+    /// Path to the Slack alarm message template file.
+    pub slack_alarm_template: PathBuf,
+
+    /// Path to the Slack restored message template file.
+    pub slack_restored_template: PathBuf,
+
+    /// Path to the alarm message template file, resolved at runtime.
+    pub batsign_alarm_template: PathBuf,
+
+    /// Path to the restored message template file, resolved at runtime.
+    pub batsign_restored_template: PathBuf,
+
+    /// Path to the webhook alarm payload template file.
+    pub webhook_alarm_template: PathBuf,
+
+    /// Path to the webhook restored payload template file.
+    pub webhook_restored_template: PathBuf,
+
+    /// Path to the cached copy of the remote Batsign URL list, resolved at runtime.
+    pub batsign_urls_cache: PathBuf,
+
+    /// Path to the alarm history file.
+    pub history_file: PathBuf,
+
+    /// Path to the record of sent alarms, kept across restarts.
+    pub sent_alarms_file: PathBuf,
+
+    /// Path to the lockfile of the running instance.
+    pub pid_file: PathBuf,
+
+    /// Path to the status socket.
+    pub status_socket: PathBuf,
+}
+
+impl Default for PathBufs {
+    /// Default values for the resource paths.
+    fn default() -> Self {
+        Self {
+            config_dir: PathBuf::new(),
+            config_file: PathBuf::new(),
+            slack_alarm_template: PathBuf::new(),
+            slack_restored_template: PathBuf::new(),
+            batsign_alarm_template: PathBuf::new(),
+            batsign_restored_template: PathBuf::new(),
+            webhook_alarm_template: PathBuf::new(),
+            webhook_restored_template: PathBuf::new(),
+            batsign_urls_cache: PathBuf::new(),
+            history_file: PathBuf::new(),
+            sent_alarms_file: PathBuf::new(),
+            pid_file: PathBuf::new(),
+            status_socket: PathBuf::new(),
+        }
+    }
+}
+
+/// Application settings, including defaults and sanity checks.
+#[derive(Debug, Serialize)]
+pub struct Settings {
+    /// GPIO settings.
+    pub gpio: GpioSettings,
+
+    /// Slack settings.
+    pub slack: SlackSettings,
+
+    /// Batsign settings.
+    pub batsign: BatsignSettings,
+
+    /// Webhook settings.
+    pub webhook: WebhookSettings,
+
+    /// Command settings.
+    pub command: CommandSettings,
+
+    /// Pipe settings.
+    pub pipe: PipeSettings,
+
+    /// Cross-backend notification settings.
+    pub notifications: NotificationsSettings,
+
+    /// History settings.
+    pub history: HistorySettings,
+
+    /// ADC settings.
+    pub adc: AdcSettings,
+
+    /// Status socket settings.
+    pub status: StatusSettings,
+
+    /// Signal settings.
+    pub signals: SignalsSettings,
+
+    /// HTTP settings.
+    pub http: HttpSettings,
+
+    /// Friendly names of GPIO pins, keyed by BCM pin number.
+    pub pin_names: BTreeMap<String, String>,
+
+    /// How durations are formatted in messages and terminal output.
+    pub duration_format: DurationFormat,
+
+    /// Whether to reload the configuration and templates whenever they change
+    /// on disk, as with `--watch-config`.
+    pub auto_reload: bool,
+
+    /// Paths to resources, resolved at runtime.
+    pub paths: PathBufs,
+
+    /// If true, the program will not send any Batsign notifications and will only print what it would do.
+    pub dry_run: bool,
+
+    /// Verbosity of terminal output.
+    pub verbosity: Verbosity,
+
+    /// If set, the monitor loop exits cleanly after running for this long.
+    pub run_for: Option<Duration>,
+
+    /// Whether the qualify holds were bypassed from the command line.
+    pub immediate: bool,
+
+    /// Whether durations outside their sensible ranges were allowed from the command line.
+    pub allow_extreme: bool,
+
+    /// Name of the profile applied on top of the config file, if any.
+    pub profile: Option<String>,
+
+    /// Whether running without a configuration directory, from the command
+    /// line and environment only.
+    pub no_config: bool,
+}
+
+impl Default for Settings {
+    /// Default values for settings, used as a base for applying config file and CLI overrides.
+    fn default() -> Self {
+        Self {
+            gpio: GpioSettings::default(),
+            slack: SlackSettings::default(),
+            batsign: BatsignSettings::default(),
+            webhook: WebhookSettings::default(),
+            command: CommandSettings::default(),
+            pipe: PipeSettings::default(),
+            notifications: NotificationsSettings::default(),
+            history: HistorySettings::default(),
+            adc: AdcSettings::default(),
+            status: StatusSettings::default(),
+            signals: SignalsSettings::default(),
+            http: HttpSettings::default(),
+            pin_names: BTreeMap::new(),
+            duration_format: DurationFormat::Compact,
+            auto_reload: false,
+            paths: PathBufs::default(),
+            dry_run: false,
+            verbosity: Verbosity::Info,
+            run_for: None,
+            immediate: false,
+            allow_extreme: false,
+            profile: None,
+            no_config: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Applies the configuration directory setting, resolving the resource paths
+    /// based on the provided directory or the default. This is used to set up
+    /// the resource paths before loading resources from disk.
+    pub fn inherit_config_dir(&mut self, config_dir: &Option<String>) -> Result<(), String> {
+        if let Some(dir) = config_dir {
+            self.paths.config_dir = PathBuf::from(dir);
+            return Ok(());
+        }
+
+        match file_config::resolve_default_config_directory_from_env() {
+            Ok(path) => {
+                self.paths.config_dir = path;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets up the settings to run without a configuration directory. There are
+    /// no files to keep history or sent alarms in or put the status socket next
+    /// to, and only Batsign can be given URLs without one, so the rest is turned
+    /// off. The templates keep their built-in defaults.
+    pub fn apply_no_config(&mut self) {
+        self.no_config = true;
+        self.slack.enabled = false;
+        self.webhook.enabled = false;
+        self.command.enabled = false;
+        self.pipe.enabled = false;
+        self.history.enabled = false;
+        self.status.enabled = false;
+        self.notifications.restart_dedupe_window = None;
+    }
+
+    /// Applies settings from the environment, currently any additional Batsign
+    /// URLs in the variable named by `defaults::batsign::URLS_ENV`.
+    pub fn apply_env(&mut self) {
+        self.apply_env_vars(|name| env::var_os(name));
+    }
+
+    /// Applies settings from environment variables as looked up by the passed
+    /// function, so that tests needn't touch the environment of the process.
+    fn apply_env_vars(&mut self, var: impl Fn(&str) -> Option<OsString>) {
+        #[cfg(feature = "notifications")]
+        if let Some(urls) = var(defaults::batsign::URLS_ENV) {
+            self.batsign
+                .extend_urls(split_url_list(&urls.to_string_lossy()));
+        }
+
+        #[cfg(not(feature = "notifications"))]
+        let _ = var;
+    }
+
+    /// Clean up settings by trimming whitespace from URLs and removing empty URLs.
+    pub fn clean_up(&mut self) {
+        self.slack.trim_urls();
+        self.batsign.trim_urls();
+        self.webhook.trim_urls();
+    }
+
+    /// Sanity check settings, returning a list of errors if any are found.
+    pub fn sanity_check(&self) -> Result<(), Vec<SanityError>> {
+        let mut vec = Vec::new();
+
+        self.gpio.sanity_check(&mut vec);
+
+        if self.gpio.input_source == InputSource::Adc {
+            self.adc.sanity_check(&mut vec);
+        }
+
+        if !self.allow_extreme && self.gpio.extreme_durations == ExtremeDurations::Error {
+            for (setting, value, limit) in self.gpio.extreme_durations() {
+                vec.push(SanityError::ExtremeDuration {
+                    setting,
+                    value,
+                    limit,
+                });
+            }
+        }
+
+        #[cfg(feature = "notifications")]
+        {
+            self.slack.sanity_check(&mut vec);
+            self.batsign.sanity_check(&mut vec);
+            self.webhook.sanity_check(&mut vec);
+        }
+
+        self.command.sanity_check(&mut vec);
+        self.pipe.sanity_check(&mut vec);
+        self.notifications.sanity_check(&mut vec);
+        self.history.sanity_check(&mut vec);
+        self.signals.sanity_check(&mut vec);
+        self.http.sanity_check(&mut vec);
+
+        for key in self.pin_names.keys() {
+            if !key.parse::<u8>().is_ok_and(|pin| pin <= MAX_GPIO_PIN) {
+                vec.push(SanityError::InvalidPinNameKey { key: key.clone() });
+            }
+        }
+
+        // HTTP-based backends don't count if they weren't compiled in.
+        let http_enabled = cfg!(feature = "notifications")
+            && (self.slack.enabled || self.batsign.enabled || self.webhook.enabled);
+
+        if !http_enabled && !self.command.enabled && !self.pipe.enabled {
+            vec.push(SanityError::NoBackendEnabled);
+        }
+
+        if vec.is_empty() { Ok(()) } else { Err(vec) }
+    }
+
+    /// Checks settings for likely mistakes that don't prevent running,
+    /// returning a list of warnings.
+    pub fn warnings(&self) -> Vec<SanityWarning> {
+        let mut vec = Vec::new();
+
+        if self.immediate {
+            vec.push(SanityWarning::HoldsBypassed);
+        }
+
+        if self.gpio.pull == PullMode::None {
+            vec.push(SanityWarning::FloatingInput);
+        }
+
+        if !self.allow_extreme {
+            self.gpio.warnings(&mut vec);
+        }
+
+        if !self.allow_extreme && self.gpio.extreme_durations == ExtremeDurations::Warn {
+            for (setting, value, limit) in self.gpio.extreme_durations() {
+                vec.push(SanityWarning::ExtremeDuration {
+                    setting,
+                    value,
+                    limit,
+                });
+            }
+        }
+
+        self.batsign.warnings(&mut vec);
+
+        vec
+    }
+
+    /// Returns the configuration file and the template files, which are
+    /// watched for changes with `--watch-config`.
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        vec![
+            self.paths.config_file.clone(),
+            self.paths.slack_alarm_template.clone(),
+            self.paths.slack_restored_template.clone(),
+            self.paths.batsign_alarm_template.clone(),
+            self.paths.batsign_restored_template.clone(),
+            self.paths.webhook_alarm_template.clone(),
+            self.paths.webhook_restored_template.clone(),
+        ]
+    }
+
+    /// Returns the template files along with what they are and the built-in
+    /// default of each, as written by `--save`.
+    pub fn template_files(&self) -> [(&'static str, &Path, &'static str); 6] {
+        [
+            (
+                "Slack alarm template",
+                &self.paths.slack_alarm_template,
+                defaults::slack::ALARM_MESSAGE_TEMPLATE_BODY,
+            ),
+            (
+                "Slack restored template",
+                &self.paths.slack_restored_template,
+                defaults::slack::RESTORED_MESSAGE_TEMPLATE_BODY,
+            ),
+            (
+                "Batsign alarm template",
+                &self.paths.batsign_alarm_template,
+                defaults::batsign::ALARM_MESSAGE_TEMPLATE_BODY,
+            ),
+            (
+                "Batsign restored template",
+                &self.paths.batsign_restored_template,
+                defaults::batsign::RESTORED_MESSAGE_TEMPLATE_BODY,
+            ),
+            (
+                "Webhook alarm payload template",
+                &self.paths.webhook_alarm_template,
+                defaults::webhook::ALARM_PAYLOAD_TEMPLATE_BODY,
+            ),
+            (
+                "Webhook restored payload template",
+                &self.paths.webhook_restored_template,
+                defaults::webhook::RESTORED_PAYLOAD_TEMPLATE_BODY,
+            ),
+        ]
+    }
+
+    /// Returns whether alarms of the monitored pin are to be sent to a
+    /// channel accepting the passed severities.
+    pub fn routes_to(&self, severities: &[Severity]) -> bool {
+        severities.contains(&self.gpio.severity)
+    }
+
+    /// Returns the friendly name of the passed GPIO pin, or `GPIO<n>` if it has none.
+    ///
+    /// The monitored pin is called by its `pin_label`, if one is set.
+    pub fn pin_name(&self, pin_number: u8) -> String {
+        if pin_number == self.gpio.pin_number
+            && let Some(pin_label) = &self.gpio.pin_label
+        {
+            return pin_label.clone();
+        }
+
+        self.pin_names
+            .get(&pin_number.to_string())
+            .cloned()
+            .unwrap_or_else(|| format!("GPIO{pin_number}"))
+    }
+
+    /// Returns whether debug information should be printed.
+    pub fn debug(&self) -> bool {
+        self.verbosity >= Verbosity::Debug
+    }
+
+    /// Returns whether every single pin reading should be printed.
+    pub fn trace(&self) -> bool {
+        self.verbosity >= Verbosity::Trace
+    }
+
+    /// Returns whether normal, non-error output should be suppressed.
+    pub fn quiet(&self) -> bool {
+        self.verbosity == Verbosity::Quiet
+    }
+
+    /// Returns the key GPIO settings as aligned lines, as shown by `print`
+    /// and substituted for the `{config_summary}` placeholder.
+    pub fn config_summary(&self) -> String {
+        let mut lines = vec![
+            format!("Pin number                   {}", self.gpio.pin_number),
+            format!(
+                "Pin name                     {}",
+                self.pin_name(self.gpio.pin_number)
+            ),
+            format!("Severity                     {}", self.gpio.severity),
+            format!(
+                "Poll interval                {}",
+                format_duration(self.gpio.poll_interval, self.duration_format)
+            ),
+        ];
+
+        if let Some(alarm_poll_interval) = self.gpio.alarm_poll_interval {
+            lines.push(format!(
+                "Alarm poll interval          {}",
+                format_duration(alarm_poll_interval, self.duration_format)
+            ));
+        }
+
+        match self.gpio.m_of_n() {
+            Some((m, n)) => lines.push(format!("Qualify                      {m} of {n} readings")),
+            None => lines.extend([
+                format!(
+                    "Qualify-HIGH hold            {}",
+                    format_duration(self.gpio.qualify_high_hold, self.duration_format)
+                ),
+                format!(
+                    "Qualify-LOW hold             {}",
+                    format_duration(self.gpio.qualify_low_hold, self.duration_format)
+                ),
+            ]),
+        }
+
+        for (label, level) in [("HIGH", Level::High), ("LOW ", Level::Low)] {
+            let (best, worst) = self.gpio.qualify_latency(level);
+
+            lines.push(format!(
+                "Effective qualify-{label}       {} to {}",
+                format_duration(best, self.duration_format),
+                format_duration(worst, self.duration_format)
+            ));
+        }
+
+        lines.extend([
+            format!("Input source                 {}", self.gpio.input_source),
+            format!(
+                "Input mode                   {}",
+                InputMode::of(self.gpio.baseline_level, self.gpio.pull)
+                    .map_or_else(|| String::from("(custom)"), |mode| mode.to_string())
+            ),
+            format!("Baseline level               {}", self.gpio.baseline_level),
+            format!("Pull resistor                {}", self.gpio.pull),
+            format!(
+                "Sysfs GPIO directory         {}",
+                self.gpio
+                    .sysfs_base
+                    .as_ref()
+                    .map_or("(none; using rppal)".to_string(), |base| base
+                        .display()
+                        .to_string())
+            ),
+            format!(
+                "Notify on startup alarm      {}",
+                self.gpio.notify_on_startup_alarm
+            ),
+            format!(
+                "Read error timeout           {}",
+                format_duration(self.gpio.read_error_timeout, self.duration_format)
+            ),
+            format!(
+                "Post-notification dead time  {}",
+                format_duration(self.gpio.post_notify_deadtime, self.duration_format)
+            ),
+            format!(
+                "Startup delay                {}",
+                format_duration(self.gpio.startup_delay, self.duration_format)
+            ),
+            format!(
+                "Startup jitter               {}",
+                format_duration(self.gpio.startup_jitter, self.duration_format)
+            ),
+            format!(
+                "Calibration duration         {}",
+                format_duration(self.gpio.calibration_duration, self.duration_format)
+            ),
+            format!(
+                "Flap detection               {}",
+                self.gpio
+                    .flap_threshold
+                    .map_or("(off)".to_string(), |threshold| format!(
+                        "more than {threshold} changes within {}",
+                        format_duration(self.gpio.flap_window, self.duration_format)
+                    ))
+            ),
+            format!(
+                "Coalesce window              {}",
+                self.gpio
+                    .coalesce_window
+                    .map_or("(off)".to_string(), |window| format_duration(
+                        window,
+                        self.duration_format
+                    ))
+            ),
+            format!(
+                "Extreme durations            {}{}",
+                self.gpio.extreme_durations,
+                if self.allow_extreme {
+                    " (allowed by --allow-extreme)"
+                } else {
+                    ""
+                }
+            ),
+        ]);
+
+        lines.join("\n")
+    }
+
+    /// Print the settings in a human-readable format.
+    pub fn print(&self) {
+        if self.debug() {
+            // If debug is enabled, print the entire settings struct with all details.
+            println!("{:#?}", self);
+            return;
+        }
+
+        if self.no_config {
+            println!("Using no configuration directory (--no-config)");
+        } else {
+            println!(
+                "Using configuration directory {}",
+                self.paths.config_dir.display()
+            );
+        }
+        println!(
+            "Profile                      {}",
+            self.profile.as_deref().unwrap_or("(none)")
+        );
+        println!("Duration format              {}", self.duration_format);
+        println!("Auto-reload                  {}", self.auto_reload);
+
+        println!();
+        println!("-- GPIO --");
+        println!("{}", self.config_summary());
+
+        if self.gpio.input_source == InputSource::Adc {
+            println!();
+            println!("-- ADC --");
+            println!("SPI bus                      {}", self.adc.spi_bus);
+            println!("Chip select                  {}", self.adc.chip_select);
+            println!("Channel                      {}", self.adc.channel);
+            println!("Threshold                    {}", self.adc.threshold);
+            println!("Hysteresis                   {}", self.adc.hysteresis);
+        }
+
+        println!();
+        println!("-- Slack --");
+        println!("Enabled                      {}", self.slack.enabled);
+        println!("Webhook URLs                 {:?}", self.slack.urls);
+        println!(
+            "Notification interval        {}",
+            format_duration(self.slack.notification_interval, self.duration_format)
+        );
+
+        println!(
+            "Notification retry interval  {}",
+            format_duration(self.slack.retry_interval, self.duration_format)
+        );
+        println!(
+            "Severities                   {}",
+            format_severities(&self.slack.severities)
+        );
+
+        println!();
+        println!("-- Batsign --");
+        println!("Enabled                      {}", self.batsign.enabled);
+        println!("URLs                         {:?}", self.batsign.urls);
+        println!(
+            "URL source                   {}",
+            self.batsign.urls_source.as_deref().unwrap_or("(none)")
+        );
+        println!(
+            "Request style                {}",
+            self.batsign.request_style
+        );
+        println!("Line ending                  {}", self.batsign.line_ending);
+
+        match self.batsign.mode {
+            BatsignMode::All => println!("Mode                         all"),
+            BatsignMode::Failover => println!(
+                "Mode                         failover, skipping failed URLs for {}",
+                format_duration(self.batsign.failover_health_window, self.duration_format)
+            ),
+        }
+
+        if self.batsign.hmac_secret.is_some() {
+            println!(
+                "HMAC signature               {}, in {}",
+                self.batsign.hmac_algorithm, self.batsign.hmac_header
+            );
+        } else {
+            println!("HMAC signature               (unsigned)");
+        }
+        println!(
+            "Notification interval        {}",
+            format_duration(self.batsign.notification_interval, self.duration_format)
+        );
+        println!(
+            "Notification retry interval  {}",
+            format_duration(self.batsign.retry_interval, self.duration_format)
+        );
+        println!(
+            "Severities                   {}",
+            format_severities(&self.batsign.severities)
+        );
+
+        if self.batsign.require_reachable_on_start {
+            println!(
+                "Require reachable on start   yes, retrying for {}",
+                format_duration(self.batsign.reachable_wait, self.duration_format)
+            );
+        } else {
+            println!("Require reachable on start   no");
+        }
+
+        if self.batsign.pin_dns {
+            println!(
+                "Pin DNS                      yes, re-resolving every {}",
+                format_duration(
+                    defaults::batsign::DNS_REFRESH_INTERVAL,
+                    self.duration_format
+                )
+            );
+        } else {
+            println!("Pin DNS                      no");
+        }
+
+        println!();
+        println!("-- Webhook --");
+        println!("Enabled                      {}", self.webhook.enabled);
+        println!("URLs                         {:?}", self.webhook.urls);
+        println!("Gzip URLs                    {:?}", self.webhook.gzip_urls);
+        println!(
+            "Gzip payloads over           {} bytes",
+            self.webhook.gzip_min_bytes
+        );
+        println!(
+            "Notification interval        {}",
+            format_duration(self.webhook.notification_interval, self.duration_format)
+        );
+        println!(
+            "Notification retry interval  {}",
+            format_duration(self.webhook.retry_interval, self.duration_format)
+        );
+        println!(
+            "Severities                   {}",
+            format_severities(&self.webhook.severities)
+        );
+
+        println!();
+        println!("-- Command --");
+        println!("Enabled                      {}", self.command.enabled);
+        println!(
+            "Alarm command                {:?}",
+            self.command.alarm_command
+        );
+        println!(
+            "Restore command              {:?}",
+            self.command.restored_command
+        );
+        println!(
+            "Notification interval        {}",
+            format_duration(self.command.notification_interval, self.duration_format)
+        );
+        println!(
+            "Notification retry interval  {}",
+            format_duration(self.command.retry_interval, self.duration_format)
+        );
+        println!(
+            "Severities                   {}",
+            format_severities(&self.command.severities)
+        );
+
+        println!();
+        println!("-- Pipe --");
+        println!("Enabled                      {}", self.pipe.enabled);
+        println!("Command                      {:?}", self.pipe.command);
+        println!(
+            "Notification interval        {}",
+            format_duration(self.pipe.notification_interval, self.duration_format)
+        );
+        println!(
+            "Notification retry interval  {}",
+            format_duration(self.pipe.retry_interval, self.duration_format)
+        );
+        println!(
+            "Severities                   {}",
+            format_severities(&self.pipe.severities)
+        );
+
+        println!();
+        println!("-- Notifications --");
+        println!(
+            "Max message size             {} bytes",
+            self.notifications.max_message_bytes
+        );
+        println!(
+            "Include config summary       {}",
+            self.notifications.include_config_in_notification
+        );
+        println!(
+            "Send restored notifications  {}",
+            self.notifications.send_restored
+        );
+        println!(
+            "Notify on partial failure    {}",
+            self.notifications.notify_on_partial_failure
+        );
+        println!(
+            "Notify on startup failure    {}",
+            self.notifications.notify_on_startup_failure
+        );
+        println!(
+            "Status report interval       {}",
+            self.notifications
+                .status_report_interval
+                .map_or("(off)".to_string(), |interval| format_duration(
+                    interval,
+                    self.duration_format
+                ))
+        );
+        println!(
+            "Dedupe identical messages    {}",
+            self.notifications
+                .dedupe_window()
+                .map_or("(off)".to_string(), |window| format!(
+                    "within {}",
+                    format_duration(window, self.duration_format)
+                ))
+        );
+        println!(
+            "Restart dedupe window        {}",
+            self.notifications
+                .restart_dedupe_window
+                .map_or("(off)".to_string(), |window| format_duration(
+                    window,
+                    self.duration_format
+                ))
+        );
+        println!(
+            "Send queue size              {}",
+            self.notifications.send_queue_size
+        );
+        println!(
+            "Send timeout                 {}",
+            format_duration(self.notifications.send_timeout, self.duration_format)
+        );
+        println!(
+            "Maintenance until            {}",
+            self.notifications
+                .maintenance_until
+                .as_deref()
+                .unwrap_or("(none)")
+        );
+        println!(
+            "Maintenance duration         {}",
+            format_duration(
+                self.notifications.maintenance_duration,
+                self.duration_format
+            )
+        );
+        println!(
+            "Include log tail             {}",
+            match self.notifications.include_log_tail {
+                0 => "(off)".to_string(),
+                lines => format!("last {lines} events"),
+            }
+        );
+
+        println!();
+        println!("-- History --");
+        println!("Enabled                      {}", self.history.enabled);
+        println!(
+            "History file                 {}",
+            self.paths.history_file.display()
+        );
+        println!("Max entries                  {}", self.history.max_entries);
+        println!(
+            "Compress rotated logs        {}",
+            self.history.compress_rotated_logs
+        );
+
+        println!();
+        println!("-- Status --");
+        println!("Enabled                      {}", self.status.enabled);
+        println!(
+            "Status socket                {}",
+            self.paths.status_socket.display()
+        );
+        println!("Recent history size          {}", self.status.history_size);
+
+        println!();
+        println!("-- Signals --");
+        println!("Shutdown                     SIGINT, SIGTERM");
+        println!("Reload                       {}", self.signals.reload);
+        println!("Test notification            {}", self.signals.test);
+        println!("Dump state                   {}", self.signals.dump_state);
+        println!("Maintenance                  {}", self.signals.maintenance);
+
+        println!();
+        println!("-- HTTP --");
+        println!(
+            "Timeout                      {}",
+            format_duration(self.http.timeout, self.duration_format)
+        );
+        println!(
+            "Max error body size          {} bytes",
+            self.http.max_error_body_bytes
+        );
+        println!(
+            "Success statuses             {}",
+            self.http
+                .success_statuses
+                .as_ref()
+                .map_or("2xx".to_string(), |statuses| statuses
+                    .iter()
+                    .map(u16::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "))
+        );
+        println!(
+            "Retry timeout schedule       {}",
+            self.http
+                .retry_timeout_schedule
+                .as_ref()
+                .map_or("(off)".to_string(), |schedule| schedule
+                    .iter()
+                    .map(|&timeout| format_duration(timeout, self.duration_format))
+                    .collect::<Vec<_>>()
+                    .join(", "))
+        );
+
+        if let Some(run_for) = self.run_for {
+            println!();
+            println!(
+                "(RUNNING FOR {})",
+                format_duration(run_for, self.duration_format)
+            );
+        }
+
+        if self.dry_run {
+            println!();
+            println!("(DRY RUN)");
+        }
+    }
+
+    /// Resolves the resource paths based on the config directory.
+    pub fn resolve_resource_paths(&mut self) {
+        self.paths.config_file = self.paths.config_dir.join(defaults::CONFIG_FILENAME);
+
+        self.paths.slack_alarm_template = self
+            .paths
+            .config_dir
+            .join(defaults::slack::ALARM_MESSAGE_TEMPLATE_FILENAME);
+
+        self.paths.slack_restored_template = self
+            .paths
+            .config_dir
+            .join(defaults::slack::RESTORED_MESSAGE_TEMPLATE_FILENAME);
+
+        self.paths.batsign_alarm_template = self
+            .paths
+            .config_dir
+            .join(defaults::batsign::ALARM_MESSAGE_TEMPLATE_FILENAME);
+
+        self.paths.batsign_restored_template = self
+            .paths
+            .config_dir
+            .join(defaults::batsign::RESTORED_MESSAGE_TEMPLATE_FILENAME);
+
+        self.paths.webhook_alarm_template = self
+            .paths
+            .config_dir
+            .join(defaults::webhook::ALARM_PAYLOAD_TEMPLATE_FILENAME);
+
+        self.paths.webhook_restored_template = self
+            .paths
+            .config_dir
+            .join(defaults::webhook::RESTORED_PAYLOAD_TEMPLATE_FILENAME);
+
+        self.paths.batsign_urls_cache = self
+            .paths
+            .config_dir
+            .join(defaults::batsign::URLS_CACHE_FILENAME);
+
+        self.paths.history_file = self.paths.config_dir.join(defaults::history::FILENAME);
+        self.paths.sent_alarms_file = self
+            .paths
+            .config_dir
+            .join(defaults::notifications::SENT_ALARMS_FILENAME);
+        self.paths.pid_file = self.paths.config_dir.join(defaults::PID_FILENAME);
+        self.paths.status_socket = self
+            .paths
+            .config_dir
+            .join(defaults::status::SOCKET_FILENAME);
+    }
+
+    /// Fetches the list of Batsign URLs from the configured remote source, if any,
+    /// and appends them to the configured URLs.
+    ///
+    /// A successfully fetched list is cached to disk. If the fetch fails, the
+    /// last cached list is used instead, and the fetch error is returned
+    /// alongside the number of URLs that were read from the cache.
+    #[cfg(feature = "notifications")]
+    pub fn load_remote_batsign_urls(&mut self) -> Result<usize, (String, usize)> {
+        let Some(source) = self.batsign.urls_source.clone() else {
+            return Ok(0);
+        };
+
+        match backend::batsign::fetch_url_list(&source, defaults::batsign::URLS_SOURCE_TIMEOUT) {
+            Ok(body) => {
+                let urls = backend::batsign::parse_url_list(&body);
+                let num_urls = urls.len();
+
+                if let Err(e) = file_config::write_atomic(&self.paths.batsign_urls_cache, &body) {
+                    eprintln!(
+                        "[!] Failed to cache Batsign URL list to {}: {e}",
+                        self.paths.batsign_urls_cache.display()
+                    );
+                }
+
+                self.batsign.extend_urls(urls);
+                Ok(num_urls)
+            }
+            Err(e) => {
+                let urls = match fs::read_to_string(&self.paths.batsign_urls_cache) {
+                    Ok(body) => backend::batsign::parse_url_list(&body),
+                    Err(_) => Vec::new(),
+                };
+
+                let num_urls = urls.len();
+                self.batsign.extend_urls(urls);
+                Err((e, num_urls))
+            }
+        }
+    }
+
+    /// Applies per-channel template path, history file and status socket overrides from the config file,
+    /// followed by those of the named profile, if any.
+    ///
+    /// Relative paths are resolved against the configuration directory.
+    /// Files without overrides keep their default paths.
+    pub fn apply_path_overrides(
+        &mut self,
+        file_config: &Option<file_config::FileConfig>,
+        profile: Option<&str>,
+    ) {
+        let Some(file_config) = file_config else {
+            return;
+        };
+
+        self.apply_config_path_overrides(file_config);
+
+        if let Some(profile) = profile.and_then(|name| file_config.profile(name)) {
+            self.apply_config_path_overrides(profile);
+        }
+    }
+
+    /// Applies the path overrides of a single config file table.
+    fn apply_config_path_overrides(&mut self, file_config: &file_config::FileConfig) {
+        let overrides = [
+            (
+                &file_config.slack.alarm_template,
+                &mut self.paths.slack_alarm_template,
+            ),
+            (
+                &file_config.slack.restored_template,
+                &mut self.paths.slack_restored_template,
+            ),
+            (
+                &file_config.batsign.alarm_template,
+                &mut self.paths.batsign_alarm_template,
+            ),
+            (
+                &file_config.batsign.restored_template,
+                &mut self.paths.batsign_restored_template,
+            ),
+            (
+                &file_config.webhook.alarm_template,
+                &mut self.paths.webhook_alarm_template,
+            ),
+            (
+                &file_config.webhook.restored_template,
+                &mut self.paths.webhook_restored_template,
+            ),
+            (&file_config.history.file, &mut self.paths.history_file),
+            (&file_config.status.socket, &mut self.paths.status_socket),
+        ];
+
+        for (file, path) in overrides {
+            if let Some(file) = file {
+                *path = self.paths.config_dir.join(file);
+            }
+        }
+    }
+
+    /// Loads the message templates from disk, returning a description, the path
+    /// and the error of each file that could not be read.
+    pub fn load_resources_from_disk(&mut self) -> Vec<(&'static str, PathBuf, io::Error)> {
+        let mut vec = Vec::new();
+
+        match read_to_trimmed_string(&self.paths.slack_alarm_template) {
+            Ok(s) => self.slack.alarm_message_template_body = s,
+            Err(e) => vec.push((
+                "Slack alarm template",
+                self.paths.slack_alarm_template.clone(),
+                e,
+            )),
+        };
+
+        match read_to_trimmed_string(&self.paths.slack_restored_template) {
+            Ok(s) => self.slack.restored_message_template_body = s,
+            Err(e) => vec.push((
+                "Slack restored template",
+                self.paths.slack_restored_template.clone(),
+                e,
+            )),
+        };
+
+        match read_to_trimmed_string(&self.paths.batsign_alarm_template) {
+            Ok(s) => self.batsign.alarm_message_template_body = s,
+            Err(e) => vec.push((
+                "Batsign alarm template",
+                self.paths.batsign_alarm_template.clone(),
+                e,
+            )),
+        };
+
+        match read_to_trimmed_string(&self.paths.batsign_restored_template) {
+            Ok(s) => self.batsign.restored_message_template_body = s,
+            Err(e) => vec.push((
+                "Batsign restored template",
+                self.paths.batsign_restored_template.clone(),
+                e,
+            )),
+        };
+
+        // The webhook templates were added later, so fall back to the defaults
+        // if they don't exist rather than failing for existing installations.
+        match read_to_trimmed_string(&self.paths.webhook_alarm_template) {
+            Ok(s) => self.webhook.alarm_payload_template_body = s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => vec.push((
+                "Webhook alarm payload template",
+                self.paths.webhook_alarm_template.clone(),
+                e,
+            )),
+        };
+
+        match read_to_trimmed_string(&self.paths.webhook_restored_template) {
+            Ok(s) => self.webhook.restored_payload_template_body = s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => vec.push((
+                "Webhook restored payload template",
+                self.paths.webhook_restored_template.clone(),
+                e,
+            )),
+        };
+
+        vec
+    }
+
+    /// Applies config file settings to the default settings, followed by those
+    /// of the named profile, if any. The profile must exist in the file.
+    pub fn apply_file(
+        &mut self,
+        file_config: &Option<file_config::FileConfig>,
+        profile: Option<&str>,
+    ) {
+        let Some(file_config) = file_config else {
+            return;
+        };
+
+        self.apply_file_config(file_config);
+
+        if let Some(name) = profile
+            && let Some(profile_config) = file_config.profile(name)
+        {
+            self.apply_file_config(profile_config);
+            self.profile = Some(name.to_string());
+        }
+    }
+
+    /// Applies the settings of a single config file table, overriding the current settings where specified.
+    fn apply_file_config(&mut self, file_config: &file_config::FileConfig) {
+        self.gpio.apply_file(&file_config.gpio);
+        self.slack.apply_file(&file_config.slack);
+        self.batsign.apply_file(&file_config.batsign);
+        self.webhook.apply_file(&file_config.webhook);
+        self.command.apply_file(&file_config.command);
+        self.pipe.apply_file(&file_config.pipe);
+        self.notifications.apply_file(&file_config.notifications);
+        self.history.apply_file(&file_config.history);
+        self.adc.apply_file(&file_config.adc);
+        self.status.apply_file(&file_config.status);
+        self.signals.apply_file(&file_config.signals);
+        self.http.apply_file(&file_config.http);
+
+        if let Some(duration_format) = file_config.duration_format {
+            self.duration_format = duration_format;
+        }
+
+        if let Some(auto_reload) = file_config.auto_reload {
+            self.auto_reload = auto_reload;
+        }
+
+        for (pin, name) in &file_config.pin_names {
+            let name = name.trim();
+
+            if !name.is_empty() {
+                self.pin_names
+                    .insert(pin.trim().to_string(), name.to_string());
+            }
+        }
     }
 
     /// Applies CLI settings, returning the resulting settings.
@@ -511,7 +3298,48 @@ impl Settings {
         // Config directory is applied separately in `inherit_config_dir`
         //because it affects how other settings are loaded from disk.
         self.dry_run = cli.dry_run;
-        self.debug = cli.debug;
+        self.verbosity = Verbosity::from_cli(cli);
+        self.run_for = cli.run_for;
+        self.allow_extreme = cli.allow_extreme;
+
+        #[cfg(feature = "notifications")]
+        {
+            if cli.replace_urls {
+                self.batsign.urls.clear();
+            }
+
+            self.batsign.extend_urls(cli.batsign_url.clone());
+        }
+
+        if cli.since_boot {
+            self.gpio.notify_on_startup_alarm = true;
+        }
+
+        if let Some(input_mode) = cli.input_mode {
+            self.gpio.baseline_level = input_mode.baseline_level();
+            self.gpio.pull = input_mode.pull();
+        }
+
+        if cli.no_restored {
+            self.notifications.send_restored = false;
+        }
+
+        if let Some(startup_delay) = cli.startup_delay {
+            self.gpio.startup_delay = startup_delay;
+        }
+
+        if let Some(startup_jitter) = cli.startup_jitter {
+            self.gpio.startup_jitter = startup_jitter;
+        }
+
+        // `--immediate` conflicts with `--save`, so the holds zeroed here
+        // never make it into the configuration file.
+        if cli.immediate {
+            self.immediate = true;
+            self.gpio.qualify_mode = QualifyMode::Hold;
+            self.gpio.qualify_high_hold = Duration::ZERO;
+            self.gpio.qualify_low_hold = Duration::ZERO;
+        }
     }
 }
 
@@ -520,8 +3348,30 @@ fn read_to_trimmed_string(path: &Path) -> io::Result<String> {
     Ok(fs::read_to_string(path)?.trim().to_string())
 }
 
+/// Splits a list of URLs separated by whitespace or commas, skipping empty entries.
+#[cfg(feature = "notifications")]
+pub fn split_url_list(list: &str) -> Vec<String> {
+    list.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Formats a list of severities as a comma-separated list, or `(none)` if it is empty.
+fn format_severities(severities: &[Severity]) -> String {
+    if severities.is_empty() {
+        return "(none)".to_string();
+    }
+
+    severities
+        .iter()
+        .map(Severity::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Trims whitespace from each string in the vector and removes any empty strings, returning a new vector.
-fn trim_vec_of_strings(vec: &[String]) -> Vec<String> {
+pub fn trim_vec_of_strings(vec: &[String]) -> Vec<String> {
     vec.iter()
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
@@ -530,6 +3380,646 @@ fn trim_vec_of_strings(vec: &[String]) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_sanity_check_defaults() {
+        let settings = Settings::default();
+        let errors = settings.sanity_check().unwrap_err();
+
+        // Slack and Batsign are enabled by default but have no URLs
+        assert_eq!(
+            errors,
+            vec![SanityError::MissingSlackUrl, SanityError::MissingBatsignUrl]
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "notifications"))]
+    fn test_sanity_check_defaults_without_notifications() {
+        let settings = Settings::default();
+        let errors = settings.sanity_check().unwrap_err();
+
+        // Slack and Batsign are enabled by default but not compiled in
+        assert_eq!(errors, vec![SanityError::NoBackendEnabled]);
+    }
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_sanity_check_errors() {
+        let mut settings = Settings::default();
+        settings.gpio.pin_number = 40;
+        settings.gpio.poll_interval = Duration::ZERO;
+        settings.slack.enabled = false;
+        settings.batsign.urls = vec!["batsign.me/at/test@example.com/token".to_string()];
+        settings.http.timeout = Duration::ZERO;
+        settings.http.success_statuses = Some(vec![200, 2000]);
+        settings.http.retry_timeout_schedule = Some(vec![Duration::from_secs(5), Duration::ZERO]);
+        settings
+            .pin_names
+            .insert("boiler".to_string(), "24".to_string());
+
+        let errors = settings.sanity_check().unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                SanityError::InvalidPin {
+                    pin_number: 40,
+                    max: 27
+                },
+                SanityError::ZeroPollInterval,
+                SanityError::InvalidUrl {
+                    kind: "Batsign URL",
+                    url: "batsign.me/at/test@example.com/token".to_string()
+                },
+                SanityError::ZeroHttpTimeout,
+                SanityError::InvalidSuccessStatus { status: 2000 },
+                SanityError::ZeroRetryTimeout,
+                SanityError::InvalidPinNameKey {
+                    key: "boiler".to_string()
+                },
+            ]
+        );
+
+        assert_eq!(
+            errors[2].to_string(),
+            "Batsign URL \"batsign.me/at/test@example.com/token\" does not seem to be a valid URL."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_warnings_missing_subject_line() {
+        let mut settings = Settings::default();
+        assert!(settings.warnings().is_empty());
+
+        settings.batsign.alarm_message_template_body = "PellX Alarm\nBurner is down.".to_string();
+        settings.batsign.restored_message_template_body = "Subject:\nRestored.\n".to_string();
+        assert_eq!(
+            settings.warnings(),
+            vec![
+                SanityWarning::MissingSubjectLine { name: "alarm" },
+                SanityWarning::MissingSubjectLine { name: "restored" },
+            ]
+        );
+
+        settings.batsign.enabled = false;
+        assert!(settings.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_warnings_hold_shorter_than_poll_interval() {
+        let mut settings = Settings::default();
+        settings.gpio.poll_interval = Duration::from_secs(1);
+        settings.gpio.qualify_high_hold = Duration::from_millis(500);
+        settings.gpio.qualify_low_hold = Duration::ZERO;
+
+        // Still runs, qualifying on the next poll.
+        assert!(
+            !settings
+                .sanity_check()
+                .err()
+                .unwrap_or_default()
+                .iter()
+                .any(|e| e.to_string().contains("poll interval"))
+        );
+        assert_eq!(
+            settings.warnings(),
+            vec![SanityWarning::HoldShorterThanPollInterval {
+                level: "HIGH",
+                hold: Duration::from_millis(500),
+                poll_interval: Duration::from_secs(1),
+            }]
+        );
+
+        settings.allow_extreme = true;
+        assert!(settings.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_extreme_durations() {
+        let mut settings = Settings::default();
+        assert!(settings.gpio.extreme_durations().is_empty());
+
+        settings.gpio.qualify_high_hold = Duration::from_secs(10 * 3600);
+        settings.gpio.poll_interval = Duration::from_millis(1);
+        let extreme = SanityWarning::ExtremeDuration {
+            setting: "qualify_high_hold",
+            value: Duration::from_secs(10 * 3600),
+            limit: defaults::gpio::MAX_SENSIBLE_HOLD,
+        };
+
+        assert_eq!(settings.gpio.extreme_durations().len(), 2);
+        assert!(settings.warnings().contains(&extreme));
+        assert_eq!(
+            extreme.to_string(),
+            "GPIO qualify_high_hold of 10h is above the sensible maximum of 1h; \
+            pass --allow-extreme if this is intended."
+        );
+
+        settings.gpio.extreme_durations = ExtremeDurations::Error;
+        assert!(!settings.warnings().contains(&extreme));
+        assert!(
+            settings
+                .sanity_check()
+                .unwrap_err()
+                .contains(&SanityError::ExtremeDuration {
+                    setting: "poll_interval",
+                    value: Duration::from_millis(1),
+                    limit: defaults::gpio::MIN_SENSIBLE_POLL_INTERVAL,
+                })
+        );
+
+        settings.allow_extreme = true;
+        assert!(
+            !settings
+                .sanity_check()
+                .err()
+                .unwrap_or_default()
+                .iter()
+                .any(|e| matches!(e, SanityError::ExtremeDuration { .. }))
+        );
+
+        settings.gpio.extreme_durations = ExtremeDurations::Warn;
+        assert!(!settings.warnings().contains(&extreme));
+    }
+
+    #[test]
+    fn test_pull_mode() {
+        let mut settings = Settings::default();
+        settings.gpio.pin_number = 2;
+        settings.gpio.pull = PullMode::Down;
+
+        let mut errors = Vec::new();
+        settings.gpio.sanity_check(&mut errors);
+        assert_eq!(
+            errors,
+            vec![SanityError::PullDownOnFixedPullUp { pin_number: 2 }]
+        );
+
+        settings.gpio.pull = PullMode::None;
+        assert_eq!(settings.warnings(), vec![SanityWarning::FloatingInput]);
+    }
+
+    #[test]
+    fn test_input_mode() {
+        use clap::Parser;
+
+        let default = Settings::default();
+        assert_eq!(
+            InputMode::of(default.gpio.baseline_level, default.gpio.pull),
+            Some(InputMode::HighPullup)
+        );
+        assert_eq!(InputMode::of(BaselineLevel::Auto, PullMode::Up), None);
+
+        // The granular settings override the preset.
+        let gpio_config: file_config::GpioConfig =
+            toml::from_str("input_mode = \"alarm-active-low-floating\"\npull = \"down\"").unwrap();
+        let mut gpio = GpioSettings::default();
+        gpio.apply_file(&gpio_config);
+        assert_eq!(gpio.baseline_level, BaselineLevel::High);
+        assert_eq!(gpio.pull, PullMode::Down);
+
+        let mut settings = Settings::default();
+        settings.apply_cli(&Cli::parse_from([
+            "pellx_monitor",
+            "--input-mode",
+            "alarm-active-high-pulldown",
+        ]));
+        assert_eq!(settings.gpio.baseline_level, BaselineLevel::Low);
+        assert_eq!(settings.gpio.pull, PullMode::Down);
+    }
+
+    #[test]
+    fn test_adc_sanity_check() {
+        let config: file_config::FileConfig = toml::from_str(
+            "[gpio]\ninput_source = \"adc\"\n\n[adc]\nchannel = 8\nthreshold = 10\nhysteresis = 20",
+        )
+        .unwrap();
+        let mut settings = Settings::default();
+        settings.apply_file_config(&config);
+        assert_eq!(settings.gpio.input_source, InputSource::Adc);
+
+        let errors = settings.sanity_check().unwrap_err();
+        assert!(errors.contains(&SanityError::AdcOutOfRange {
+            setting: "channel",
+            value: 8,
+            max: defaults::adc::MAX_CHANNEL.into(),
+        }));
+        assert!(errors.contains(&SanityError::AdcHysteresisAboveThreshold {
+            hysteresis: 20,
+            threshold: 10,
+        }));
+
+        // The ADC settings are ignored when reading a GPIO pin.
+        settings.gpio.input_source = InputSource::Gpio;
+        assert!(
+            settings
+                .sanity_check()
+                .err()
+                .unwrap_or_default()
+                .iter()
+                .all(|e| !matches!(e, SanityError::AdcOutOfRange { .. }))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_signal() {
+        let mut settings = Settings::default();
+        settings.signals.test = Signal::Hup;
+
+        let mut errors = Vec::new();
+        settings.signals.sanity_check(&mut errors);
+        assert_eq!(
+            errors,
+            vec![SanityError::DuplicateSignal {
+                signal: Signal::Hup
+            }]
+        );
+
+        settings.signals.reload = Signal::None;
+        settings.signals.dump_state = Signal::None;
+        errors.clear();
+        settings.signals.sanity_check(&mut errors);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_pin_name() {
+        let mut settings = Settings::default();
+        settings
+            .pin_names
+            .insert("24".to_string(), "boiler".to_string());
+
+        assert_eq!(settings.pin_name(24), "boiler");
+        assert_eq!(settings.pin_name(25), "GPIO25");
+
+        settings.gpio.pin_number = 24;
+        settings.gpio.pin_label = Some("Boiler overheat".to_string());
+        assert_eq!(settings.pin_name(24), "Boiler overheat");
+    }
+
+    #[test]
+    fn test_poll_interval_for() {
+        let mut gpio = GpioSettings::default();
+        assert_eq!(gpio.poll_interval_for(Level::High), gpio.poll_interval);
+
+        gpio.alarm_poll_interval = Some(Duration::from_millis(200));
+        assert_eq!(
+            gpio.poll_interval_for(Level::High),
+            Duration::from_millis(200)
+        );
+        assert_eq!(gpio.poll_interval_for(Level::Low), gpio.poll_interval);
+    }
+
+    #[test]
+    fn test_qualify_latency() {
+        let mut gpio = GpioSettings {
+            poll_interval: Duration::from_secs(1),
+            qualify_high_hold: Duration::from_secs(10),
+            qualify_low_hold: Duration::from_millis(2500),
+            ..GpioSettings::default()
+        };
+
+        assert_eq!(
+            gpio.qualify_latency(Level::High),
+            (Duration::from_secs(10), Duration::from_secs(11))
+        );
+        assert_eq!(
+            gpio.qualify_latency(Level::Low),
+            (Duration::from_secs(3), Duration::from_secs(4))
+        );
+
+        gpio.alarm_poll_interval = Some(Duration::from_millis(200));
+        assert_eq!(
+            gpio.qualify_latency(Level::High),
+            (Duration::from_secs(10), Duration::from_secs(11))
+        );
+        assert_eq!(
+            gpio.qualify_latency(Level::Low),
+            (Duration::from_secs(3), Duration::from_millis(3200))
+        );
+
+        gpio.qualify_mode = QualifyMode::MOfN;
+        gpio.qualify_m = 3;
+        gpio.qualify_n = 5;
+        gpio.alarm_poll_interval = None;
+        assert_eq!(
+            gpio.qualify_latency(Level::High),
+            (Duration::from_secs(2), Duration::from_secs(3))
+        );
+
+        gpio.qualify_mode = QualifyMode::Hold;
+        gpio.qualify_high_hold = Duration::ZERO;
+        assert_eq!(
+            gpio.qualify_latency(Level::High),
+            (Duration::ZERO, Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_m_of_n_replaces_holds() {
+        let mut gpio = GpioSettings::default();
+        let mut config = file_config::GpioConfig {
+            qualify_mode: Some(QualifyMode::MOfN),
+            ..file_config::GpioConfig::default()
+        };
+
+        gpio.apply_file(&config);
+        assert_eq!(gpio.m_of_n(), Some((3, 5)));
+
+        let mut vec = Vec::new();
+        gpio.sanity_check(&mut vec);
+        assert!(vec.is_empty());
+
+        config.qualify_high_hold = Some(Duration::from_secs(10));
+        config.qualify_m = Some(6);
+        gpio.apply_file(&config);
+
+        let mut vec = Vec::new();
+        gpio.sanity_check(&mut vec);
+        assert_eq!(
+            vec,
+            vec![
+                SanityError::HoldWithMOfN,
+                SanityError::InvalidMOfN { m: 6, n: 5 }
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_apply_cli_batsign_urls() {
+        use clap::Parser;
+
+        let file_urls = vec!["https://a.example/x".to_string()];
+
+        let mut settings = Settings::default();
+        settings.batsign.urls = file_urls.clone();
+        settings.apply_cli(&Cli::parse_from([
+            "pellx_monitor",
+            "-u",
+            "https://b.example/y",
+            "--batsign-url",
+            "https://a.example/x",
+        ]));
+        assert_eq!(
+            settings.batsign.urls,
+            vec!["https://a.example/x", "https://b.example/y"]
+        );
+
+        let mut settings = Settings::default();
+        settings.batsign.urls = file_urls;
+        settings.apply_cli(&Cli::parse_from([
+            "pellx_monitor",
+            "-u",
+            "https://b.example/y",
+            "--replace-urls",
+        ]));
+        assert_eq!(settings.batsign.urls, vec!["https://b.example/y"]);
+
+        assert!(Cli::try_parse_from(["pellx_monitor", "--replace-urls"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_webhook_gzip_urls() {
+        let mut settings = WebhookSettings {
+            enabled: true,
+            urls: vec!["https://a.example/x".to_string()],
+            gzip_urls: vec![" https://a.example/x ".to_string()],
+            ..WebhookSettings::default()
+        };
+        settings.trim_urls();
+        assert_eq!(
+            settings.gzip_min_bytes_for("https://a.example/x"),
+            Some(defaults::webhook::GZIP_MIN_BYTES)
+        );
+        assert_eq!(settings.gzip_min_bytes_for("https://b.example/y"), None);
+
+        let mut vec = Vec::new();
+        settings.sanity_check(&mut vec);
+        assert!(vec.is_empty());
+
+        settings.gzip_urls.push("https://b.example/y".to_string());
+        settings.sanity_check(&mut vec);
+        assert_eq!(
+            vec,
+            vec![SanityError::UnknownGzipUrl {
+                url: "https://b.example/y".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_severity_routing() {
+        let file_config: file_config::FileConfig = serde_json::from_str(
+            r#"{
+                "gpio": { "severity": "warning" },
+                "slack": { "severities": ["info", "warning"] },
+                "batsign": { "severities": ["critical"] }
+            }"#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        assert!(settings.routes_to(&settings.slack.severities));
+
+        settings.apply_file(&Some(file_config), None);
+        assert_eq!(settings.gpio.severity, Severity::Warning);
+        assert!(settings.routes_to(&settings.slack.severities));
+        assert!(!settings.routes_to(&settings.batsign.severities));
+        assert!(settings.routes_to(&settings.webhook.severities));
+        assert_eq!(
+            format_severities(&settings.slack.severities),
+            "info, warning"
+        );
+        assert_eq!(format_severities(&[]), "(none)");
+    }
+
+    #[test]
+    fn test_precedence() {
+        use clap::Parser;
+
+        let file_config: file_config::FileConfig = toml::from_str(
+            r#"
+            [gpio]
+            pin_number = 17
+            startup_delay = "30s"
+            startup_jitter = "5s"
+
+            [batsign]
+            urls = ["https://file.example/a"]
+
+            [notifications]
+            send_restored = false
+            "#,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        settings.apply_file(&Some(file_config), None);
+        #[cfg(feature = "notifications")]
+        settings.apply_env_vars(|name| {
+            (name == defaults::batsign::URLS_ENV).then(|| OsString::from("https://env.example/b"))
+        });
+        #[cfg(not(feature = "notifications"))]
+        settings.apply_env_vars(|_| None);
+        settings.apply_cli(&Cli::parse_from([
+            "pellx_monitor",
+            "--startup-jitter",
+            "10s",
+            "--dry-run",
+        ]));
+
+        let defaults = Settings::default();
+
+        // Set nowhere, so the default.
+        assert_eq!(settings.gpio.poll_interval, defaults.gpio.poll_interval);
+        assert_eq!(settings.gpio.alarm_poll_interval, None);
+        assert_eq!(settings.gpio.pull, defaults.gpio.pull);
+
+        // Set in the file only, with the command line saying nothing.
+        assert_eq!(settings.gpio.pin_number, 17);
+        assert_eq!(settings.gpio.startup_delay, Duration::from_secs(30));
+
+        // A flag that is absent doesn't turn back what the file turned off.
+        assert!(!settings.notifications.send_restored);
+
+        // Set in both, so the command line.
+        assert_eq!(settings.gpio.startup_jitter, Duration::from_secs(10));
+
+        // Set on the command line only.
+        assert!(settings.dry_run);
+
+        // URLs from the environment are added to those of the file.
+        #[cfg(feature = "notifications")]
+        assert_eq!(
+            settings.batsign.urls,
+            vec!["https://file.example/a", "https://env.example/b"]
+        );
+
+        // An empty file and environment leave the defaults be, for the
+        // command line to override.
+        let mut settings = Settings::default();
+        settings.apply_file(&None, None);
+        settings.apply_env_vars(|_| None);
+        settings.apply_cli(&Cli::parse_from(["pellx_monitor", "--startup-delay", "1m"]));
+        assert_eq!(settings.gpio.startup_delay, Duration::from_secs(60));
+        assert_eq!(settings.gpio.startup_jitter, defaults.gpio.startup_jitter);
+        assert_eq!(settings.batsign.urls, defaults.batsign.urls);
+        assert!(settings.notifications.send_restored);
+    }
+
+    #[test]
+    fn test_apply_file_profile() {
+        let file_config: file_config::FileConfig = serde_json::from_str(
+            r#"{
+                "gpio": { "pin_number": 17, "poll_interval": "5s" },
+                "profiles": { "testing": { "gpio": { "poll_interval": "1s" } } }
+            }"#,
+        )
+        .unwrap();
+        let file_config = Some(file_config);
+
+        let mut settings = Settings::default();
+        settings.apply_file(&file_config, None);
+        assert_eq!(settings.gpio.poll_interval, Duration::from_secs(5));
+        assert_eq!(settings.profile, None);
+
+        let mut settings = Settings::default();
+        settings.apply_file(&file_config, Some("testing"));
+        assert_eq!(settings.gpio.pin_number, 17);
+        assert_eq!(settings.gpio.poll_interval, Duration::from_secs(1));
+        assert_eq!(settings.profile.as_deref(), Some("testing"));
+    }
+
+    #[test]
+    fn test_sub_second_poll_interval_round_trips() {
+        let file_config: file_config::FileConfig =
+            serde_json::from_str(r#"{ "gpio": { "poll_interval": "250ms" } }"#).unwrap();
+
+        let mut settings = Settings::default();
+        settings.apply_file(&Some(file_config), None);
+        assert_eq!(settings.gpio.poll_interval, Duration::from_millis(250));
+        assert_eq!(
+            format_duration(settings.gpio.poll_interval, DurationFormat::Compact),
+            "250ms"
+        );
+        assert_eq!(
+            format_duration(settings.gpio.poll_interval, DurationFormat::Clock),
+            "0:00:00.250"
+        );
+
+        let saved = serde_json::to_string(&file_config::FileConfig::from(&settings)).unwrap();
+        let reloaded: file_config::FileConfig = serde_json::from_str(&saved).unwrap();
+        assert_eq!(
+            reloaded.gpio.poll_interval,
+            Some(Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn test_startup_wait() {
+        let mut gpio = GpioSettings::default();
+        assert_eq!(gpio.startup_wait(12345), Duration::ZERO);
+
+        gpio.startup_delay = Duration::from_secs(30);
+        assert_eq!(gpio.startup_wait(12345), Duration::from_secs(30));
+
+        gpio.startup_jitter = Duration::from_secs(10);
+        assert_eq!(gpio.startup_wait(0), Duration::from_secs(30));
+        assert_eq!(gpio.startup_wait(10_000), Duration::from_secs(40));
+        assert_eq!(gpio.startup_wait(10_001), Duration::from_secs(30));
+        assert!(gpio.startup_wait(u64::MAX) <= Duration::from_secs(40));
+    }
+
+    #[test]
+    fn test_sanity_check_no_backend_enabled() {
+        let mut settings = Settings::default();
+        settings.slack.enabled = false;
+        settings.batsign.enabled = false;
+
+        assert_eq!(
+            settings.sanity_check(),
+            Err(vec![SanityError::NoBackendEnabled])
+        );
+
+        settings.pipe.enabled = true;
+        assert_eq!(
+            settings.sanity_check(),
+            Err(vec![SanityError::MissingPipeCommand])
+        );
+
+        settings.pipe.command = String::from("mail -s alarm root");
+        assert_eq!(settings.sanity_check(), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_no_config() {
+        let mut settings = Settings::default();
+        settings.apply_no_config();
+        assert!(!settings.slack.enabled);
+        assert!(!settings.history.enabled);
+        assert!(!settings.status.enabled);
+        assert_eq!(
+            settings.sanity_check().unwrap_err(),
+            vec![SanityError::MissingBatsignUrl]
+        );
+
+        settings.batsign.extend_urls(split_url_list(
+            " https://a.example/x,https://b.example/y\nhttps://a.example/x ",
+        ));
+        assert_eq!(
+            settings.batsign.urls,
+            vec!["https://a.example/x", "https://b.example/y"]
+        );
+        assert_eq!(settings.sanity_check(), Ok(()));
+    }
+
     #[test]
     fn test_trim_vec_of_strings() {
         let input = vec![
@@ -0,0 +1,208 @@
+//! Interactive first-run setup.
+//!
+//! `--setup` asks on the terminal for the few settings a typical installation
+//! needs: the pin to monitor, where to send notifications, and how long the
+//! pin must hold its level before an alarm or restore qualifies. The answers
+//! go through the same sanity check as a configuration file, and are then
+//! written the same way `--save` writes them.
+
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+
+use crate::duration::{DurationFormat, format_duration};
+use crate::settings::Settings;
+
+/// Asks the setup questions, updating the passed settings with the answers.
+/// The questions are asked again for as long as the answers don't pass the
+/// sanity check and the user wants to try again.
+///
+/// Returns whether the configuration should be written, which is the case
+/// once the answers are valid and, if a configuration file already exists,
+/// overwriting it has been confirmed. End of input aborts the setup.
+pub fn run(settings: &mut Settings, input: &mut impl BufRead) -> bool {
+    println!(
+        "Press Enter to keep the value in brackets, or Ctrl+D to abort without writing anything."
+    );
+    println!();
+
+    loop {
+        if ask_settings(settings, input).is_none() {
+            println!();
+            return false;
+        }
+
+        settings.clean_up();
+
+        let Err(vec) = settings.sanity_check() else {
+            break;
+        };
+
+        eprintln!("[!] Configuration has errors:");
+
+        for error in vec {
+            eprintln!("  * {error}");
+        }
+
+        if !confirm(input, "Go through the questions again?") {
+            return false;
+        }
+
+        println!();
+    }
+
+    let path = &settings.paths.config_file;
+
+    if path.exists() {
+        return confirm(
+            input,
+            &format!("{} already exists. Overwrite it?", path.display()),
+        );
+    }
+
+    true
+}
+
+/// Asks the individual questions. Returns `None` at end of input.
+fn ask_settings(settings: &mut Settings, input: &mut impl BufRead) -> Option<()> {
+    settings.gpio.pin_number = ask_parsed(
+        input,
+        "GPIO pin number to monitor",
+        &settings.gpio.pin_number.to_string(),
+        str::parse::<u8>,
+    )?;
+
+    #[cfg(feature = "notifications")]
+    {
+        settings.batsign.urls = ask_urls(input, "Batsign URL(s)", &settings.batsign.urls)?;
+        settings.batsign.enabled = !settings.batsign.urls.is_empty();
+
+        settings.slack.urls = ask_urls(input, "Slack webhook URL(s)", &settings.slack.urls)?;
+        settings.slack.enabled = !settings.slack.urls.is_empty();
+    }
+
+    settings.gpio.qualify_high_hold = ask_parsed(
+        input,
+        "How long the pin must read HIGH before alarming",
+        &format_duration(settings.gpio.qualify_high_hold, DurationFormat::Compact),
+        humantime::parse_duration,
+    )?;
+
+    settings.gpio.qualify_low_hold = ask_parsed(
+        input,
+        "How long the pin must read LOW before it counts as restored",
+        &format_duration(settings.gpio.qualify_low_hold, DurationFormat::Compact),
+        humantime::parse_duration,
+    )?;
+
+    Some(())
+}
+
+/// Asks for a list of URLs separated by whitespace or commas, where `none`
+/// stands for no URLs at all. Returns `None` at end of input.
+#[cfg(feature = "notifications")]
+fn ask_urls(input: &mut impl BufRead, what: &str, urls: &[String]) -> Option<Vec<String>> {
+    let default = if urls.is_empty() {
+        "none".to_string()
+    } else {
+        urls.join(" ")
+    };
+
+    let answer = ask(input, &format!("{what}, or \"none\""), &default)?;
+
+    if answer.eq_ignore_ascii_case("none") {
+        Some(Vec::new())
+    } else {
+        Some(crate::settings::split_url_list(&answer))
+    }
+}
+
+/// Asks the passed question until the answer parses. Returns `None` at end
+/// of input.
+fn ask_parsed<T, E: Display>(
+    input: &mut impl BufRead,
+    question: &str,
+    default: &str,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> Option<T> {
+    loop {
+        let answer = ask(input, question, default)?;
+
+        match parse(&answer) {
+            Ok(value) => return Some(value),
+            Err(e) => eprintln!("[!] Invalid answer \"{answer}\": {e}"),
+        }
+    }
+}
+
+/// Asks the passed question with the default shown in brackets, and returns
+/// the trimmed answer, or the default if it is empty. Returns `None` at end
+/// of input.
+fn ask(input: &mut impl BufRead, question: &str, default: &str) -> Option<String> {
+    print!("{question} [{default}]: ");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+
+    match input.read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) if answer.trim().is_empty() => Some(default.to_string()),
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
+
+/// Asks the passed yes/no question, returning whether it was answered yes.
+/// Anything else, including end of input, counts as no.
+pub fn confirm(input: &mut impl BufRead, question: &str) -> bool {
+    print!("{question} [y/N] ");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+
+    if input.read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    #[cfg(feature = "notifications")]
+    fn test_run() {
+        let mut settings = Settings::default();
+        let mut input = Cursor::new("x\n17\nhttps://batsign.me/at/a/b\nnone\nsoon\n2m\n\n");
+        assert!(run(&mut settings, &mut input));
+        assert_eq!(settings.gpio.pin_number, 17);
+        assert_eq!(settings.batsign.urls, vec!["https://batsign.me/at/a/b"]);
+        assert!(settings.batsign.enabled);
+        assert!(!settings.slack.enabled);
+        assert_eq!(
+            settings.gpio.qualify_high_hold,
+            std::time::Duration::from_secs(120)
+        );
+        assert_eq!(
+            settings.gpio.qualify_low_hold,
+            Settings::default().gpio.qualify_low_hold
+        );
+
+        // No notifier left, and no second try.
+        let mut input = Cursor::new("\nnone\n\n\n\nn\n");
+        assert!(!run(&mut settings, &mut input));
+
+        // Aborted halfway through.
+        let mut input = Cursor::new("17\n");
+        assert!(!run(&mut Settings::default(), &mut input));
+    }
+
+    #[test]
+    fn test_confirm() {
+        assert!(confirm(&mut Cursor::new("yes\n"), "?"));
+        assert!(confirm(&mut Cursor::new(" Y \n"), "?"));
+        assert!(!confirm(&mut Cursor::new("\n"), "?"));
+        assert!(!confirm(&mut Cursor::new(""), "?"));
+    }
+}
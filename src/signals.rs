@@ -0,0 +1,73 @@
+//! Handling of SIGINT and SIGTERM, so the monitor loop can exit cleanly, and
+//! of the signals that can be mapped to runtime actions in the `[signals]`
+//! section: SIGHUP, SIGUSR1 and SIGUSR2.
+//!
+//! The handlers only set a flag, which the monitor loop checks once per poll.
+//! Exiting through the normal return path lets destructors run, which among
+//! other things removes the PID lockfile.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::settings::Signal;
+
+/// Set when a shutdown signal has been received.
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Set when SIGHUP has been received, and not yet handled.
+static HUP_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+/// Set when SIGUSR1 has been received, and not yet handled.
+static USR1_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+/// Set when SIGUSR2 has been received, and not yet handled.
+static USR2_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+/// Signal handler, recording that a shutdown was requested.
+extern "C" fn handle_shutdown_signal(_signal: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Signal handler, recording which of the mappable signals was received.
+extern "C" fn handle_action_signal(signal: libc::c_int) {
+    match signal {
+        libc::SIGHUP => HUP_RECEIVED.store(true, Ordering::SeqCst),
+        libc::SIGUSR1 => USR1_RECEIVED.store(true, Ordering::SeqCst),
+        libc::SIGUSR2 => USR2_RECEIVED.store(true, Ordering::SeqCst),
+        _ => {}
+    }
+}
+
+/// Installs handlers for SIGINT and SIGTERM, and for SIGHUP, SIGUSR1 and
+/// SIGUSR2 whether or not they are mapped to an action, so that none of
+/// them kill the process.
+pub fn install_handlers() {
+    let shutdown = handle_shutdown_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let action = handle_action_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+    // SAFETY: The handlers only store to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, shutdown);
+        libc::signal(libc::SIGTERM, shutdown);
+        libc::signal(libc::SIGHUP, action);
+        libc::signal(libc::SIGUSR1, action);
+        libc::signal(libc::SIGUSR2, action);
+    }
+}
+
+/// Returns whether a shutdown signal has been received.
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Returns whether the passed signal has been received since the last call,
+/// clearing it. An unmapped action never is.
+pub fn take_request(signal: Signal) -> bool {
+    let received = match signal {
+        Signal::Hup => &HUP_RECEIVED,
+        Signal::Usr1 => &USR1_RECEIVED,
+        Signal::Usr2 => &USR2_RECEIVED,
+        Signal::None => return false,
+    };
+
+    received.swap(false, Ordering::SeqCst)
+}
@@ -0,0 +1,425 @@
+//! Status socket, for querying a running monitor.
+//!
+//! The monitor listens on a Unix domain socket, by default `status.sock` in
+//! the configuration directory. A client connects, writes a single request
+//! line, and reads back a single line of JSON:
+//!
+//! * `status`: a snapshot of the current state of the monitor
+//! * `history`: the most recent qualified alarms and restores, oldest first,
+//!   as an array of the same records that are written to the history file
+//! * `maintenance [<duration>]`: silences notifications for the passed
+//!   duration, or the configured `maintenance_duration`
+//! * `maintenance off`: ends maintenance mode early
+//!
+//! Maintenance requests are left for the monitor loop to pick up on its next
+//! poll, and answered with `{"ok": ...}` once queued.
+//!
+//! The recent records are kept in memory, capped at a configured number,
+//! whether or not the history file is enabled.
+//!
+//! For dashboards that can't talk to a Unix socket, the alarm state can also
+//! be served over plain HTTP with `--status-http`, as the JSON response to a
+//! `GET /status`. It is read-only and unauthenticated.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::history::Entry;
+
+/// How long to wait for a client to send its request before giving up on it.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Snapshot of the state of the running monitor.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Snapshot {
+    /// The GPIO pin number being monitored.
+    pub pin_number: u8,
+
+    /// Friendly name of the GPIO pin.
+    pub pin_name: String,
+
+    /// Local wall-clock time the monitor was started.
+    pub started: String,
+
+    /// Level of the last successful reading, if any.
+    pub level: Option<String>,
+
+    /// Level that last qualified, if any.
+    pub qualified_level: Option<String>,
+
+    /// Whether there is an alarm.
+    pub alarm: bool,
+
+    /// Local wall-clock time the current alarm, or the nominal state, began.
+    pub since: String,
+
+    /// Number of times the pin has been read.
+    pub polls: u64,
+
+    /// Number of failed pin reads.
+    pub read_errors: u64,
+
+    /// Local wall-clock time maintenance mode ends, if notifications are silenced.
+    pub maintenance_until: Option<String>,
+}
+
+/// A request to enter or leave maintenance mode, made through the socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceRequest {
+    /// Silence notifications for the passed duration, or the configured one.
+    Start(Option<Duration>),
+
+    /// End maintenance mode.
+    End,
+}
+
+/// State shared between the monitor loop and the status socket.
+pub struct Status {
+    /// Snapshot of the current state, kept up to date by the monitor loop.
+    pub snapshot: Snapshot,
+
+    /// The most recent records, oldest first.
+    recent: VecDeque<Entry>,
+
+    /// Maximum number of records to keep in `recent`.
+    capacity: usize,
+
+    /// Maintenance request not yet picked up by the monitor loop.
+    pub maintenance_request: Option<MaintenanceRequest>,
+}
+
+impl Status {
+    /// Creates a new `Status` with the passed snapshot, keeping at most
+    /// `capacity` recent records.
+    pub fn new(snapshot: Snapshot, capacity: usize) -> Self {
+        Self {
+            snapshot,
+            recent: VecDeque::with_capacity(capacity),
+            capacity,
+            maintenance_request: None,
+        }
+    }
+
+    /// Adds a record, dropping the oldest one if there are too many.
+    pub fn push(&mut self, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back(entry);
+    }
+
+    /// Returns the last `count` recent records, oldest first.
+    pub fn tail(&self, count: usize) -> impl Iterator<Item = &Entry> {
+        self.recent
+            .iter()
+            .skip(self.recent.len().saturating_sub(count))
+    }
+
+    /// Returns the response to the passed request, as a line of JSON.
+    fn respond(&mut self, request: &str) -> String {
+        let response = match request.split_once(' ').unwrap_or((request, "")) {
+            ("status", "") => serde_json::to_value(&self.snapshot),
+            ("history", "") => serde_json::to_value(&self.recent),
+            ("maintenance", argument) => Ok(self.request_maintenance(argument.trim())),
+            _ => Ok(serde_json::json!({ "error": format!("unknown request \"{request}\"") })),
+        };
+
+        response.map_or_else(
+            |e| serde_json::json!({ "error": e.to_string() }).to_string(),
+            |value| value.to_string(),
+        )
+    }
+
+    /// Queues a maintenance request for the monitor loop, returning the response.
+    fn request_maintenance(&mut self, argument: &str) -> serde_json::Value {
+        let request = match argument {
+            "off" => MaintenanceRequest::End,
+            "" => MaintenanceRequest::Start(None),
+            duration => match humantime::parse_duration(duration) {
+                Ok(duration) if !duration.is_zero() => MaintenanceRequest::Start(Some(duration)),
+                Ok(_) => return serde_json::json!({ "error": "duration must be non-zero" }),
+                Err(e) => return serde_json::json!({ "error": e.to_string() }),
+            },
+        };
+
+        self.maintenance_request = Some(request);
+
+        match request {
+            MaintenanceRequest::Start(_) => serde_json::json!({ "ok": "entering maintenance" }),
+            MaintenanceRequest::End => serde_json::json!({ "ok": "leaving maintenance" }),
+        }
+    }
+}
+
+/// Status shared between threads.
+pub type SharedStatus = Arc<Mutex<Status>>;
+
+/// A bound status socket, removed again on drop.
+#[derive(Debug)]
+pub struct StatusSocket {
+    /// Path to the socket file.
+    path: PathBuf,
+}
+
+impl Drop for StatusSocket {
+    /// Removes the socket file.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Binds the status socket at the passed path and serves requests from a
+/// background thread.
+///
+/// A socket file left behind by an instance that is no longer running is
+/// replaced, but one that is still being listened on is left alone.
+pub fn serve(path: &Path, status: SharedStatus) -> io::Result<StatusSocket> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::from(io::ErrorKind::AddrInUse));
+        }
+
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = handle_client(stream, &status);
+        }
+    });
+
+    Ok(StatusSocket {
+        path: path.to_path_buf(),
+    })
+}
+
+/// Reads a request line from a client and writes back the response.
+fn handle_client(stream: UnixStream, status: &SharedStatus) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    let response = match status.lock() {
+        Ok(mut status) => status.respond(request.trim()),
+        Err(_) => serde_json::json!({ "error": "status unavailable" }).to_string(),
+    };
+
+    let mut stream = stream;
+    writeln!(stream, "{response}")
+}
+
+/// Serves `GET /status` over HTTP at the passed address from a background
+/// thread, returning the address actually bound.
+pub fn serve_http(addr: SocketAddr, status: SharedStatus) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = handle_http_client(stream, &status);
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// Reads an HTTP request from a client and writes back the response.
+///
+/// Only `GET /status` is served, with the alarm state as JSON. Headers are
+/// read and ignored, and the connection is closed after the response.
+fn handle_http_client(stream: TcpStream, status: &SharedStatus) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+
+    let mut header = String::new();
+
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+
+    let (code, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/status")) => match status.lock() {
+            Ok(status) => (
+                "200 OK",
+                serde_json::json!({
+                    "alarm": status.snapshot.alarm,
+                    "pin": status.snapshot.pin_number,
+                    "since": status.snapshot.since,
+                }),
+            ),
+            Err(_) => (
+                "503 Service Unavailable",
+                serde_json::json!({ "error": "status unavailable" }),
+            ),
+        },
+        (Some("GET"), _) => ("404 Not Found", serde_json::json!({ "error": "not found" })),
+        _ => (
+            "405 Method Not Allowed",
+            serde_json::json!({ "error": "method not allowed" }),
+        ),
+    };
+
+    let body = body.to_string();
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {code}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Sends a request to the status socket of a running monitor, returning the response.
+pub fn query(path: &Path, request: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "{request}")?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(i: u64) -> Entry {
+        Entry {
+            timestamp: format!("2026-01-01 00:00:{i:02}"),
+            event: "alarm".to_string(),
+            pin_number: 24,
+            pin_name: "boiler".to_string(),
+            since_secs: i,
+            outcomes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_serve_and_query() {
+        let path = std::env::temp_dir().join(format!(
+            "pellx_monitor_status_test_{}.sock",
+            std::process::id()
+        ));
+
+        let mut status = Status::new(
+            Snapshot {
+                pin_number: 24,
+                pin_name: "boiler".to_string(),
+                ..Snapshot::default()
+            },
+            2,
+        );
+
+        for i in 0..3 {
+            status.push(entry(i));
+        }
+
+        let socket = serve(&path, Arc::new(Mutex::new(status))).unwrap();
+        assert!(
+            serve(
+                &path,
+                Arc::new(Mutex::new(Status::new(Snapshot::default(), 0)))
+            )
+            .is_err()
+        );
+
+        let snapshot: serde_json::Value =
+            serde_json::from_str(&query(&path, "status").unwrap()).unwrap();
+        assert_eq!(snapshot["pin_name"], "boiler");
+
+        let recent: Vec<Entry> = serde_json::from_str(&query(&path, "history").unwrap()).unwrap();
+        assert_eq!(recent, vec![entry(1), entry(2)]);
+
+        let error: serde_json::Value =
+            serde_json::from_str(&query(&path, "blork").unwrap()).unwrap();
+        assert!(error["error"].is_string());
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_maintenance_request() {
+        let mut status = Status::new(Snapshot::default(), 0);
+
+        assert!(status.respond("maintenance 2h").contains("\"ok\""));
+        assert_eq!(
+            status.maintenance_request.take(),
+            Some(MaintenanceRequest::Start(Some(Duration::from_secs(7200))))
+        );
+
+        status.respond("maintenance");
+        assert_eq!(
+            status.maintenance_request.take(),
+            Some(MaintenanceRequest::Start(None))
+        );
+
+        status.respond("maintenance off");
+        assert_eq!(
+            status.maintenance_request.take(),
+            Some(MaintenanceRequest::End)
+        );
+
+        assert!(status.respond("maintenance blork").contains("\"error\""));
+        assert!(status.respond("status please").contains("\"error\""));
+        assert_eq!(status.maintenance_request, None);
+    }
+
+    #[test]
+    fn test_serve_http() {
+        let status = Status::new(
+            Snapshot {
+                pin_number: 24,
+                alarm: true,
+                since: "2026-01-01 00:00:00".to_string(),
+                ..Snapshot::default()
+            },
+            0,
+        );
+
+        let addr =
+            serve_http("127.0.0.1:0".parse().unwrap(), Arc::new(Mutex::new(status))).unwrap();
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/status");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let body: serde_json::Value =
+            serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "alarm": true, "pin": 24, "since": "2026-01-01 00:00:00" })
+        );
+
+        assert!(get("/metrics").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
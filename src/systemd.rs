@@ -0,0 +1,47 @@
+//! Readiness notification to systemd, for `Type=notify` services.
+//!
+//! When started by systemd with `NotifyAccess` set, `$NOTIFY_SOCKET` names a
+//! datagram socket to which `READY=1` is sent once the monitor is about to
+//! start polling. Outside of systemd the variable is unset and nothing is sent.
+
+use std::env;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+
+/// Tells systemd the service is ready, if it is listening.
+///
+/// Returns `Ok(false)` if `$NOTIFY_SOCKET` is not set.
+pub fn notify_ready() -> io::Result<bool> {
+    let Some(socket) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+
+    let datagram = UnixDatagram::unbound()?;
+    let socket = socket.to_string_lossy();
+
+    // A leading `@` denotes a socket in the abstract namespace.
+    if let Some(name) = socket.strip_prefix('@') {
+        send_abstract(&datagram, name)?;
+    } else {
+        datagram.send_to(b"READY=1", socket.as_ref())?;
+    }
+
+    Ok(true)
+}
+
+/// Sends `READY=1` to a socket in the abstract namespace.
+#[cfg(target_os = "linux")]
+fn send_abstract(datagram: &UnixDatagram, name: &str) -> io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    let addr = SocketAddr::from_abstract_name(name)?;
+    datagram.send_to_addr(b"READY=1", &addr)?;
+    Ok(())
+}
+
+/// Abstract sockets only exist on Linux.
+#[cfg(not(target_os = "linux"))]
+fn send_abstract(_datagram: &UnixDatagram, _name: &str) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
@@ -0,0 +1,162 @@
+//! Substitution of placeholders in message templates.
+//!
+//! Templates may contain the following placeholders, which are replaced with
+//! details of the notification being sent:
+//!
+//! * `{pin_number}`: the GPIO pin number being monitored
+//! * `{pin_name}`: the friendly name of the pin, or `GPIO<n>` if it has none
+//! * `{pin_label}`: the same as `{pin_name}`
+//! * `{level}`: `HIGH` or `LOW`
+//! * `{state}`: `alarm` or `restored`
+//! * `{severity}`: how urgent alarms of the pin are: `info`, `warning` or `critical`
+//! * `{config_summary}`: a summary of the key settings, if
+//!   `include_config_in_notification` is enabled; otherwise nothing
+//! * `{since}`: how long the pin has been at its current level, e.g. `1m 10s`
+//!   or `0:01:10`, depending on the configured duration format. It counts
+//!   from when the pin was first read at the level to the time the message
+//!   is rendered for, which for a retry or reminder is that of the retry or
+//!   reminder, not of the first attempt
+//!
+//! Unknown placeholders are left untouched.
+//!
+//! If `include_log_tail` is set, the most recent alarms and restores are
+//! additionally appended to alarm messages, below a separator.
+
+use std::time::Duration;
+
+use crate::defaults;
+use crate::duration::format_duration;
+use crate::gpio::Level;
+use crate::notify::Context;
+
+/// Formats a message by substituting the placeholders in the passed template
+/// with values from the notification context.
+///
+/// `{since}` is computed from the instants in the context alone, `since` to
+/// `now`, so that the same context always renders the same message, however
+/// late it is sent.
+pub fn format_message(template: &str, ctx: &Context) -> String {
+    let since = ctx.now.saturating_duration_since(ctx.since);
+
+    let (level, state) = match ctx.level {
+        Level::High => ("HIGH", "alarm"),
+        Level::Low => ("LOW", "restored"),
+    };
+
+    template
+        .replace("{pin_number}", &ctx.pin_number.to_string())
+        .replace("{pin_name}", &ctx.pin_name)
+        .replace("{pin_label}", &ctx.pin_name)
+        .replace("{level}", level)
+        .replace("{state}", state)
+        .replace("{severity}", &ctx.severity.to_string())
+        .replace("{config_summary}", &ctx.config_summary)
+        .replace(
+            "{since}",
+            &format_duration(Duration::from_secs(since.as_secs()), ctx.duration_format),
+        )
+}
+
+/// Appends the recent events in the passed log tail to a message body, below
+/// a separator. The body is returned as is if the log tail is empty.
+pub fn append_log_tail(body: String, log_tail: &str) -> String {
+    if log_tail.is_empty() {
+        return body;
+    }
+
+    format!(
+        "{body}\n\n{}\n{log_tail}",
+        defaults::notifications::LOG_TAIL_SEPARATOR
+    )
+}
+
+/// Marker appended to messages that have been truncated.
+const ELLIPSIS: &str = "…";
+
+/// Truncates a message to at most `max_bytes` bytes, ending it with an ellipsis.
+///
+/// The message is cut at a character boundary. Returns `None` if the message
+/// already fits.
+pub fn truncate_message(message: &str, max_bytes: usize) -> Option<String> {
+    if message.len() <= max_bytes {
+        return None;
+    }
+
+    let mut end = max_bytes.saturating_sub(ELLIPSIS.len());
+
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut truncated = message[..end].to_string();
+
+    if truncated.len() + ELLIPSIS.len() <= max_bytes {
+        truncated.push_str(ELLIPSIS);
+    }
+
+    Some(truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_format_message() {
+        let now = Instant::now();
+        let ctx = Context {
+            since: now - Duration::from_millis(70_500),
+            config_summary: "Pin number 24".to_string(),
+            ..Context::for_test(Level::High, now)
+        };
+
+        assert_eq!(
+            format_message(
+                "{pin_name} (GPIO{pin_number}) {level} ({state}) for {since}",
+                &ctx
+            ),
+            "boiler (GPIO24) HIGH (alarm) for 1m 10s"
+        );
+
+        assert_eq!(format_message("{pin_label}", &ctx), "boiler");
+
+        // Rendered later for a retry, it counts from the same trigger.
+        let retry = Context {
+            now: now + Duration::from_secs(300),
+            ..ctx.clone()
+        };
+        assert_eq!(format_message("{since}", &retry), "6m 10s");
+
+        // A clock that is behind the trigger reports nothing rather than panicking.
+        let early = Context {
+            now: ctx.since - Duration::from_secs(1),
+            ..ctx.clone()
+        };
+        assert_eq!(format_message("{since}", &early), "0s");
+        assert_eq!(format_message("[{severity}]", &ctx), "[critical]");
+        assert_eq!(format_message("{unknown} {}", &ctx), "{unknown} {}");
+        assert_eq!(
+            format_message("Down.\n{config_summary}", &ctx),
+            "Down.\nPin number 24"
+        );
+    }
+
+    #[test]
+    fn test_append_log_tail() {
+        assert_eq!(append_log_tail("Down.".to_string(), ""), "Down.");
+        assert_eq!(
+            append_log_tail("Down.".to_string(), "first\nsecond"),
+            "Down.\n\n--- recent events ---\nfirst\nsecond"
+        );
+    }
+
+    #[test]
+    fn test_truncate_message() {
+        assert_eq!(truncate_message("short", 8), None);
+        assert_eq!(truncate_message("0123456789", 8).unwrap(), "01234…");
+        // Don't split the multi-byte `ö`
+        assert_eq!(truncate_message("aaaaöbbbbb", 8).unwrap(), "aaaa…");
+    }
+}
@@ -0,0 +1,92 @@
+//! Watching the configuration and resource files for changes, for `--watch-config`.
+//!
+//! Files are watched by comparing their modification times and sizes once per
+//! poll of the pin, which needs no platform support and is cheap for the
+//! handful of files involved. A change is only reported once the files have
+//! stopped changing for a while, so that a file still being rewritten isn't
+//! read half-done.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Watches a set of files for changes.
+pub struct ConfigWatcher {
+    /// The files being watched.
+    paths: Vec<PathBuf>,
+
+    /// Modification time and size of each file when last checked, or `None`
+    /// if it couldn't be read.
+    stamps: Vec<Option<(SystemTime, u64)>>,
+
+    /// When a change was last seen, if it has yet to be reported.
+    changed_at: Option<Instant>,
+
+    /// How long the files must stay unchanged before a change is reported.
+    debounce: Duration,
+}
+
+impl ConfigWatcher {
+    /// Creates a new `ConfigWatcher` for the passed files, as they are now.
+    pub fn new(paths: Vec<PathBuf>, debounce: Duration) -> Self {
+        let stamps = paths.iter().map(|path| stamp(path)).collect();
+
+        Self {
+            paths,
+            stamps,
+            changed_at: None,
+            debounce,
+        }
+    }
+
+    /// Checks the files for changes, returning `true` once a change has
+    /// settled for the debounce time. Each change is reported once.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        let stamps: Vec<_> = self.paths.iter().map(|path| stamp(path)).collect();
+
+        if stamps != self.stamps {
+            self.stamps = stamps;
+            self.changed_at = Some(now);
+            return false;
+        }
+
+        match self.changed_at {
+            Some(t) if now.saturating_duration_since(t) >= self.debounce => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the modification time and size of the passed file, or `None` if
+/// it can't be read.
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_is_reported_once_settled() {
+        let path = std::env::temp_dir().join(format!("pellx_watch_{}.toml", std::process::id()));
+        fs::write(&path, "poll_interval = \"1s\"\n").unwrap();
+
+        let debounce = Duration::from_secs(2);
+        let mut watcher = ConfigWatcher::new(vec![path.clone()], debounce);
+        let t0 = Instant::now();
+        assert!(!watcher.poll(t0));
+
+        fs::write(&path, "poll_interval = \"500ms\"\n").unwrap();
+        assert!(!watcher.poll(t0));
+        assert!(!watcher.poll(t0 + debounce - Duration::from_millis(1)));
+        assert!(watcher.poll(t0 + debounce));
+        assert!(!watcher.poll(t0 + debounce * 2));
+
+        fs::remove_file(&path).unwrap();
+    }
+}